@@ -0,0 +1,2 @@
+-- makudoku engine version that generated or last verified the puzzle
+ALTER TABLE puzzles ADD COLUMN engine_version TEXT;
@@ -0,0 +1,2 @@
+-- Unknown top-level puzzle_json fields, quarantined at write time (JSON object)
+ALTER TABLE puzzles ADD COLUMN extra_json TEXT;
@@ -0,0 +1,6 @@
+-- Weeks for which the admin digest has been sent
+CREATE TABLE IF NOT EXISTS digest_log (
+  week_start TEXT PRIMARY KEY,
+  sent_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
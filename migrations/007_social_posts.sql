@@ -0,0 +1,9 @@
+-- Announcements of published puzzles on social networks
+CREATE TABLE IF NOT EXISTS social_posts (
+  date_utc TEXT NOT NULL,
+  network TEXT NOT NULL,
+  post_url TEXT,
+  posted_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  PRIMARY KEY (date_utc, network)
+);
@@ -0,0 +1,2 @@
+-- Small PNG of the puzzle SVG for archive listings, rendered on first request
+ALTER TABLE puzzles ADD COLUMN thumbnail_png BLOB;
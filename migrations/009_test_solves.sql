@@ -0,0 +1,12 @@
+-- Admin test solves of drafts, one per tester and puzzle
+CREATE TABLE IF NOT EXISTS test_solves (
+  date_utc TEXT NOT NULL,
+  tester TEXT NOT NULL,
+  grid TEXT NOT NULL,
+  notes TEXT,
+  -- NULL while in progress
+  verdict TEXT CHECK (verdict IN ('pass', 'fail')),
+  updated_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  PRIMARY KEY (date_utc, tester)
+);
@@ -0,0 +1,9 @@
+-- Memorable names for special puzzles, served at /p/{slug}
+CREATE TABLE IF NOT EXISTS slugs (
+  slug TEXT PRIMARY KEY,
+  date_utc TEXT NOT NULL REFERENCES puzzles(date_utc) ON DELETE CASCADE,
+  created_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_slugs_date ON slugs(date_utc);
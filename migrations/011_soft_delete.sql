@@ -0,0 +1,2 @@
+-- Deleted puzzles are kept until restored or overwritten; NULL means live
+ALTER TABLE puzzles ADD COLUMN deleted_at_utc TEXT;
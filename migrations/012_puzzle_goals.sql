@@ -0,0 +1,10 @@
+-- Community goal for a special puzzle, e.g. 1000 solves, shown with its stats
+CREATE TABLE IF NOT EXISTS puzzle_goals (
+  date_utc TEXT PRIMARY KEY REFERENCES puzzles(date_utc) ON DELETE CASCADE,
+  metric TEXT NOT NULL DEFAULT 'solves'
+    CHECK (metric IN ('views', 'checks', 'solves')),
+  target INTEGER NOT NULL CHECK (target > 0),
+  label TEXT,
+  updated_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
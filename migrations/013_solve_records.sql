@@ -0,0 +1,9 @@
+-- One row per player session that completed a puzzle; puzzle_stats.solves
+-- only moves when a row is added here
+CREATE TABLE IF NOT EXISTS solve_records (
+  date_utc TEXT NOT NULL,
+  session TEXT NOT NULL,
+  solved_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  PRIMARY KEY (date_utc, session)
+);
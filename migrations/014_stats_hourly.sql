@@ -0,0 +1,10 @@
+-- Views, checks and solves per puzzle per UTC hour, for activity timelines
+CREATE TABLE IF NOT EXISTS puzzle_stats_hourly (
+  date_utc TEXT NOT NULL,
+  -- Start of the hour, e.g. 2025-01-15T08:00:00Z
+  hour_utc TEXT NOT NULL,
+  views INTEGER NOT NULL DEFAULT 0,
+  checks INTEGER NOT NULL DEFAULT 0,
+  solves INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (date_utc, hour_utc)
+);
@@ -0,0 +1,18 @@
+-- Background work queued through the admin API, e.g. puzzle generation.
+-- Rows outlive restarts; jobs left running by a crash are queued again.
+CREATE TABLE IF NOT EXISTS jobs (
+  id TEXT PRIMARY KEY,
+  kind TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'queued'
+    CHECK (status IN ('queued', 'running', 'done', 'failed')),
+  request_json TEXT NOT NULL,
+  result_json TEXT,
+  error TEXT,
+  created_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  started_at_utc TEXT,
+  finished_at_utc TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_status_created
+  ON jobs(status, created_at_utc);
@@ -0,0 +1,2 @@
+-- When cancelling a job was asked for; cancelled jobs end as 'failed'
+ALTER TABLE jobs ADD COLUMN cancelled_at_utc TEXT;
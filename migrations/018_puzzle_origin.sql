@@ -0,0 +1,3 @@
+-- Where a puzzle imported from another instance came from (JSON); NULL for
+-- puzzles made here
+ALTER TABLE puzzles ADD COLUMN origin_json TEXT;
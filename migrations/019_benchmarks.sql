@@ -0,0 +1,13 @@
+-- Generation timings from POST /api/admin/benchmarks/run, one row per run;
+-- cases_json holds the per variant set and clue target medians
+CREATE TABLE IF NOT EXISTS benchmark_runs (
+  id TEXT PRIMARY KEY,
+  engine_version TEXT NOT NULL,
+  created_at_utc TEXT NOT NULL
+    DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  samples INTEGER NOT NULL,
+  cases_json TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_benchmark_runs_engine_created
+  ON benchmark_runs(engine_version, created_at_utc);
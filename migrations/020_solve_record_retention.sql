@@ -0,0 +1,7 @@
+-- Lets the retention task find old solve records and DELETE /api/me/data
+-- find a session's records without scanning the table
+CREATE INDEX IF NOT EXISTS idx_solve_records_solved_at
+  ON solve_records(solved_at_utc);
+
+CREATE INDEX IF NOT EXISTS idx_solve_records_session
+  ON solve_records(session);
@@ -0,0 +1,10 @@
+-- Random puzzles handed out by /api/puzzle/random, kept for a while so
+-- players can check them by random_id; the expiry task deletes old rows
+CREATE TABLE IF NOT EXISTS random_puzzles (
+  id TEXT PRIMARY KEY,
+  puzzle_json TEXT NOT NULL,
+  created_at_utc TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_random_puzzles_created
+  ON random_puzzles(created_at_utc);
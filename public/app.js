@@ -10,10 +10,13 @@ const multiSelectBtn = document.getElementById("multi-select");
 const undoBtn = document.getElementById("undo-btn");
 const redoBtn = document.getElementById("redo-btn");
 const checkBtn = document.getElementById("check-btn");
+const fillCandidatesBtn = document.getElementById("fill-candidates-btn");
 const digitPad = document.querySelector(".digit-pad");
 
 let currentSolution = [];
 let variants = [];
+let constraintLabels = [];
+let customRules = [];
 
 // SVG-related refs (updated after we insert the SVG)
 let svg = null;
@@ -43,6 +46,7 @@ let adminLastPuzzleJson = null;
 let adminLastSvg = null;
 let adminLastVariants = [];
 let currentPuzzleDate = null;
+let currentRandomId = null; // random puzzles are checked by id instead of date
 let solvedForDate = false;
 let checkInFlight = false;
 
@@ -51,21 +55,55 @@ const VARIANT_LABELS = {
   kropki_black: "Kropki (black)",
   thermo: "Thermo",
   arrow: "Arrow",
+  renban: "Renban",
+  whisper: "German whispers",
+  palindrome: "Palindrome",
+  between: "Between lines",
   killer: "Killer cages",
+  little_killer: "Little killer",
+  sandwich: "Sandwich",
+  quadruple: "Quadruples",
   king: "King move",
   knight: "Knight move",
   queen: "Queen move",
+  disjoint_groups: "Disjoint groups",
+  extra_region: "Extra regions",
+  windoku: "Windoku",
+  diagonal: "Diagonal",
+  color_region: "Colored regions",
+  kropki_negative: "Kropki (all dots given)",
+  xv_negative: "XV (all marks given)",
 };
 
+// Constraint types the admin builder creates from a single path of cells.
+const PATH_CONSTRAINT_TYPES = ["thermo", "arrow", "renban", "whisper", "palindrome", "between"];
+
 const VARIANT_DESCRIPTIONS = {
   kropki_white: "White dots connect consecutive digits (difference of 1).",
   kropki_black: "Black dots connect digits in a 1:2 ratio.",
   thermo: "Thermo lines increase from bulb to tip.",
-  arrow: "Digits along the arrow sum to the circle value.",
+  arrow: "Digits along the arrow sum to the circle value; a two-cell pill reads as a two-digit number.",
+  renban: "Purple lines contain a set of non-repeating consecutive digits, in any order.",
+  whisper: "Adjacent digits along a green line differ by at least 5.",
+  palindrome: "Grey lines read the same from either end.",
+  between: "Digits on a line fall strictly between the digits in the circles at its ends.",
   killer: "Cages sum to the given total in the top left corner of the cage, no repeats in a cage.",
+  little_killer:
+    "Clues outside the grid give the sum of the digits along the indicated diagonal; digits may repeat.",
+  sandwich:
+    "Clues outside the grid give the sum of the digits between the 1 and the 9 in that row or column.",
+  quadruple: "Digits in a circle must appear in the four cells around it.",
   king: "Same digit cannot appear twice within king's move.",
   knight: "Same digit cannot appear twice within knight's move.",
   queen: "Same digit cannot appear twice within a queen's move.",
+  disjoint_groups: "Cells in the same position within their boxes contain different digits.",
+  extra_region: "Each shaded region contains the digits 1-9 once.",
+  windoku: "The four shaded windows each contain the digits 1-9 once.",
+  diagonal: "Digits cannot repeat along the marked diagonals.",
+  color_region: "Shaded regions are decorative unless stated; in a marked region digits cannot repeat.",
+  kropki_negative:
+    "All kropki dots are given: adjacent cells without a dot are neither consecutive nor in a 1:2 ratio.",
+  xv_negative: "All X and V marks are given: adjacent cells without a mark do not sum to 5 or 10.",
 };
 
 function isTypingInInput() {
@@ -202,7 +240,7 @@ function showHelpModal() {
   variantsList.className = "help-list";
 
   const variantItems = Array.isArray(variants) ? variants : [];
-  if (variantItems.length === 0) {
+  if (variantItems.length === 0 && customRules.length === 0) {
     const li = document.createElement("li");
     li.textContent = "Classic sudoku rules only.";
     variantsList.appendChild(li);
@@ -214,8 +252,27 @@ function showHelpModal() {
       li.textContent = `${label}: ${desc}`;
       variantsList.appendChild(li);
     });
+    customRules.forEach((rule) => {
+      const li = document.createElement("li");
+      li.textContent = rule;
+      variantsList.appendChild(li);
+    });
   }
 
+  const labelsTitle = document.createElement("h3");
+  labelsTitle.textContent = "Labelled constraints";
+
+  const labelsList = document.createElement("ul");
+  labelsList.className = "help-list";
+  constraintLabels.forEach((item) => {
+    const li = document.createElement("li");
+    const name = [item.label, item.group ? `(${item.group})` : null]
+      .filter(Boolean)
+      .join(" ");
+    li.textContent = `${name}: ${formatVariantLabel(item.type)}`;
+    labelsList.appendChild(li);
+  });
+
   const numpadTitle = document.createElement("h3");
   numpadTitle.textContent = "Numpad";
 
@@ -227,6 +284,7 @@ function showHelpModal() {
     "Hold Shift to temporarily enter candidate mode.",
     "Multi-select: toggle multiple cells at once.",
     "Check: compare entries against the solution (if available).",
+    "Fill pencil marks: mark every digit each empty cell can still take.",
     "Undo/Redo: revert or reapply moves.",
     "Erase: clear values or candidates.",
     "Keyboard: 1-9 to enter, 0/Backspace/Delete to erase, arrows to move.",
@@ -265,6 +323,10 @@ function showHelpModal() {
   modal.appendChild(h2);
   modal.appendChild(variantsTitle);
   modal.appendChild(variantsList);
+  if (constraintLabels.length > 0) {
+    modal.appendChild(labelsTitle);
+    modal.appendChild(labelsList);
+  }
   modal.appendChild(numpadTitle);
   modal.appendChild(numpadList);
   modal.appendChild(actions);
@@ -322,7 +384,7 @@ function isGridComplete() {
 
 function maybeCheckSolved() {
   if (document.body.classList.contains("admin")) return;
-  if (!currentPuzzleDate || solvedForDate || checkInFlight) return;
+  if ((!currentPuzzleDate && !currentRandomId) || solvedForDate || checkInFlight) return;
   if (!isGridComplete()) return;
 
   const grid = buildGridString();
@@ -332,14 +394,17 @@ function maybeCheckSolved() {
   fetch("/api/puzzle/check", {
     method: "POST",
     headers: { "Content-Type": "application/json", Accept: "application/json" },
-    body: JSON.stringify({ grid }),
+    body: JSON.stringify({ grid, date_utc: currentPuzzleDate, random_id: currentRandomId }),
   })
     .then((res) => (res.ok ? res.json() : Promise.reject(res)))
     .then((data) => {
       if (data?.status === "complete") {
         solvedForDate = true;
         saveProgress();
-        showModal("Congratulations", "You solved today's puzzle!");
+        showModal(
+          "Congratulations",
+          currentRandomId ? "You solved the puzzle!" : "You solved today's puzzle!"
+        );
       }
     })
     .catch((err) => {
@@ -360,6 +425,7 @@ function pushUndo(state) {
 }
 
 function storageKey() {
+  if (currentRandomId) return `makudoku-progress-random-${currentRandomId}`;
   if (!currentPuzzleDate) return null;
   return `makudoku-progress-${currentPuzzleDate}`;
 }
@@ -508,6 +574,33 @@ function initStateFromSvg() {
   updateUndoRedoUi();
 }
 
+// ---------- Branding ----------
+
+async function loadBranding() {
+  const res = await fetch("/api/meta/branding").catch(() => null);
+  if (!res || !res.ok) return;
+  const branding = await res.json();
+
+  const root = document.documentElement.style;
+  root.setProperty("--accent", branding.accent_color);
+  root.setProperty("--accent-text", branding.accent_text_color);
+  document.title = `${branding.site_name} – Daily Sudoku Puzzle`;
+  const nameEl = document.getElementById("site-name");
+  if (nameEl) nameEl.textContent = branding.site_name;
+
+  const footer = document.getElementById("site-footer");
+  if (!footer) return;
+  footer.replaceChildren(
+    ...branding.footer_links.map((link) => {
+      const a = document.createElement("a");
+      a.href = link.url;
+      a.textContent = link.label;
+      return a;
+    }),
+  );
+  footer.hidden = branding.footer_links.length === 0;
+}
+
 // ---------- Fetch & load puzzle ----------
 
 function getPuzzleEndpoint() {
@@ -518,6 +611,9 @@ function getPuzzleEndpoint() {
   if (fromDataset) return fromDataset;
   // Defensive fallback: admin pages should default to random puzzles.
   if (window.location?.pathname?.startsWith("/admin")) return "/api/puzzle/random";
+  // Vanity links (/p/{slug}) redirect here with ?p={slug}.
+  const slug = new URLSearchParams(window.location?.search || "").get("p");
+  if (slug) return `/api/puzzle/slug/${encodeURIComponent(slug)}`;
   return "/api/puzzle/today";
 }
 
@@ -592,6 +688,8 @@ function applyPuzzleData(data, message) {
   solutionFlat = solution ?? null;
 
   variants = Array.isArray(data.variants) ? data.variants : [];
+  constraintLabels = Array.isArray(data.labels) ? data.labels : [];
+  customRules = Array.isArray(data.custom_rules) ? data.custom_rules : [];
   renderVariants(variants);
   statusEl.textContent = message || "";
   if (puzzleTitleEl) {
@@ -604,6 +702,7 @@ function applyPuzzleData(data, message) {
     adminLastVariants = Array.isArray(data.variants) ? data.variants : [];
   } else {
     currentPuzzleDate = data.date_utc || null;
+    currentRandomId = data.random_id || null;
     loadProgress();
     if (currentPuzzleDate) {
       fetch("/api/puzzle/track", {
@@ -806,12 +905,18 @@ if (checkBtn) {
     fetch("/api/puzzle/check", {
       method: "POST",
       headers: { "Content-Type": "application/json", Accept: "application/json" },
-      body: JSON.stringify({ grid }),
+      body: JSON.stringify({ grid, date_utc: currentPuzzleDate, random_id: currentRandomId }),
     })
       .then(async (res) => {
         if (!res.ok) {
           const text = await res.text();
-          throw new Error(text || `Server error: ${res.status}`);
+          let message = text;
+          try {
+            message = JSON.parse(text).error || text;
+          } catch {
+            // Plain text error
+          }
+          throw new Error(message || `Server error: ${res.status}`);
         }
         return res.json();
       })
@@ -837,6 +942,47 @@ if (checkBtn) {
   });
 }
 
+if (fillCandidatesBtn) {
+  fillCandidatesBtn.addEventListener("click", () => {
+    const grid = buildGridString();
+    if (!grid || !currentState) return;
+
+    fetch("/api/puzzle/candidates", {
+      method: "POST",
+      headers: { "Content-Type": "application/json", Accept: "application/json" },
+      body: JSON.stringify({ grid, date_utc: currentPuzzleDate, random_id: currentRandomId }),
+    })
+      .then(async (res) => {
+        if (!res.ok) {
+          const text = await res.text();
+          throw new Error(text || `Server error: ${res.status}`);
+        }
+        return res.json();
+      })
+      .then((data) => {
+        if (!Array.isArray(data?.candidates) || data.candidates.length !== 81) {
+          throw new Error("Unexpected response");
+        }
+        const next = cloneState(currentState);
+        data.candidates.forEach((digits, idx) => {
+          // Givens and filled cells come back without candidates.
+          if (next.values[idx] || !digits.length) return;
+          next.candidates[idx] = digits.reduce((mask, digit) => mask | (1 << (digit - 1)), 0);
+        });
+        if (statesEqual(currentState, next)) return;
+        pushUndo(currentState);
+        currentState = next;
+        applyStateToSvg(currentState);
+        if (updateSelectionStylesFn) updateSelectionStylesFn();
+        saveProgress();
+      })
+      .catch((err) => {
+        console.error(err);
+        showModal("Pencil marks failed", err.message || String(err));
+      });
+  });
+}
+
 const helpBtn = document.getElementById("help-btn");
 if (helpBtn) {
   helpBtn.addEventListener("click", () => {
@@ -1261,6 +1407,19 @@ function initAdminCustomForm() {
   const constraintCells = document.getElementById("constraint-cells");
   const constraintSum = document.getElementById("constraint-sum");
   const constraintNoRepeats = document.getElementById("constraint-no-repeats");
+  const constraintDiagonal = document.getElementById("constraint-diagonal");
+  const constraintLittleCell = document.getElementById("constraint-little-cell");
+  const constraintLittleDirection = document.getElementById("constraint-little-direction");
+  const constraintLittleSum = document.getElementById("constraint-little-sum");
+  const constraintSandwichLine = document.getElementById("constraint-sandwich-line");
+  const constraintSandwichIndex = document.getElementById("constraint-sandwich-index");
+  const constraintSandwichSum = document.getElementById("constraint-sandwich-sum");
+  const constraintQuadCell = document.getElementById("constraint-quad-cell");
+  const constraintQuadDigits = document.getElementById("constraint-quad-digits");
+  const constraintExtraCells = document.getElementById("constraint-extra-cells");
+  const constraintRegionCells = document.getElementById("constraint-region-cells");
+  const constraintRegionColor = document.getElementById("constraint-region-color");
+  const constraintRegionDistinct = document.getElementById("constraint-region-distinct");
   const constraintAdd = document.getElementById("constraint-add");
   const constraintLoad = document.getElementById("constraint-load");
   const constraintClear = document.getElementById("constraint-clear");
@@ -1289,8 +1448,14 @@ function initAdminCustomForm() {
       const type = group.dataset.group;
       const shouldShow =
         (type === "pair" && (show === "kropki_white" || show === "kropki_black")) ||
-        (type === "path" && (show === "thermo" || show === "arrow")) ||
-        (type === "killer" && show === "killer");
+        (type === "path" && PATH_CONSTRAINT_TYPES.includes(show)) ||
+        (type === "killer" && show === "killer") ||
+        (type === "little_killer" && show === "little_killer") ||
+        (type === "sandwich" && show === "sandwich") ||
+        (type === "quadruple" && show === "quadruple") ||
+        (type === "extra_region" && show === "extra_region") ||
+        (type === "diagonal" && show === "diagonal") ||
+        (type === "color_region" && show === "color_region");
       group.style.display = shouldShow ? "grid" : "none";
     });
   };
@@ -1384,7 +1549,7 @@ function initAdminCustomForm() {
         const a = parseCell(constraintA?.value?.trim() || "");
         const b = parseCell(constraintB?.value?.trim() || "");
         item = { type, a, b };
-      } else if (type === "thermo" || type === "arrow") {
+      } else if (PATH_CONSTRAINT_TYPES.includes(type)) {
         const path = parseCells(constraintPath?.value?.trim() || "");
         item = { type, path };
       } else if (type === "killer") {
@@ -1399,6 +1564,50 @@ function initAdminCustomForm() {
           sum,
           no_repeats: constraintNoRepeats?.checked ?? true,
         };
+      } else if (type === "little_killer") {
+        const cell = parseCell(constraintLittleCell?.value?.trim() || "");
+        const sum = Number(constraintLittleSum?.value ?? "");
+        if (!Number.isFinite(sum)) {
+          throw new Error("Little killer sum must be a number");
+        }
+        item = { type, cell, direction: constraintLittleDirection?.value || "down_right", sum };
+      } else if (type === "sandwich") {
+        const index = Number(constraintSandwichIndex?.value ?? "");
+        const sum = Number(constraintSandwichSum?.value ?? "");
+        if (!Number.isInteger(index) || index < 0 || index > 8) {
+          throw new Error("Sandwich row/column must be 0-8");
+        }
+        if (!Number.isFinite(sum)) {
+          throw new Error("Sandwich sum must be a number");
+        }
+        const line = constraintSandwichLine?.value === "col" ? "col" : "row";
+        item = { type, [line]: index, sum };
+      } else if (type === "extra_region") {
+        const cells = parseCells(constraintExtraCells?.value?.trim() || "");
+        if (cells.length !== 9) {
+          throw new Error("Extra region must have 9 cells");
+        }
+        item = { type, cells };
+      } else if (type === "quadruple") {
+        const cell = parseCell(constraintQuadCell?.value?.trim() || "");
+        const digits = (constraintQuadDigits?.value || "")
+          .split(/[\s,]+/)
+          .filter(Boolean)
+          .map(Number);
+        if (digits.length === 0 || digits.some((d) => !Number.isInteger(d) || d < 1 || d > 9)) {
+          throw new Error("Quadruple digits must be 1-9");
+        }
+        item = { type, cell, digits };
+      } else if (type === "diagonal") {
+        item = { type, which: constraintDiagonal?.value || "both" };
+      } else if (type === "color_region") {
+        const cells = parseCells(constraintRegionCells?.value?.trim() || "");
+        item = {
+          type,
+          cells,
+          color: constraintRegionColor?.value || "#ffe08a",
+          distinct: constraintRegionDistinct?.checked ?? false,
+        };
       } else {
         item = { type };
       }
@@ -1577,12 +1786,55 @@ function initAdminRandomPublish() {
   });
 }
 
-loadPuzzle();
+async function initAdminSession() {
+  const loginSection = document.getElementById("admin-login");
+  const loginForm = document.getElementById("admin-login-form");
+  const tokenInput = document.getElementById("admin-token");
+  const logoutBtn = document.getElementById("admin-logout");
+
+  logoutBtn?.addEventListener("click", async () => {
+    await fetch("/api/admin/logout", { method: "POST" }).catch(() => {});
+    window.location.reload();
+  });
+
+  loginForm?.addEventListener("submit", async (event) => {
+    event.preventDefault();
+    try {
+      const res = await fetch("/api/admin/login", {
+        method: "POST",
+        headers: { "Content-Type": "application/json", Accept: "application/json" },
+        body: JSON.stringify({ token: tokenInput?.value ?? "" }),
+      });
+      if (!res.ok) {
+        const text = await res.text();
+        throw new Error(text || `Server error: ${res.status}`);
+      }
+      window.location.reload();
+    } catch (err) {
+      console.error(err);
+      showModal("Login failed", err.message || String(err));
+    }
+  });
+
+  const res = await fetch("/api/admin/session").catch(() => null);
+  const loggedIn = Boolean(res && res.ok);
+  document.body.classList.toggle("admin-locked", !loggedIn);
+  if (loginSection) loginSection.hidden = loggedIn;
+  if (logoutBtn) logoutBtn.hidden = !loggedIn;
+  return loggedIn;
+}
 
 if (document.body.classList.contains("admin")) {
-  initAdminTabs();
-  initAdminCustomForm();
-  initAdminRandomPublish();
-  initAdminReviewPicker();
-  initAdminAnalytics();
+  initAdminSession().then((loggedIn) => {
+    if (!loggedIn) return;
+    loadPuzzle();
+    initAdminTabs();
+    initAdminCustomForm();
+    initAdminRandomPublish();
+    initAdminReviewPicker();
+    initAdminAnalytics();
+  });
+} else {
+  loadBranding();
+  loadPuzzle();
 }
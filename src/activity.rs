@@ -0,0 +1,142 @@
+use axum::response::sse::Event;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::{collections::VecDeque, convert::Infallible, sync::Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+/// Events kept for admins who connect later; `recent` replays from these.
+const RECENT_CAPACITY: usize = 200;
+
+/// Events a slow listener may fall behind by before it misses some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Events the tail can be filtered to.
+pub const KINDS: &[&str] = &["view", "check", "solve"];
+
+/// One player action, as shown to admins watching the tail. Carries no
+/// more about the player than the analytics events do.
+#[derive(Serialize, Clone)]
+pub struct Activity {
+    pub event: &'static str,
+    pub date_utc: String,
+    /// The check result for `check` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+    pub at_utc: String,
+}
+
+/// In-process fan-out of player activity to `/api/admin/activity/stream`.
+/// Unlike `EventStream` it is always on and keeps nothing beyond the
+/// recent buffer.
+pub struct ActivityFeed {
+    recent: Mutex<VecDeque<Activity>>,
+    tx: broadcast::Sender<Activity>,
+}
+
+impl Default for ActivityFeed {
+    fn default() -> Self {
+        Self {
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_CAPACITY)),
+            tx: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl ActivityFeed {
+    pub fn publish(&self, event: &'static str, date_utc: &str, status: Option<&'static str>) {
+        let activity = Activity {
+            event,
+            date_utc: date_utc.to_string(),
+            status,
+            at_utc: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(activity.clone());
+        // Sent under the lock so a new subscriber sees each event either in
+        // its replay or on the channel, never both or neither.
+        let _ = self.tx.send(activity);
+    }
+
+    /// The last `count` events and a receiver for everything after them.
+    fn subscribe(&self, count: usize) -> (Vec<Activity>, broadcast::Receiver<Activity>) {
+        let recent = self.recent.lock().unwrap();
+        let skip = recent.len().saturating_sub(count);
+        (
+            recent.iter().skip(skip).cloned().collect(),
+            self.tx.subscribe(),
+        )
+    }
+}
+
+/// Which events a listener wants.
+pub struct ActivityFilter {
+    /// Empty means every kind.
+    pub kinds: Vec<String>,
+    pub date_utc: Option<String>,
+}
+
+impl ActivityFilter {
+    fn matches(&self, activity: &Activity) -> bool {
+        (self.kinds.is_empty() || self.kinds.iter().any(|k| k == activity.event))
+            && self
+                .date_utc
+                .as_ref()
+                .is_none_or(|date| *date == activity.date_utc)
+    }
+}
+
+/// Sends up to `recent` matching buffered events, then live ones until the
+/// client disconnects. The recent events are picked before filtering, so
+/// fewer may match.
+pub async fn stream(
+    feed: &ActivityFeed,
+    filter: ActivityFilter,
+    recent: usize,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    let (replay, mut rx) = feed.subscribe(recent);
+    for activity in replay.iter().filter(|a| filter.matches(a)) {
+        if !send_event(&tx, activity.event, activity).await {
+            return;
+        }
+    }
+
+    loop {
+        let received = tokio::select! {
+            received = rx.recv() => received,
+            _ = tx.closed() => return,
+        };
+        match received {
+            Ok(activity) => {
+                if filter.matches(&activity) && !send_event(&tx, activity.event, &activity).await {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                let lagged = serde_json::json!({ "missed": missed });
+                if !send_event(&tx, "lagged", &lagged).await {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Returns whether the client is still listening.
+async fn send_event(
+    tx: &mpsc::Sender<Result<Event, Infallible>>,
+    name: &str,
+    data: &impl Serialize,
+) -> bool {
+    match Event::default().event(name).json_data(data) {
+        Ok(event) => tx.send(Ok(event)).await.is_ok(),
+        Err(e) => {
+            tracing::warn!("encoding activity event failed: {e}");
+            false
+        }
+    }
+}
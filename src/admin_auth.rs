@@ -0,0 +1,148 @@
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{AUTHORIZATION, COOKIE, SET_COOKIE, WWW_AUTHENTICATE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::AppState;
+
+pub const SESSION_COOKIE: &str = "makudoku_admin_session";
+
+const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Admin UI sessions created by `POST /api/admin/login`. Kept in memory, so a
+/// restart logs everyone out.
+#[derive(Default)]
+pub struct AdminSessions {
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+impl AdminSessions {
+    fn create(&self) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, expires| *expires > now);
+        sessions.insert(id.clone(), now + SESSION_TTL);
+        id
+    }
+
+    fn is_valid(&self, id: &str) -> bool {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(id)
+            .is_some_and(|expires| *expires > Instant::now())
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    token: String,
+}
+
+/// Lets a request through when it carries `Authorization: Bearer
+/// <MAKUDOKU_ADMIN_TOKEN>` or a valid session cookie.
+pub async fn require_admin(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if state.config.admin_token.is_none() {
+        return (StatusCode::NOT_FOUND, "Admin API is not enabled").into_response();
+    }
+    if !is_admin(&state, req.headers()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Bearer")],
+            "Admin login required",
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+/// Whether the request carries admin credentials, for public endpoints with
+/// admin-only options. Always false while the admin API is disabled.
+pub fn is_admin(state: &AppState, headers: &HeaderMap) -> bool {
+    let Some(expected) = state.config.admin_token.as_deref() else {
+        return false;
+    };
+    let bearer_ok = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, expected));
+    bearer_ok || session_cookie(headers).is_some_and(|id| state.admin_sessions.is_valid(&id))
+}
+
+pub async fn login_handler(
+    State(state): State<AppState>,
+    Json(req): Json<LoginRequest>,
+) -> Response {
+    let Some(expected) = state.config.admin_token.as_deref() else {
+        return (StatusCode::NOT_FOUND, "Admin API is not enabled").into_response();
+    };
+    if !tokens_match(&req.token, expected) {
+        return (StatusCode::UNAUTHORIZED, "Invalid admin token").into_response();
+    }
+
+    let id = state.admin_sessions.create();
+    let cookie = session_cookie_header(&state, &id, SESSION_TTL.as_secs());
+    (StatusCode::NO_CONTENT, [(SET_COOKIE, cookie)]).into_response()
+}
+
+pub async fn logout_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(id) = session_cookie(&headers) {
+        state.admin_sessions.remove(&id);
+    }
+    let cookie = session_cookie_header(&state, "", 0);
+    (StatusCode::NO_CONTENT, [(SET_COOKIE, cookie)]).into_response()
+}
+
+/// Reached only through `require_admin`, so the admin UI can ask whether it
+/// is logged in.
+pub async fn session_handler() -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+/// Compares in time that depends only on the lengths, so a guess can't be
+/// refined from how long the rejection took.
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn session_cookie_header(state: &AppState, id: &str, max_age: u64) -> String {
+    let secure = if state.config.admin_cookie_secure {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!("{SESSION_COOKIE}={id}; Path=/; HttpOnly; SameSite=Strict; Max-Age={max_age}{secure}")
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
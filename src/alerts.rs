@@ -0,0 +1,74 @@
+use anyhow::{Context, bail};
+use chrono::{NaiveDate, Utc};
+use sqlx::SqlitePool;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use crate::{config::Config, store};
+
+/// Days from tomorrow that already have a draft or published puzzle, up to
+/// the first gap.
+pub async fn future_runway(db: &SqlitePool, today: NaiveDate) -> Result<usize, sqlx::Error> {
+    let today_value = today.to_string();
+    let assigned: HashSet<String> = store::assigned_dates_after(db, &today_value)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(today
+        .iter_days()
+        .skip(1)
+        .take_while(|date| assigned.contains(&date.to_string()))
+        .count())
+}
+
+/// Posts `message` to the low-queue webhook. The body carries it as both
+/// `content` (Discord) and `text` (Slack), so either kind of URL works.
+pub async fn send(config: &Config, message: &str) -> anyhow::Result<()> {
+    let Some(url) = config.low_queue_webhook_url.as_deref() else {
+        bail!("MAKUDOKU_LOW_QUEUE_WEBHOOK_URL is not set");
+    };
+    reqwest::Client::new()
+        .post(url)
+        .json(&serde_json::json!({ "content": message, "text": message }))
+        .send()
+        .await
+        .context("webhook request failed")?
+        .error_for_status()
+        .context("webhook returned an error")?;
+    Ok(())
+}
+
+/// Warns through the webhook when fewer than `low_queue_min_days` days
+/// after today have a puzzle. Alerts at most once per day, and again after
+/// a restart.
+pub async fn run_low_queue_loop(db: SqlitePool, config: Arc<Config>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(15 * 60));
+    let mut alerted_on: Option<NaiveDate> = None;
+    loop {
+        ticker.tick().await;
+        let today = Utc::now().date_naive();
+        if alerted_on == Some(today) {
+            continue;
+        }
+        let runway = match future_runway(&db, today).await {
+            Ok(runway) => runway,
+            Err(e) => {
+                tracing::warn!("low queue check failed: {e}");
+                continue;
+            }
+        };
+        if runway >= config.low_queue_min_days {
+            continue;
+        }
+
+        let message = format!(
+            "Makudoku puzzle queue is running low: {runway} day(s) after {today} have a puzzle, \
+             fewer than the {} wanted.",
+            config.low_queue_min_days
+        );
+        match send(&config, &message).await {
+            Ok(()) => alerted_on = Some(today),
+            Err(e) => tracing::warn!("low queue alert failed: {e:#}"),
+        }
+    }
+}
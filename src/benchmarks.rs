@@ -0,0 +1,319 @@
+use makudoku::{SimpleRng, VERSION as ENGINE_VERSION, generate_full_solution_with};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Instant;
+
+/// Variant sets a run can cover, by name. Each holds only constraints that
+/// some grid always satisfies, so a failed sample means a generator
+/// problem rather than an impossible fixture.
+pub const VARIANT_SETS: &[(&str, &str)] = &[
+    ("classic", "[]"),
+    ("diagonal", r#"[{ "type": "diagonal" }]"#),
+    ("king", r#"[{ "type": "king" }]"#),
+    ("knight", r#"[{ "type": "knight" }]"#),
+    ("disjoint_groups", r#"[{ "type": "disjoint_groups" }]"#),
+    ("windoku", r#"[{ "type": "windoku" }]"#),
+    (
+        "thermo",
+        r#"[{ "type": "thermo", "path": [[0, 0], [0, 1], [0, 2], [0, 3]] }]"#,
+    ),
+];
+
+pub const DEFAULT_CLUE_TARGETS: &[usize] = &[30, 26];
+
+const DEFAULT_SAMPLES: usize = 3;
+const MAX_SAMPLES: usize = 10;
+const MAX_CASES: usize = 50;
+
+/// What a run covers; unset fields use every variant set, the default clue
+/// targets and `DEFAULT_SAMPLES`.
+#[derive(Deserialize, Default)]
+pub struct BenchmarkOptions {
+    pub variant_sets: Option<Vec<String>>,
+    pub clue_targets: Option<Vec<usize>>,
+    pub samples: Option<usize>,
+}
+
+/// Medians over the samples of one variant set and clue target.
+#[derive(Serialize, Deserialize)]
+pub struct BenchmarkCase {
+    pub variant_set: String,
+    pub clue_target: usize,
+    /// Samples that produced a puzzle; the medians cover only these.
+    pub completed: usize,
+    pub failed: usize,
+    /// Full solution plus digging down to the clue target.
+    pub generate_ms: Option<f64>,
+    /// One uniqueness check of the finished puzzle.
+    pub unique_check_ms: Option<f64>,
+    pub clue_count: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct BenchmarkRun {
+    pub id: String,
+    pub engine_version: String,
+    pub created_at_utc: String,
+    pub samples: usize,
+    pub cases: Vec<BenchmarkCase>,
+}
+
+/// The cases of a run, checked against the limits.
+pub fn plan(options: &BenchmarkOptions) -> Result<(Vec<(String, usize)>, usize), String> {
+    let variant_sets = match &options.variant_sets {
+        Some(names) => {
+            for name in names {
+                if !VARIANT_SETS.iter().any(|(known, _)| known == name) {
+                    return Err(format!("unknown variant set {name}"));
+                }
+            }
+            names.clone()
+        }
+        None => VARIANT_SETS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+    };
+    let clue_targets = options
+        .clue_targets
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CLUE_TARGETS.to_vec());
+    if let Some(target) = clue_targets.iter().find(|t| !(17..=80).contains(*t)) {
+        return Err(format!("clue target {target} is not between 17 and 80"));
+    }
+    let samples = options.samples.unwrap_or(DEFAULT_SAMPLES);
+    if samples == 0 || samples > MAX_SAMPLES {
+        return Err(format!("samples must be between 1 and {MAX_SAMPLES}"));
+    }
+
+    let cases: Vec<(String, usize)> = variant_sets
+        .iter()
+        .flat_map(|set| clue_targets.iter().map(|target| (set.clone(), *target)))
+        .collect();
+    if cases.is_empty() || cases.len() > MAX_CASES {
+        return Err(format!(
+            "a run needs between 1 and {MAX_CASES} variant set and clue target pairs"
+        ));
+    }
+    Ok((cases, samples))
+}
+
+/// Times every case. Blocking. Sample `n` of every case uses seed `n`, so
+/// runs on different engine versions generate from the same starting point.
+pub fn run(cases: &[(String, usize)], samples: usize) -> Result<Vec<BenchmarkCase>, String> {
+    let mut results = Vec::with_capacity(cases.len());
+    for (variant_set, clue_target) in cases {
+        let (_, fixture) = VARIANT_SETS
+            .iter()
+            .find(|(name, _)| name == variant_set)
+            .ok_or_else(|| format!("unknown variant set {variant_set}"))?;
+        let constraints: Vec<serde_json::Value> =
+            serde_json::from_str(fixture).map_err(|e| e.to_string())?;
+        let specs = crate::constraints_from_json(&constraints)?;
+
+        let mut generate_ms = Vec::with_capacity(samples);
+        let mut unique_check_ms = Vec::with_capacity(samples);
+        let mut clue_counts = Vec::with_capacity(samples);
+        let mut failed = 0;
+        for seed in 1..=samples as u64 {
+            let mut rng = SimpleRng::from_seed(seed);
+            let started = Instant::now();
+            let puzzle = generate_full_solution_with(rng.clone(), |eng| {
+                crate::apply_variant_specs(eng, &specs);
+            })
+            .and_then(|solution| {
+                crate::generate_puzzle_from_solution(
+                    &solution,
+                    *clue_target,
+                    &specs,
+                    &mut rng,
+                    None,
+                    &crate::GenerationLimits::unlimited(),
+                    &[],
+                )
+            });
+            let Ok(puzzle) = puzzle else {
+                failed += 1;
+                continue;
+            };
+            generate_ms.push(elapsed_ms(started));
+
+            let started = Instant::now();
+            crate::has_unique_solution_with_specs(&puzzle, &specs, None, &mut rng);
+            unique_check_ms.push(elapsed_ms(started));
+            clue_counts.push(puzzle.chars().filter(|c| *c != '.').count() as f64);
+        }
+
+        results.push(BenchmarkCase {
+            variant_set: variant_set.clone(),
+            clue_target: *clue_target,
+            completed: generate_ms.len(),
+            failed,
+            generate_ms: median(generate_ms),
+            unique_check_ms: median(unique_check_ms),
+            clue_count: median(clue_counts),
+        });
+    }
+    Ok(results)
+}
+
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// Runs planned cases on the blocking pool and stores the result. Shared by
+/// the admin endpoint and the `benchmark` command.
+pub async fn run_and_save(
+    db: &SqlitePool,
+    cases: Vec<(String, usize)>,
+    samples: usize,
+) -> Result<BenchmarkRun, String> {
+    let results = tokio::task::spawn_blocking(move || run(&cases, samples))
+        .await
+        .map_err(|e| format!("Benchmark task failed: {e}"))??;
+    save(db, samples, results).await
+}
+
+/// Stores a finished run under a new id.
+async fn save(
+    db: &SqlitePool,
+    samples: usize,
+    cases: Vec<BenchmarkCase>,
+) -> Result<BenchmarkRun, String> {
+    let run = BenchmarkRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        engine_version: ENGINE_VERSION.to_string(),
+        created_at_utc: crate::now_utc_string(),
+        samples,
+        cases,
+    };
+    let cases_json = serde_json::to_string(&run.cases).map_err(|e| e.to_string())?;
+    let samples = run.samples as i64;
+    sqlx::query!(
+        r#"
+        INSERT INTO benchmark_runs (id, engine_version, created_at_utc, samples, cases_json)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+        run.id,
+        run.engine_version,
+        run.created_at_utc,
+        samples,
+        cases_json
+    )
+    .execute(db)
+    .await
+    .map_err(|e| format!("DB error: {e}"))?;
+    Ok(run)
+}
+
+struct BenchmarkRunRow {
+    id: String,
+    engine_version: String,
+    created_at_utc: String,
+    samples: i64,
+    cases_json: String,
+}
+
+impl From<BenchmarkRunRow> for BenchmarkRun {
+    fn from(row: BenchmarkRunRow) -> Self {
+        Self {
+            id: row.id,
+            engine_version: row.engine_version,
+            created_at_utc: row.created_at_utc,
+            samples: row.samples as usize,
+            cases: serde_json::from_str(&row.cases_json).unwrap_or_default(),
+        }
+    }
+}
+
+/// Stored runs newest first, optionally for one engine version.
+pub async fn list(
+    db: &SqlitePool,
+    engine_version: Option<&str>,
+    limit: i64,
+) -> Result<Vec<BenchmarkRun>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        BenchmarkRunRow,
+        r#"
+        SELECT id AS "id!", engine_version, created_at_utc, samples, cases_json
+        FROM benchmark_runs
+        WHERE (? IS NULL OR engine_version = ?)
+        ORDER BY created_at_utc DESC
+        LIMIT ?
+        "#,
+        engine_version,
+        engine_version,
+        limit
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(rows.into_iter().map(BenchmarkRun::from).collect())
+}
+
+pub async fn get(db: &SqlitePool, id: &str) -> Result<Option<BenchmarkRun>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        BenchmarkRunRow,
+        r#"
+        SELECT id AS "id!", engine_version, created_at_utc, samples, cases_json
+        FROM benchmark_runs
+        WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.map(BenchmarkRun::from))
+}
+
+/// One case present in both runs of a comparison.
+#[derive(Serialize)]
+pub struct CaseComparison {
+    pub variant_set: String,
+    pub clue_target: usize,
+    pub base_generate_ms: Option<f64>,
+    pub head_generate_ms: Option<f64>,
+    /// Relative change from base to head, e.g. `0.25` for 25% slower.
+    pub generate_change: Option<f64>,
+    pub base_unique_check_ms: Option<f64>,
+    pub head_unique_check_ms: Option<f64>,
+    pub unique_check_change: Option<f64>,
+}
+
+/// Pairs up the cases both runs measured.
+pub fn compare(base: &BenchmarkRun, head: &BenchmarkRun) -> Vec<CaseComparison> {
+    let change = |base: Option<f64>, head: Option<f64>| match (base, head) {
+        (Some(base), Some(head)) if base > 0.0 => Some((head - base) / base),
+        _ => None,
+    };
+    head.cases
+        .iter()
+        .filter_map(|head_case| {
+            let base_case = base.cases.iter().find(|c| {
+                c.variant_set == head_case.variant_set && c.clue_target == head_case.clue_target
+            })?;
+            Some(CaseComparison {
+                variant_set: head_case.variant_set.clone(),
+                clue_target: head_case.clue_target,
+                base_generate_ms: base_case.generate_ms,
+                head_generate_ms: head_case.generate_ms,
+                generate_change: change(base_case.generate_ms, head_case.generate_ms),
+                base_unique_check_ms: base_case.unique_check_ms,
+                head_unique_check_ms: head_case.unique_check_ms,
+                unique_check_change: change(base_case.unique_check_ms, head_case.unique_check_ms),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::store;
+
+/// `settings` key the branding is stored under.
+const SETTINGS_KEY: &str = "branding";
+
+const MAX_SITE_NAME_CHARS: usize = 64;
+const MAX_LINK_LABEL_CHARS: usize = 64;
+const MAX_FOOTER_LINKS: usize = 10;
+
+/// How an instance presents itself, so operators can run their own daily
+/// site from this crate. Fields left out keep the Makudoku defaults.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Branding {
+    /// Shown in the page header, feed titles, post images and the digest.
+    pub site_name: String,
+    /// `#rgb`/`#rrggbb` or a color name, for buttons and the post image band.
+    pub accent_color: String,
+    /// Text drawn on the accent color.
+    pub accent_text_color: String,
+    pub footer_links: Vec<FooterLink>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FooterLink {
+    pub label: String,
+    /// An `http(s)://` URL or a site-relative path.
+    pub url: String,
+}
+
+impl Default for Branding {
+    fn default() -> Self {
+        Self {
+            site_name: "Makudoku".to_string(),
+            accent_color: "#111827".to_string(),
+            accent_text_color: "#ffffff".to_string(),
+            footer_links: Vec::new(),
+        }
+    }
+}
+
+impl Branding {
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.site_name.trim();
+        if name.is_empty() || name.chars().count() > MAX_SITE_NAME_CHARS {
+            return Err(format!(
+                "site_name must be 1 to {MAX_SITE_NAME_CHARS} characters"
+            ));
+        }
+        for color in [&self.accent_color, &self.accent_text_color] {
+            crate::parse_svg_color(&serde_json::Value::from(color.as_str()))?;
+        }
+        if self.footer_links.len() > MAX_FOOTER_LINKS {
+            return Err(format!("at most {MAX_FOOTER_LINKS} footer links"));
+        }
+        for link in &self.footer_links {
+            let label = link.label.trim();
+            if label.is_empty() || label.chars().count() > MAX_LINK_LABEL_CHARS {
+                return Err(format!(
+                    "footer link labels must be 1 to {MAX_LINK_LABEL_CHARS} characters"
+                ));
+            }
+            let url = link.url.as_str();
+            let allowed = url.starts_with("https://")
+                || url.starts_with("http://")
+                || (url.starts_with('/') && !url.starts_with("//"));
+            if !allowed || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                return Err(format!("invalid footer link url: {url}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The stored branding, or the defaults when it was never set.
+pub async fn load(db: &SqlitePool) -> Result<Branding, String> {
+    let Some(json) = store::get_setting(db, SETTINGS_KEY)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?
+    else {
+        return Ok(Branding::default());
+    };
+    serde_json::from_str(&json).map_err(|e| format!("Stored branding is invalid: {e}"))
+}
+
+/// Like `load`, for output that should still go out with the defaults
+/// when the setting can't be read.
+pub async fn load_or_default(db: &SqlitePool) -> Branding {
+    load(db).await.unwrap_or_else(|err| {
+        tracing::warn!("using default branding: {err}");
+        Branding::default()
+    })
+}
+
+pub async fn save(db: &SqlitePool, branding: &Branding) -> Result<(), String> {
+    let json = serde_json::to_string(branding).map_err(|e| e.to_string())?;
+    store::set_setting(db, SETTINGS_KEY, &json)
+        .await
+        .map_err(|e| format!("DB error: {e}"))
+}
@@ -0,0 +1,202 @@
+use chrono::{SecondsFormat, Utc};
+use futures_util::{StreamExt, stream::BoxStream};
+use makudoku::VERSION as ENGINE_VERSION;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::io;
+use tokio::sync::mpsc;
+
+use crate::store::{self, ExportRow};
+
+/// Identifies an export bundle, bumped if the record layout changes.
+pub const BUNDLE_FORMAT: &str = "makudoku-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct BundleStats {
+    pub views: i64,
+    pub checks: i64,
+    pub solves: i64,
+}
+
+/// One `puzzles` row with its stats, as written by `GET /api/admin/export`.
+#[derive(Serialize, Deserialize)]
+pub struct BundlePuzzle {
+    pub date_utc: String,
+    pub status: String,
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    pub render_version: i64,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub difficulty: Option<i64>,
+    pub variants: Vec<String>,
+    pub engine_version: Option<String>,
+    pub extra_json: Option<String>,
+    pub created_at_utc: String,
+    pub updated_at_utc: String,
+    pub published_at_utc: Option<String>,
+    pub stats: Option<BundleStats>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at_utc: String,
+    pub engine_version: String,
+    pub puzzles: Vec<BundlePuzzle>,
+}
+
+/// Which puzzles an export covers; unset fields don't filter.
+#[derive(Default)]
+pub struct BundleFilter {
+    pub status: Option<String>,
+    /// Inclusive `YYYY-MM-DD` bounds.
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl From<ExportRow> for BundlePuzzle {
+    fn from(row: ExportRow) -> Self {
+        Self {
+            stats: row.views.map(|views| BundleStats {
+                views,
+                checks: row.checks.unwrap_or(0),
+                solves: row.solves.unwrap_or(0),
+            }),
+            date_utc: row.date_utc,
+            status: row.status,
+            puzzle_json: row.puzzle_json,
+            svg: row.svg,
+            render_version: row.render_version,
+            title: row.title,
+            author: row.author,
+            difficulty: row.difficulty,
+            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
+                .unwrap_or_default(),
+            engine_version: row.engine_version,
+            extra_json: row.extra_json,
+            created_at_utc: row.created_at_utc,
+            updated_at_utc: row.updated_at_utc,
+            published_at_utc: row.published_at_utc,
+        }
+    }
+}
+
+/// Rows matching `filter` in date order, fetched one at a time.
+fn fetch_rows<'a>(
+    db: &'a SqlitePool,
+    filter: &'a BundleFilter,
+) -> BoxStream<'a, Result<ExportRow, sqlx::Error>> {
+    store::export_rows(
+        db,
+        filter.status.as_deref(),
+        filter.from.as_deref(),
+        filter.to.as_deref(),
+    )
+}
+
+/// The puzzles `filter` covers, for the JSON bundle and exchange exports.
+pub async fn load_puzzles(
+    db: &SqlitePool,
+    filter: &BundleFilter,
+) -> Result<Vec<BundlePuzzle>, sqlx::Error> {
+    let mut rows = fetch_rows(db, filter);
+    let mut puzzles = Vec::new();
+    while let Some(row) = rows.next().await {
+        puzzles.push(row?.into());
+    }
+    Ok(puzzles)
+}
+
+/// Sends an NDJSON export line by line: the header, then one line per
+/// puzzle. Stops early if the receiver goes away; a failed query ends the
+/// body with an error, so clients can tell the export is truncated.
+pub async fn stream_ndjson(
+    db: SqlitePool,
+    filter: BundleFilter,
+    tx: mpsc::Sender<Result<String, io::Error>>,
+) {
+    let header = BundleHeader {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at_utc: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        engine_version: Some(ENGINE_VERSION.to_string()),
+    };
+    if send_line(&tx, &header).await.is_err() {
+        return;
+    }
+
+    let mut rows = fetch_rows(&db, &filter);
+    while let Some(row) = rows.next().await {
+        let sent = match row {
+            Ok(row) => send_line(&tx, &BundlePuzzle::from(row)).await,
+            Err(e) => {
+                let _ = tx.send(Err(io::Error::other(e))).await;
+                return;
+            }
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send_line<T: Serialize>(
+    tx: &mpsc::Sender<Result<String, io::Error>>,
+    value: &T,
+) -> Result<(), ()> {
+    let line = serde_json::to_string(value)
+        .map(|line| line + "\n")
+        .map_err(io::Error::other);
+    tx.send(line).await.map_err(|_| ())
+}
+
+/// First line of an NDJSON export. Restores only read `format` and
+/// `version`, which a JSON bundle carries at its top level.
+#[derive(Serialize, Deserialize)]
+struct BundleHeader {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    exported_at_utc: Option<String>,
+    #[serde(default)]
+    engine_version: Option<String>,
+}
+
+/// Reads a bundle written by `GET /api/admin/export`, in either format.
+pub fn parse(body: &str) -> Result<Vec<BundlePuzzle>, String> {
+    // A JSON bundle is one document; anything else is read as NDJSON.
+    if let Ok(bundle) = serde_json::from_str::<Bundle>(body) {
+        check_header(&bundle.format, bundle.version)?;
+        return Ok(bundle.puzzles);
+    }
+
+    let mut lines = body
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let (_, first) = lines.next().ok_or_else(|| "bundle is empty".to_string())?;
+    let header: BundleHeader =
+        serde_json::from_str(first).map_err(|e| format!("invalid bundle header: {e}"))?;
+    check_header(&header.format, header.version)?;
+    lines
+        .map(|(line_no, line)| {
+            serde_json::from_str(line).map_err(|e| format!("invalid record on line {line_no}: {e}"))
+        })
+        .collect()
+}
+
+fn check_header(format: &str, version: u32) -> Result<(), String> {
+    if format != BUNDLE_FORMAT {
+        return Err(format!("not a {BUNDLE_FORMAT} export"));
+    }
+    if version > BUNDLE_VERSION {
+        return Err(format!(
+            "bundle version {version} is newer than the supported {BUNDLE_VERSION}"
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use axum::{
+    Json,
+    http::{
+        HeaderMap, StatusCode,
+        header::{COOKIE, RETRY_AFTER},
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const SESSION_COOKIE: &str = "makudoku_session";
+
+/// Most sessions and addresses tracked at once. When the map is full,
+/// expired entries are dropped first, then the one idle the longest.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Session(String),
+    Address(IpAddr),
+}
+
+/// Sliding-window limit on `/api/puzzle/check`, counted per player session
+/// rather than per address so players behind a shared NAT don't use up each
+/// other's checks. A session only gets its own budget once it has come back
+/// with the cookie; until then its checks count against its address, so
+/// clients that drop or make up cookies share one budget per address.
+pub struct CheckLimiter {
+    window: Duration,
+    /// `(min_difficulty, limit)` pairs, sorted by difficulty.
+    tiers: Vec<(i64, usize)>,
+    entries: Mutex<HashMap<Key, VecDeque<Instant>>>,
+}
+
+impl CheckLimiter {
+    /// Returns `None` when no tiers are configured.
+    pub fn new(window: Duration, mut tiers: Vec<(i64, usize)>) -> Option<Self> {
+        if tiers.is_empty() {
+            return None;
+        }
+        tiers.sort_unstable();
+        Some(Self {
+            window,
+            tiers,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The limit of the hardest tier at or below `difficulty`. Unrated
+    /// puzzles, and ones below every tier, use the easiest tier.
+    fn limit_for(&self, difficulty: Option<i64>) -> usize {
+        let difficulty = difficulty.unwrap_or(i64::MIN);
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(min, _)| *min <= difficulty)
+            .unwrap_or(&self.tiers[0])
+            .1
+    }
+
+    /// Records a check for `session`, or says how long to wait when it is
+    /// already at its limit. Sessions the limiter hasn't seen yet, either new
+    /// ones or ones it forgot, are charged to `client` and get their own
+    /// budget from the next check on. Without a `client` the session is
+    /// charged directly.
+    pub fn try_check(
+        &self,
+        session: &str,
+        client: Option<IpAddr>,
+        difficulty: Option<i64>,
+    ) -> Result<(), Throttled> {
+        let limit = self.limit_for(difficulty);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        let session = Key::Session(session.to_string());
+        let known = entries.contains_key(&session);
+        let key = match (known, client) {
+            (false, Some(client)) => Key::Address(client),
+            _ => session.clone(),
+        };
+        self.charge(&mut entries, key, limit, now)?;
+        if !known && !entries.contains_key(&session) {
+            self.make_room(&mut entries, now);
+            entries.insert(session, VecDeque::new());
+        }
+        Ok(())
+    }
+
+    fn charge(
+        &self,
+        entries: &mut HashMap<Key, VecDeque<Instant>>,
+        key: Key,
+        limit: usize,
+        now: Instant,
+    ) -> Result<(), Throttled> {
+        if !entries.contains_key(&key) {
+            self.make_room(entries, now);
+        }
+        let hits = entries.entry(key).or_default();
+        while hits.front().is_some_and(|t| now - *t >= self.window) {
+            hits.pop_front();
+        }
+        if hits.len() >= limit {
+            let oldest = hits.front().copied().unwrap_or(now);
+            return Err(Throttled {
+                what: "checks",
+                retry_after: self.window.saturating_sub(now - oldest),
+                limit,
+                window: self.window,
+            });
+        }
+        hits.push_back(now);
+        Ok(())
+    }
+
+    /// Keeps the map below `MAX_ENTRIES` before a new entry goes in.
+    fn make_room(&self, entries: &mut HashMap<Key, VecDeque<Instant>>, now: Instant) {
+        if entries.len() < MAX_ENTRIES {
+            return;
+        }
+        let window = self.window;
+        entries.retain(|_, hits| hits.back().is_some_and(|t| now - *t < window));
+        if entries.len() < MAX_ENTRIES {
+            return;
+        }
+        let idle = entries
+            .iter()
+            .min_by_key(|(_, hits)| hits.back().copied())
+            .map(|(key, _)| key.clone());
+        if let Some(idle) = idle {
+            entries.remove(&idle);
+        }
+    }
+
+    /// Drops the check history of a session whose data was deleted.
+    pub fn forget(&self, session: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&Key::Session(session.to_string()));
+    }
+}
+
+/// A refused check or request: the limit, its window and when the oldest
+/// hit in the window expires.
+pub struct Throttled {
+    /// What was limited, for the error message, e.g. `checks`.
+    pub what: &'static str,
+    pub retry_after: Duration,
+    pub limit: usize,
+    pub window: Duration,
+}
+
+/// JSON body of a `429`, so clients can back off instead of retrying blindly.
+#[derive(Serialize)]
+struct ThrottledBody {
+    error: String,
+    retry_after_ms: u128,
+    limit: usize,
+    window_ms: u128,
+}
+
+impl IntoResponse for Throttled {
+    fn into_response(self) -> Response {
+        let retry_after = self.retry_after.as_secs().max(1);
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            Json(ThrottledBody {
+                error: format!("Too many {}, try again in {retry_after}s", self.what),
+                retry_after_ms: self.retry_after.as_millis().max(1),
+                limit: self.limit,
+                window_ms: self.window.as_millis(),
+            }),
+        )
+            .into_response()
+    }
+}
+
+/// The player's session id from its cookie, if the client has one.
+pub fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty() && value.len() <= 64)
+}
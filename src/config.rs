@@ -0,0 +1,281 @@
+use anyhow::Context;
+use std::{env, str::FromStr};
+
+use crate::rng::Seeding;
+
+/// Server settings read from `MAKUDOKU_*` environment variables at startup.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Token guarding `/api/admin/*`, as a bearer token or via the admin
+    /// login; the admin API is disabled when unset.
+    pub admin_token: Option<String>,
+    /// Mark the admin session cookie `Secure`, for instances served over
+    /// HTTPS.
+    pub admin_cookie_secure: bool,
+    /// Base64 encoded 32-byte Ed25519 secret key used to sign public puzzle payloads.
+    pub signing_key: Option<String>,
+    /// Identifier published next to signatures so verifiers can pick the right key.
+    pub signing_key_id: Option<String>,
+    /// Bearer token mirrors must present to `GET /api/sync/puzzles`; the
+    /// endpoint is disabled when unset.
+    pub sync_token: Option<String>,
+    /// Base URL of the instance a `--mirror` server pulls from.
+    pub mirror_upstream: Option<String>,
+    /// Token sent to the upstream sync endpoint.
+    pub mirror_token: Option<String>,
+    pub mirror_interval_secs: u64,
+    /// Largest `puzzle_json` the admin create endpoint will store, in bytes.
+    pub max_puzzle_json_bytes: usize,
+    /// Largest SVG the admin create endpoint will store, in bytes.
+    pub max_svg_bytes: usize,
+    /// HTTP endpoint that accepts `{ from, to, subject, text }` JSON and
+    /// sends it as email.
+    pub email_api_url: Option<String>,
+    /// Bearer token for the email endpoint.
+    pub email_api_key: Option<String>,
+    pub email_from: Option<String>,
+    /// Admin addresses receiving the weekly digest; it isn't scheduled when empty.
+    pub digest_recipients: Vec<String>,
+    /// Public site URL, used as `{link}` in social posts.
+    pub public_url: Option<String>,
+    /// Mastodon instance base URL, e.g. `https://mastodon.social`.
+    pub mastodon_url: Option<String>,
+    /// Mastodon access token with `write:media` and `write:statuses`.
+    pub mastodon_token: Option<String>,
+    pub mastodon_template: Option<String>,
+    pub bluesky_service: String,
+    pub bluesky_handle: Option<String>,
+    /// Bluesky app password (not the account password).
+    pub bluesky_app_password: Option<String>,
+    pub bluesky_template: Option<String>,
+    /// Endpoint receiving anonymized view/check/solve events as NDJSON
+    /// POST batches.
+    pub events_url: Option<String>,
+    /// Bearer token sent to the events endpoint.
+    pub events_token: Option<String>,
+    /// Local file events are appended to as NDJSON, when no URL is set.
+    pub events_file: Option<String>,
+    pub check_window_secs: u64,
+    /// Checks allowed per session and window, as `(min_difficulty, limit)`
+    /// tiers; checks are not throttled when empty.
+    pub check_limits: Vec<(i64, usize)>,
+    /// Items from `PUBLISH_CHECKLIST_ITEMS` a puzzle must meet before it can
+    /// be published without `force`.
+    pub publish_checklist: Vec<String>,
+    /// Seeding of custom generation requests that don't pass a `seed`.
+    pub custom_rng_seeding: Seeding,
+    /// Seeding of `/api/puzzle/random`, pooled or not, without a `seed`.
+    pub random_rng_seeding: Seeding,
+    /// Seeding of `/api/admin/puzzles/generate`.
+    pub generate_rng_seeding: Seeding,
+    /// Longest a custom generation may run, and the default for requests
+    /// without `max_millis`.
+    pub generate_max_millis: u64,
+    /// Most uniqueness checks a custom generation may spend removing clues.
+    pub generate_max_uniqueness_checks: usize,
+    /// Publish the draft dated today at 00:00 UTC.
+    pub auto_publish: bool,
+    /// Discord or Slack webhook warned when the queue runs low; the check
+    /// doesn't run when unset.
+    pub low_queue_webhook_url: Option<String>,
+    /// Days after today that should have a puzzle before the webhook fires.
+    pub low_queue_min_days: usize,
+    /// Minutes after midnight UTC during which `today` falls back to
+    /// yesterday's puzzle while today's isn't published; 0 disables it.
+    pub today_grace_minutes: u64,
+    /// Days solve records are kept before the daily retention task deletes
+    /// them; 0 keeps them forever.
+    pub solve_record_retention_days: u64,
+    /// Random puzzles kept generated ahead of requests; 0 generates each
+    /// one on request.
+    pub random_pool_size: usize,
+    /// Hours a served random puzzle can still be checked by its id; 0
+    /// keeps them forever.
+    pub random_puzzle_ttl_hours: u64,
+    /// Requests per client address and window to the public API, as
+    /// `(limit, window_secs)`; unlimited when unset.
+    pub rate_limit_cheap: Option<(usize, u64)>,
+    /// The same for the endpoints that generate puzzles.
+    pub rate_limit_expensive: Option<(usize, u64)>,
+    /// Rate limit by the last `X-Forwarded-For` address instead of the peer.
+    pub rate_limit_trust_proxy: bool,
+    /// Name of this instance in exchange documents it exports.
+    pub instance_name: Option<String>,
+    /// Base64 Ed25519 public keys of instances whose exchange signatures
+    /// are trusted on import.
+    pub exchange_trusted_keys: Vec<String>,
+}
+
+/// `unique`: the givens have a unique solution matching any stored one;
+/// `difficulty` and `title`: set on the puzzle; `test_solved`: at least one
+/// passing admin test solve; `rules`: the puzzle has custom rules text.
+pub const PUBLISH_CHECKLIST_ITEMS: &[&str] =
+    &["unique", "difficulty", "title", "test_solved", "rules"];
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            admin_token: env_string("MAKUDOKU_ADMIN_TOKEN"),
+            admin_cookie_secure: env_parse("MAKUDOKU_ADMIN_COOKIE_SECURE")?.unwrap_or(false),
+            signing_key: env_string("MAKUDOKU_SIGNING_KEY"),
+            signing_key_id: env_string("MAKUDOKU_SIGNING_KEY_ID"),
+            sync_token: env_string("MAKUDOKU_SYNC_TOKEN"),
+            mirror_upstream: env_string("MAKUDOKU_MIRROR_UPSTREAM"),
+            mirror_token: env_string("MAKUDOKU_MIRROR_TOKEN"),
+            mirror_interval_secs: env_parse("MAKUDOKU_MIRROR_INTERVAL_SECS")?.unwrap_or(300),
+            max_puzzle_json_bytes: env_parse("MAKUDOKU_MAX_PUZZLE_JSON_BYTES")?
+                .unwrap_or(64 * 1024),
+            max_svg_bytes: env_parse("MAKUDOKU_MAX_SVG_BYTES")?.unwrap_or(512 * 1024),
+            email_api_url: env_string("MAKUDOKU_EMAIL_API_URL"),
+            email_api_key: env_string("MAKUDOKU_EMAIL_API_KEY"),
+            email_from: env_string("MAKUDOKU_EMAIL_FROM"),
+            digest_recipients: env_list("MAKUDOKU_DIGEST_TO"),
+            public_url: env_string("MAKUDOKU_PUBLIC_URL"),
+            mastodon_url: env_string("MAKUDOKU_MASTODON_URL"),
+            mastodon_token: env_string("MAKUDOKU_MASTODON_TOKEN"),
+            mastodon_template: env_template("MAKUDOKU_MASTODON_TEMPLATE"),
+            bluesky_service: env_string("MAKUDOKU_BLUESKY_SERVICE")
+                .unwrap_or_else(|| "https://bsky.social".to_string()),
+            bluesky_handle: env_string("MAKUDOKU_BLUESKY_HANDLE"),
+            bluesky_app_password: env_string("MAKUDOKU_BLUESKY_APP_PASSWORD"),
+            bluesky_template: env_template("MAKUDOKU_BLUESKY_TEMPLATE"),
+            events_url: env_string("MAKUDOKU_EVENTS_URL"),
+            events_token: env_string("MAKUDOKU_EVENTS_TOKEN"),
+            events_file: env_string("MAKUDOKU_EVENTS_FILE"),
+            check_window_secs: env_parse("MAKUDOKU_CHECK_WINDOW_SECS")?.unwrap_or(60),
+            check_limits: env_tiers("MAKUDOKU_CHECK_LIMITS")?,
+            publish_checklist: publish_checklist()?,
+            custom_rng_seeding: env_seeding("MAKUDOKU_CUSTOM_RNG_SEEDING")?,
+            random_rng_seeding: env_seeding("MAKUDOKU_RANDOM_RNG_SEEDING")?,
+            generate_rng_seeding: env_seeding("MAKUDOKU_GENERATE_RNG_SEEDING")?,
+            generate_max_millis: env_parse("MAKUDOKU_GENERATE_MAX_MILLIS")?.unwrap_or(30_000),
+            generate_max_uniqueness_checks: env_parse("MAKUDOKU_GENERATE_MAX_UNIQUENESS_CHECKS")?
+                .unwrap_or(81),
+            auto_publish: env_parse("MAKUDOKU_AUTO_PUBLISH")?.unwrap_or(false),
+            low_queue_webhook_url: env_string("MAKUDOKU_LOW_QUEUE_WEBHOOK_URL"),
+            low_queue_min_days: env_parse("MAKUDOKU_LOW_QUEUE_MIN_DAYS")?.unwrap_or(7),
+            today_grace_minutes: env_parse("MAKUDOKU_TODAY_GRACE_MINUTES")?.unwrap_or(0),
+            solve_record_retention_days: env_parse("MAKUDOKU_SOLVE_RECORD_RETENTION_DAYS")?
+                .unwrap_or(365),
+            random_pool_size: env_parse("MAKUDOKU_RANDOM_POOL_SIZE")?.unwrap_or(8),
+            random_puzzle_ttl_hours: env_parse("MAKUDOKU_RANDOM_PUZZLE_TTL_HOURS")?.unwrap_or(72),
+            rate_limit_cheap: env_rate("MAKUDOKU_RATE_LIMIT")?,
+            rate_limit_expensive: env_rate("MAKUDOKU_RATE_LIMIT_EXPENSIVE")?,
+            rate_limit_trust_proxy: env_parse("MAKUDOKU_RATE_LIMIT_TRUST_PROXY")?.unwrap_or(false),
+            instance_name: env_string("MAKUDOKU_INSTANCE_NAME"),
+            exchange_trusted_keys: env_list("MAKUDOKU_EXCHANGE_TRUSTED_KEYS"),
+        })
+    }
+}
+
+fn env_string(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Post templates, with `\n` escapes turned into line breaks so they fit
+/// in a single env line.
+fn env_template(name: &str) -> Option<String> {
+    env_string(name).map(|v| v.replace("\\n", "\n"))
+}
+
+/// Comma separated values, with blanks dropped.
+fn env_list(name: &str) -> Vec<String> {
+    env_string(name)
+        .map(|v| {
+            v.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Comma separated `difficulty:limit` pairs, e.g. `1:30,7:10`.
+fn env_tiers(name: &str) -> anyhow::Result<Vec<(i64, usize)>> {
+    env_list(name)
+        .iter()
+        .map(|item| {
+            let (difficulty, limit) = item
+                .split_once(':')
+                .with_context(|| format!("{name} entries must be difficulty:limit"))?;
+            let difficulty = difficulty
+                .trim()
+                .parse()
+                .with_context(|| format!("{name} has an invalid difficulty"))?;
+            let limit: usize = limit
+                .trim()
+                .parse()
+                .with_context(|| format!("{name} has an invalid limit"))?;
+            anyhow::ensure!(limit > 0, "{name} limits must be at least 1");
+            Ok((difficulty, limit))
+        })
+        .collect()
+}
+
+/// A `limit/window_secs` rate, e.g. `120/60`.
+fn env_rate(name: &str) -> anyhow::Result<Option<(usize, u64)>> {
+    let Some(value) = env_string(name) else {
+        return Ok(None);
+    };
+    let (limit, window) = value
+        .split_once('/')
+        .with_context(|| format!("{name} must be limit/window_secs"))?;
+    let limit: usize = limit
+        .trim()
+        .parse()
+        .with_context(|| format!("{name} has an invalid limit"))?;
+    let window: u64 = window
+        .trim()
+        .parse()
+        .with_context(|| format!("{name} has an invalid window"))?;
+    anyhow::ensure!(
+        limit > 0 && window > 0,
+        "{name} limit and window must be at least 1"
+    );
+    Ok(Some((limit, window)))
+}
+
+/// `MAKUDOKU_PUBLISH_CHECKLIST`, plus `test_solved` when the older
+/// `MAKUDOKU_REQUIRE_TEST_SOLVE` is set.
+fn publish_checklist() -> anyhow::Result<Vec<String>> {
+    let mut items = env_list("MAKUDOKU_PUBLISH_CHECKLIST");
+    if let Some(item) = items
+        .iter()
+        .find(|item| !PUBLISH_CHECKLIST_ITEMS.contains(&item.as_str()))
+    {
+        anyhow::bail!(
+            "MAKUDOKU_PUBLISH_CHECKLIST has unknown item {item}; expected {}",
+            PUBLISH_CHECKLIST_ITEMS.join(", ")
+        );
+    }
+    if env_parse("MAKUDOKU_REQUIRE_TEST_SOLVE")?.unwrap_or(false) {
+        items.push("test_solved".to_string());
+    }
+    Ok(PUBLISH_CHECKLIST_ITEMS
+        .iter()
+        .filter(|item| items.iter().any(|i| i == *item))
+        .map(|item| item.to_string())
+        .collect())
+}
+
+/// `entropy` (the default) or `fixed:<seed>`.
+fn env_seeding(name: &str) -> anyhow::Result<Seeding> {
+    Ok(env_string(name)
+        .map(|v| Seeding::parse(&v))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("{name} is invalid: {e}"))?
+        .unwrap_or(Seeding::Entropy))
+}
+
+fn env_parse<T>(name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    env_string(name)
+        .map(|v| v.parse().with_context(|| format!("{name} is invalid")))
+        .transpose()
+}
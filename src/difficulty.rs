@@ -0,0 +1,1036 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Coarse difficulty players can ask random puzzles for, over the 1-10
+/// rating of `rate`.
+#[derive(Clone, Copy, Debug)]
+pub enum Band {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Band {
+    /// `easy`, `medium` or `hard`.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "easy" => Ok(Band::Easy),
+            "medium" => Ok(Band::Medium),
+            "hard" => Ok(Band::Hard),
+            _ => Err(format!(
+                "Unknown difficulty {name}; expected easy, medium or hard"
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Band::Easy => "easy",
+            Band::Medium => "medium",
+            Band::Hard => "hard",
+        }
+    }
+
+    /// Singles and simple variant deductions are easy; pointing, claiming
+    /// and pairs medium; anything harder, or unsolved by the rater, hard.
+    pub fn contains(self, difficulty: i64) -> bool {
+        match self {
+            Band::Easy => difficulty <= 3,
+            Band::Medium => (4..=6).contains(&difficulty),
+            Band::Hard => difficulty >= 7,
+        }
+    }
+}
+
+/// Digits 1-9 as bits 1-9 of a candidate mask.
+const ALL_DIGITS: u16 = 0b11_1111_1110;
+
+/// A human-style deduction, from easiest to hardest.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Technique {
+    HiddenSingle,
+    NakedSingle,
+    Kropki,
+    Thermo,
+    Cage,
+    Pointing,
+    Claiming,
+    NakedPair,
+    HiddenPair,
+    XWing,
+}
+
+impl Technique {
+    /// Rough 1-10 difficulty of spotting the technique.
+    fn level(self) -> i64 {
+        match self {
+            Technique::HiddenSingle => 1,
+            Technique::NakedSingle => 2,
+            Technique::Kropki | Technique::Thermo | Technique::Cage => 3,
+            Technique::Pointing | Technique::Claiming => 4,
+            Technique::NakedPair => 5,
+            Technique::HiddenPair => 6,
+            Technique::XWing => 8,
+        }
+    }
+}
+
+/// One deduction: a placed digit or removed candidates, and the cells it
+/// follows from.
+pub struct Step {
+    pub technique: Technique,
+    pub cells: Vec<usize>,
+    pub placement: Option<(usize, u8)>,
+    pub eliminations: Vec<(usize, u8)>,
+}
+
+#[derive(Serialize)]
+pub struct TechniqueCount {
+    pub technique: Technique,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct Rating {
+    /// 1-10, from the hardest technique needed; 10 when the techniques run
+    /// out before the grid is full.
+    pub difficulty: i64,
+    pub solved: bool,
+    /// How often each technique was used, easiest first.
+    pub techniques: Vec<TechniqueCount>,
+    /// Constraint types the rater can't reason with. They are ignored,
+    /// which can only make a puzzle rate harder than it is.
+    pub unmodelled: Vec<String>,
+}
+
+/// Rates a puzzle by solving it with human-style techniques, easiest
+/// first, and taking the hardest one it needed.
+pub fn rate(
+    puzzle: &str,
+    constraints: &[Value],
+    regions: Option<&[Vec<(usize, usize)>]>,
+) -> Result<Rating, String> {
+    let rules = Rules::new(constraints, regions);
+    let mut solver = Solver::new(&rules, puzzle)?;
+    let solved = solver.run()?;
+
+    let mut techniques: Vec<TechniqueCount> = Vec::new();
+    let mut used: Vec<Technique> = solver.steps.iter().map(|s| s.technique).collect();
+    used.sort();
+    for technique in used {
+        match techniques.last_mut() {
+            Some(last) if last.technique == technique => last.count += 1,
+            _ => techniques.push(TechniqueCount {
+                technique,
+                count: 1,
+            }),
+        }
+    }
+
+    let difficulty = match techniques.last() {
+        _ if !solved => 10,
+        Some(hardest) => {
+            let level = hardest.technique.level();
+            // Needing an advanced technique again and again is harder
+            // than needing it once.
+            let repeated = level >= 4 && hardest.count >= 3;
+            (level + i64::from(repeated)).min(9)
+        }
+        None => 1,
+    };
+    Ok(Rating {
+        difficulty,
+        solved,
+        techniques,
+        unmodelled: rules.unmodelled.clone(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct CellDigit {
+    pub cell: [usize; 2],
+    pub digit: u8,
+}
+
+impl CellDigit {
+    fn new((cell, digit): (usize, u8)) -> Self {
+        Self {
+            cell: [cell / 9, cell % 9],
+            digit,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PathStep {
+    pub technique: Technique,
+    /// The cells the deduction follows from, e.g. the house of a hidden
+    /// single or the corners of an X-wing.
+    pub cells: Vec<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placement: Option<CellDigit>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub eliminations: Vec<CellDigit>,
+}
+
+#[derive(Serialize)]
+pub struct SolvePath {
+    pub solved: bool,
+    pub steps: Vec<PathStep>,
+    /// The grid where the techniques ran out, `.` for empty cells.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stuck_at: Option<String>,
+    pub unmodelled: Vec<String>,
+}
+
+/// Every step `rate` takes, in order, so setters can check the intended
+/// break-in is the one a solver finds.
+pub fn solve_path(
+    puzzle: &str,
+    constraints: &[Value],
+    regions: Option<&[Vec<(usize, usize)>]>,
+) -> Result<SolvePath, String> {
+    let rules = Rules::new(constraints, regions);
+    let mut solver = Solver::new(&rules, puzzle)?;
+    let solved = solver.run()?;
+    let stuck_at = (!solved).then(|| {
+        solver
+            .values
+            .iter()
+            .map(|v| match v {
+                0 => '.',
+                _ => (b'0' + v) as char,
+            })
+            .collect()
+    });
+    let steps = solver
+        .steps
+        .into_iter()
+        .map(|step| PathStep {
+            technique: step.technique,
+            cells: step.cells.iter().map(|c| [c / 9, c % 9]).collect(),
+            placement: step.placement.map(CellDigit::new),
+            eliminations: step.eliminations.into_iter().map(CellDigit::new).collect(),
+        })
+        .collect();
+    Ok(SolvePath {
+        solved,
+        steps,
+        stuck_at,
+        unmodelled: rules.unmodelled.clone(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct PencilMarks {
+    /// Per cell, row by row; empty for filled cells.
+    pub candidates: Vec<Vec<u8>>,
+    /// Constraint types left out, so the marks may allow more than the
+    /// puzzle does.
+    pub unmodelled: Vec<String>,
+}
+
+/// Pencil marks for a partly filled grid: the digits each empty cell can
+/// still take, given its peers and what the kropki dots, thermometers and
+/// cages rule out. Conflicting entries aren't an error; they just leave
+/// fewer candidates.
+pub fn candidates(
+    cells: &[Option<u8>],
+    constraints: &[Value],
+    regions: Option<&[Vec<(usize, usize)>]>,
+) -> PencilMarks {
+    let rules = Rules::new(constraints, regions);
+    let mut solver = Solver::empty(&rules);
+    for (cell, digit) in cells.iter().enumerate() {
+        if let Some(digit) = digit {
+            solver.place(cell, *digit);
+        }
+    }
+    while let Some(step) = solver.variant_step() {
+        solver.apply(step);
+    }
+    let candidates = (0..81)
+        .map(|cell| match solver.values[cell] {
+            0 => digits(solver.candidates[cell]).collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+    PencilMarks {
+        candidates,
+        unmodelled: rules.unmodelled.clone(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HouseKind {
+    Row,
+    Col,
+    Region,
+    /// Diagonals, windoku boxes and other extra regions.
+    Extra,
+}
+
+/// Nine cells holding each digit once.
+struct House {
+    kind: HouseKind,
+    cells: Vec<usize>,
+}
+
+struct Cage {
+    cells: Vec<usize>,
+    sum: u32,
+    distinct: bool,
+}
+
+/// What the rater knows of a puzzle's constraints.
+struct Rules {
+    houses: Vec<House>,
+    /// Cells that can't share a digit with each cell.
+    peers: Vec<Vec<usize>>,
+    /// Dot pairs; `true` for black (one digit double the other).
+    kropki: Vec<(usize, usize, bool)>,
+    /// Cells along each thermometer, bulb first.
+    thermos: Vec<Vec<usize>>,
+    cages: Vec<Cage>,
+    unmodelled: Vec<String>,
+}
+
+impl Rules {
+    fn new(constraints: &[Value], regions: Option<&[Vec<(usize, usize)>]>) -> Self {
+        let mut houses = Vec::new();
+        for i in 0..9 {
+            houses.push(House {
+                kind: HouseKind::Row,
+                cells: (0..9).map(|c| i * 9 + c).collect(),
+            });
+            houses.push(House {
+                kind: HouseKind::Col,
+                cells: (0..9).map(|r| r * 9 + i).collect(),
+            });
+        }
+        match regions {
+            Some(regions) => {
+                for region in regions {
+                    houses.push(House {
+                        kind: HouseKind::Region,
+                        cells: region.iter().map(|(r, c)| r * 9 + c).collect(),
+                    });
+                }
+            }
+            None => {
+                for b in 0..9 {
+                    houses.push(House {
+                        kind: HouseKind::Region,
+                        cells: box_cells(b / 3 * 3, b % 3 * 3),
+                    });
+                }
+            }
+        }
+
+        let mut rules = Rules {
+            houses,
+            peers: Vec::new(),
+            kropki: Vec::new(),
+            thermos: Vec::new(),
+            cages: Vec::new(),
+            unmodelled: Vec::new(),
+        };
+        // Sets of cells with distinct digits that aren't whole houses.
+        let mut distinct: Vec<Vec<usize>> = Vec::new();
+        let mut moves: Vec<(isize, isize)> = Vec::new();
+        for constraint in constraints {
+            let kind = constraint.get("type").and_then(Value::as_str).unwrap_or("");
+            let modelled = match kind {
+                "diagonal" => {
+                    let which = constraint.get("which").and_then(Value::as_str);
+                    if which != Some("anti") {
+                        rules.add_extra((0..9).map(|i| i * 9 + i).collect());
+                    }
+                    if which != Some("main") {
+                        rules.add_extra((0..9).map(|i| i * 9 + 8 - i).collect());
+                    }
+                    true
+                }
+                "windoku" => {
+                    for (r, c) in [(1, 1), (1, 5), (5, 1), (5, 5)] {
+                        rules.add_extra(box_cells(r, c));
+                    }
+                    true
+                }
+                "disjoint_groups" => {
+                    for k in 0..9 {
+                        rules.add_extra(
+                            (0..81)
+                                .filter(|cell| (cell / 9 % 3) * 3 + cell % 3 == k)
+                                .collect(),
+                        );
+                    }
+                    true
+                }
+                "extra_region" => match cell_list(constraint.get("cells")) {
+                    Some(cells) if cells.len() == 9 => {
+                        rules.add_extra(cells);
+                        true
+                    }
+                    _ => false,
+                },
+                "color_region" => {
+                    let is_distinct = constraint
+                        .get("distinct")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                    match cell_list(constraint.get("cells")) {
+                        Some(cells) if is_distinct && cells.len() == 9 => rules.add_extra(cells),
+                        Some(cells) if is_distinct => distinct.push(cells),
+                        // Colors alone don't restrict digits.
+                        _ => {}
+                    }
+                    true
+                }
+                "king" => {
+                    moves.extend([(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+                    true
+                }
+                "knight" => {
+                    moves.extend([
+                        (1, 2),
+                        (2, 1),
+                        (-1, 2),
+                        (-2, 1),
+                        (1, -2),
+                        (2, -1),
+                        (-1, -2),
+                        (-2, -1),
+                    ]);
+                    true
+                }
+                "kropki_white" | "kropki_black" => {
+                    match (cell(constraint.get("a")), cell(constraint.get("b"))) {
+                        (Some(a), Some(b)) => {
+                            rules.kropki.push((a, b, kind == "kropki_black"));
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                "thermo" => rules.add_thermos(constraint),
+                "killer" => {
+                    let sum = constraint.get("sum").and_then(Value::as_u64);
+                    match (cell_list(constraint.get("cells")), sum) {
+                        (Some(cells), Some(sum)) => {
+                            let no_repeats = constraint
+                                .get("no_repeats")
+                                .and_then(Value::as_bool)
+                                .unwrap_or(true);
+                            if no_repeats {
+                                distinct.push(cells.clone());
+                            }
+                            rules.cages.push(Cage {
+                                cells,
+                                sum: sum as u32,
+                                distinct: no_repeats,
+                            });
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+            if !modelled && !rules.unmodelled.iter().any(|k| k == kind) {
+                rules.unmodelled.push(kind.to_string());
+            }
+        }
+
+        let mut seen = vec![[false; 81]; 81];
+        for set in rules.houses.iter().map(|h| &h.cells).chain(&distinct) {
+            for &a in set {
+                for &b in set {
+                    seen[a][b] = true;
+                }
+            }
+        }
+        for (cell, row) in seen.iter_mut().enumerate() {
+            let (r, c) = ((cell / 9) as isize, (cell % 9) as isize);
+            for (dr, dc) in &moves {
+                let (r2, c2) = (r + dr, c + dc);
+                if (0..9).contains(&r2) && (0..9).contains(&c2) {
+                    row[(r2 * 9 + c2) as usize] = true;
+                }
+            }
+        }
+        rules.peers = (0..81)
+            .map(|cell| (0..81).filter(|&o| o != cell && seen[cell][o]).collect())
+            .collect();
+        rules
+    }
+
+    fn add_extra(&mut self, cells: Vec<usize>) {
+        self.houses.push(House {
+            kind: HouseKind::Extra,
+            cells,
+        });
+    }
+
+    /// A `path`, or a `bulb` with `branches` leading away from it.
+    fn add_thermos(&mut self, constraint: &Value) -> bool {
+        if let Some(path) = cell_list(constraint.get("path")) {
+            self.thermos.push(path);
+            return true;
+        }
+        let Some(bulb) = cell(constraint.get("bulb")) else {
+            return false;
+        };
+        let Some(branches) = constraint.get("branches").and_then(Value::as_array) else {
+            return false;
+        };
+        for branch in branches {
+            let Some(branch) = cell_list(Some(branch)) else {
+                return false;
+            };
+            let mut line = vec![bulb];
+            line.extend(branch.into_iter().filter(|cell| *cell != bulb));
+            self.thermos.push(line);
+        }
+        true
+    }
+}
+
+fn box_cells(top: usize, left: usize) -> Vec<usize> {
+    (0..9).map(|i| (top + i / 3) * 9 + left + i % 3).collect()
+}
+
+fn cell(value: Option<&Value>) -> Option<usize> {
+    let pair = value?.as_array()?;
+    let r = pair.first()?.as_u64()? as usize;
+    let c = pair.get(1)?.as_u64()? as usize;
+    (r < 9 && c < 9).then_some(r * 9 + c)
+}
+
+fn cell_list(value: Option<&Value>) -> Option<Vec<usize>> {
+    value?.as_array()?.iter().map(|v| cell(Some(v))).collect()
+}
+
+fn bit(digit: u8) -> u16 {
+    1 << digit
+}
+
+fn digits(mask: u16) -> impl Iterator<Item = u8> {
+    (1..=9).filter(move |d| mask & bit(*d) != 0)
+}
+
+struct Solver<'a> {
+    rules: &'a Rules,
+    candidates: [u16; 81],
+    values: [u8; 81],
+    steps: Vec<Step>,
+}
+
+impl<'a> Solver<'a> {
+    fn empty(rules: &'a Rules) -> Self {
+        Solver {
+            rules,
+            candidates: [ALL_DIGITS; 81],
+            values: [0; 81],
+            steps: Vec::new(),
+        }
+    }
+
+    fn new(rules: &'a Rules, puzzle: &str) -> Result<Self, String> {
+        if puzzle.chars().count() != 81 {
+            return Err("puzzle must be exactly 81 characters".to_string());
+        }
+        let mut solver = Solver::empty(rules);
+        for (cell, ch) in puzzle.chars().enumerate() {
+            let Some(digit) = ch.to_digit(10).filter(|d| *d > 0) else {
+                continue;
+            };
+            let digit = digit as u8;
+            if solver.candidates[cell] & bit(digit) == 0 {
+                return Err(format!(
+                    "given {digit} at [{}, {}] repeats a digit it can see",
+                    cell / 9,
+                    cell % 9
+                ));
+            }
+            solver.place(cell, digit);
+        }
+        Ok(solver)
+    }
+
+    /// Applies techniques until the grid is full or none makes progress.
+    /// Returns whether the grid was filled.
+    fn run(&mut self) -> Result<bool, String> {
+        loop {
+            if self.values.iter().all(|v| *v != 0) {
+                return Ok(true);
+            }
+            let step = self
+                .hidden_single()
+                .or_else(|| self.naked_single())
+                .or_else(|| self.variant_step())
+                .or_else(|| self.locked_candidates())
+                .or_else(|| self.naked_pair())
+                .or_else(|| self.hidden_pair())
+                .or_else(|| self.x_wing());
+            let Some(step) = step else {
+                return Ok(false);
+            };
+            self.apply(step);
+            if let Some(cell) = (0..81).find(|&cell| self.candidates[cell] == 0) {
+                return Err(format!(
+                    "puzzle has no solution: no digit fits [{}, {}]",
+                    cell / 9,
+                    cell % 9
+                ));
+            }
+        }
+    }
+
+    fn apply(&mut self, step: Step) {
+        if let Some((cell, digit)) = step.placement {
+            self.place(cell, digit);
+        }
+        for &(cell, digit) in &step.eliminations {
+            self.candidates[cell] &= !bit(digit);
+        }
+        self.steps.push(step);
+    }
+
+    fn place(&mut self, cell: usize, digit: u8) {
+        self.values[cell] = digit;
+        self.candidates[cell] = bit(digit);
+        for &peer in &self.rules.peers[cell] {
+            self.candidates[peer] &= !bit(digit);
+        }
+    }
+
+    /// Empty cells of `cells` that can still hold `digit`.
+    fn positions(&self, cells: &[usize], digit: u8) -> Vec<usize> {
+        cells
+            .iter()
+            .copied()
+            .filter(|&cell| self.values[cell] == 0 && self.candidates[cell] & bit(digit) != 0)
+            .collect()
+    }
+
+    /// Candidates outside `keep` in each cell, as eliminations.
+    fn eliminations(&self, cells: &[usize], keep: impl Fn(usize) -> u16) -> Vec<(usize, u8)> {
+        cells
+            .iter()
+            .filter(|&&cell| self.values[cell] == 0)
+            .flat_map(|&cell| {
+                digits(self.candidates[cell] & !keep(cell)).map(move |digit| (cell, digit))
+            })
+            .collect()
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for house in &self.rules.houses {
+            for digit in 1..=9 {
+                if house.cells.iter().any(|&cell| self.values[cell] == digit) {
+                    continue;
+                }
+                if let [cell] = self.positions(&house.cells, digit)[..] {
+                    return Some(Step {
+                        technique: Technique::HiddenSingle,
+                        cells: house.cells.clone(),
+                        placement: Some((cell, digit)),
+                        eliminations: Vec::new(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn naked_single(&self) -> Option<Step> {
+        let cell = (0..81)
+            .find(|&cell| self.values[cell] == 0 && self.candidates[cell].count_ones() == 1)?;
+        Some(Step {
+            technique: Technique::NakedSingle,
+            cells: vec![cell],
+            placement: Some((cell, self.candidates[cell].trailing_zeros() as u8)),
+            eliminations: Vec::new(),
+        })
+    }
+
+    /// The first kropki dot, thermometer or cage that rules out a
+    /// candidate.
+    fn variant_step(&self) -> Option<Step> {
+        let step = |technique, cells: &[usize], eliminations: Vec<(usize, u8)>| {
+            (!eliminations.is_empty()).then(|| Step {
+                technique,
+                cells: cells.to_vec(),
+                placement: None,
+                eliminations,
+            })
+        };
+        for &(a, b, black) in &self.rules.kropki {
+            let fits = |x: u8, y: u8| {
+                if black {
+                    x == 2 * y || y == 2 * x
+                } else {
+                    x.abs_diff(y) == 1
+                }
+            };
+            let supported = |from: usize, to: usize| {
+                digits(self.candidates[from])
+                    .filter(|&x| digits(self.candidates[to]).any(|y| fits(x, y)))
+                    .fold(0, |mask, x| mask | bit(x))
+            };
+            let (keep_a, keep_b) = (supported(a, b), supported(b, a));
+            let eliminations =
+                self.eliminations(&[a, b], |cell| if cell == a { keep_a } else { keep_b });
+            if let Some(step) = step(Technique::Kropki, &[a, b], eliminations) {
+                return Some(step);
+            }
+        }
+        for line in &self.rules.thermos {
+            let keep = self.thermo_masks(line);
+            let eliminations = self.eliminations(line, |cell| {
+                line.iter()
+                    .zip(&keep)
+                    .filter(|(c, _)| **c == cell)
+                    .fold(ALL_DIGITS, |mask, (_, keep)| mask & keep)
+            });
+            if let Some(step) = step(Technique::Thermo, line, eliminations) {
+                return Some(step);
+            }
+        }
+        for cage in &self.rules.cages {
+            let keep = self.cage_masks(cage);
+            let eliminations = self.eliminations(&cage.cells, |cell| {
+                let i = cage.cells.iter().position(|c| *c == cell).unwrap_or(0);
+                keep[i]
+            });
+            if let Some(step) = step(Technique::Cage, &cage.cells, eliminations) {
+                return Some(step);
+            }
+        }
+        None
+    }
+
+    /// Digits each thermometer cell can take while the line still rises.
+    fn thermo_masks(&self, line: &[usize]) -> Vec<u16> {
+        let mut masks: Vec<u16> = line.iter().map(|&cell| self.candidates[cell]).collect();
+        let mut low = 0;
+        for mask in masks.iter_mut() {
+            *mask &= !((bit(low) << 1) - 1);
+            low = digits(*mask).next().unwrap_or(9);
+        }
+        let mut high = 10;
+        for mask in masks.iter_mut().rev() {
+            *mask &= bit(high) - 1;
+            high = digits(*mask).last().unwrap_or(1);
+        }
+        masks
+    }
+
+    /// Digits each cage cell can take in some way of reaching the sum.
+    fn cage_masks(&self, cage: &Cage) -> Vec<u16> {
+        let candidates: Vec<u16> = cage.cells.iter().map(|&c| self.candidates[c]).collect();
+        if !cage.distinct {
+            // Without distinct digits only the sum's bounds are used.
+            let low: u32 = candidates.iter().map(|m| m.trailing_zeros()).sum();
+            let high: u32 = candidates.iter().map(|m| 15 - m.leading_zeros()).sum();
+            return candidates
+                .iter()
+                .map(|&mask| {
+                    let (lo, hi) = (mask.trailing_zeros(), 15 - mask.leading_zeros());
+                    digits(mask)
+                        .filter(|&d| {
+                            let d = u32::from(d);
+                            low - lo + d <= cage.sum && high - hi + d >= cage.sum
+                        })
+                        .fold(0, |keep, d| keep | bit(d))
+                })
+                .collect();
+        }
+
+        // Every set of distinct digits with the right size and sum that the
+        // cells could hold between them.
+        let mut keep = vec![0u16; candidates.len()];
+        for combo in (0..=ALL_DIGITS).step_by(2) {
+            if combo.count_ones() as usize != candidates.len()
+                || digits(combo).map(u32::from).sum::<u32>() != cage.sum
+            {
+                continue;
+            }
+            let fits = candidates.iter().all(|m| m & combo != 0)
+                && candidates.iter().fold(0, |union, m| union | (m & combo)) == combo;
+            if fits {
+                for (keep, m) in keep.iter_mut().zip(&candidates) {
+                    *keep |= m & combo;
+                }
+            }
+        }
+        keep
+    }
+
+    /// A digit confined to where two houses overlap can go nowhere else
+    /// in either.
+    fn locked_candidates(&self) -> Option<Step> {
+        for (i, house) in self.rules.houses.iter().enumerate() {
+            for (j, other) in self.rules.houses.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                for digit in 1..=9 {
+                    let positions = self.positions(&house.cells, digit);
+                    if positions.len() < 2 || !positions.iter().all(|c| other.cells.contains(c)) {
+                        continue;
+                    }
+                    let rest: Vec<usize> = other
+                        .cells
+                        .iter()
+                        .copied()
+                        .filter(|c| !house.cells.contains(c))
+                        .collect();
+                    let eliminations = self.eliminations(&rest, |_| !bit(digit));
+                    if eliminations.is_empty() {
+                        continue;
+                    }
+                    let technique = match house.kind {
+                        HouseKind::Row | HouseKind::Col => Technique::Claiming,
+                        HouseKind::Region | HouseKind::Extra => Technique::Pointing,
+                    };
+                    return Some(Step {
+                        technique,
+                        cells: positions,
+                        placement: None,
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn naked_pair(&self) -> Option<Step> {
+        for house in &self.rules.houses {
+            let pairs: Vec<usize> = house
+                .cells
+                .iter()
+                .copied()
+                .filter(|&c| self.values[c] == 0 && self.candidates[c].count_ones() == 2)
+                .collect();
+            for (i, &a) in pairs.iter().enumerate() {
+                for &b in &pairs[i + 1..] {
+                    let mask = self.candidates[a];
+                    if self.candidates[b] != mask {
+                        continue;
+                    }
+                    let rest: Vec<usize> = house
+                        .cells
+                        .iter()
+                        .copied()
+                        .filter(|&c| c != a && c != b)
+                        .collect();
+                    let eliminations = self.eliminations(&rest, |_| !mask);
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: Technique::NakedPair,
+                            cells: vec![a, b],
+                            placement: None,
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn hidden_pair(&self) -> Option<Step> {
+        for house in &self.rules.houses {
+            for first in 1..=8 {
+                let cells = self.positions(&house.cells, first);
+                if cells.len() != 2 {
+                    continue;
+                }
+                for second in first + 1..=9 {
+                    if self.positions(&house.cells, second) != cells {
+                        continue;
+                    }
+                    let mask = bit(first) | bit(second);
+                    let eliminations = self.eliminations(&cells, |_| mask);
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: Technique::HiddenPair,
+                            cells,
+                            placement: None,
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A digit limited to the same two columns in two rows is limited to
+    /// those rows in both columns, and the same with rows and columns
+    /// swapped.
+    fn x_wing(&self) -> Option<Step> {
+        for (base, cover) in [
+            (HouseKind::Row, HouseKind::Col),
+            (HouseKind::Col, HouseKind::Row),
+        ] {
+            let bases: Vec<&House> = self
+                .rules
+                .houses
+                .iter()
+                .filter(|h| h.kind == base)
+                .collect();
+            let covers: Vec<&House> = self
+                .rules
+                .houses
+                .iter()
+                .filter(|h| h.kind == cover)
+                .collect();
+            for digit in 1..=9 {
+                let lines: Vec<Vec<usize>> = bases
+                    .iter()
+                    .map(|house| self.positions(&house.cells, digit))
+                    .collect();
+                for (i, first) in lines.iter().enumerate() {
+                    if first.len() != 2 {
+                        continue;
+                    }
+                    for second in &lines[i + 1..] {
+                        if second.len() != 2 {
+                            continue;
+                        }
+                        let corners: Vec<usize> = first.iter().chain(second).copied().collect();
+                        let crossing: Vec<&House> = covers
+                            .iter()
+                            .copied()
+                            .filter(|h| h.cells.contains(&first[0]) || h.cells.contains(&first[1]))
+                            .collect();
+                        let aligned = second
+                            .iter()
+                            .all(|c| crossing.iter().any(|h| h.cells.contains(c)));
+                        if !aligned {
+                            continue;
+                        }
+                        let rest: Vec<usize> = crossing
+                            .iter()
+                            .flat_map(|h| h.cells.iter().copied())
+                            .filter(|c| !corners.contains(c))
+                            .collect();
+                        let eliminations = self.eliminations(&rest, |_| !bit(digit));
+                        if !eliminations.is_empty() {
+                            return Some(Step {
+                                technique: Technique::XWing,
+                                cells: corners,
+                                placement: None,
+                                eliminations,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGLES: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const CLAIMING: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    const X_WING: &str =
+        "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5";
+    const STUCK: &str =
+        "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1";
+
+    fn counts(rating: &Rating) -> Vec<(Technique, usize)> {
+        rating
+            .techniques
+            .iter()
+            .map(|t| (t.technique, t.count))
+            .collect()
+    }
+
+    #[test]
+    fn hidden_singles_rate_one() {
+        let rating = rate(SINGLES, &[], None).unwrap();
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty, 1);
+        assert_eq!(counts(&rating), [(Technique::HiddenSingle, 51)]);
+
+        let path = solve_path(SINGLES, &[], None).unwrap();
+        assert_eq!(path.steps.len(), 51);
+        let first = &path.steps[0];
+        assert_eq!(first.technique, Technique::HiddenSingle);
+        let placement = first.placement.as_ref().unwrap();
+        assert_eq!((placement.cell, placement.digit), ([6, 0], 9));
+    }
+
+    #[test]
+    fn repeated_claiming_rates_one_above_its_level() {
+        let rating = rate(CLAIMING, &[], None).unwrap();
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty, 5);
+        assert_eq!(
+            counts(&rating),
+            [
+                (Technique::HiddenSingle, 60),
+                (Technique::NakedSingle, 4),
+                (Technique::Pointing, 1),
+                (Technique::Claiming, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn x_wing_rates_eight() {
+        let rating = rate(X_WING, &[], None).unwrap();
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty, 8);
+        assert_eq!(
+            counts(&rating),
+            [
+                (Technique::HiddenSingle, 35),
+                (Technique::Claiming, 2),
+                (Technique::HiddenPair, 1),
+                (Technique::XWing, 1),
+            ]
+        );
+
+        let path = solve_path(X_WING, &[], None).unwrap();
+        let first = &path.steps[0];
+        assert_eq!(first.technique, Technique::Claiming);
+        assert!(first.placement.is_none());
+        let eliminations: Vec<_> = first
+            .eliminations
+            .iter()
+            .map(|e| (e.cell, e.digit))
+            .collect();
+        assert_eq!(eliminations, [([8, 3], 9)]);
+    }
+
+    #[test]
+    fn stuck_puzzles_rate_ten() {
+        let rating = rate(STUCK, &[], None).unwrap();
+        assert!(!rating.solved);
+        assert_eq!(rating.difficulty, 10);
+
+        let path = solve_path(STUCK, &[], None).unwrap();
+        assert!(path.steps.is_empty());
+        assert_eq!(path.stuck_at.as_deref(), Some(STUCK));
+    }
+
+    #[test]
+    fn unknown_constraints_are_reported() {
+        let constraints = [serde_json::json!({ "type": "arrow" })];
+        let rating = rate(SINGLES, &constraints, None).unwrap();
+        assert_eq!(rating.unmodelled, ["arrow"]);
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Context, bail};
+use chrono::{Datelike, Duration as DateDuration, NaiveDate, Timelike, Utc, Weekday};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use crate::{config::Config, store};
+
+/// Digests go out on Monday at this UTC hour.
+const SEND_HOUR_UTC: u32 = 8;
+
+#[derive(Serialize)]
+pub struct WeeklyDigest {
+    pub site_name: String,
+    /// First day of the coming week (the day the digest is composed).
+    pub week_start: String,
+    /// Days in the coming week with no puzzle at all (deleted ones don't
+    /// count).
+    pub schedule_gaps: Vec<String>,
+    /// Days in the coming week whose puzzle is still a draft.
+    pub drafts: Vec<String>,
+    pub last_week: LastWeekStats,
+    /// Ratings aren't tracked, so this is last week's most solved puzzle.
+    pub top_puzzle: Option<TopPuzzle>,
+}
+
+#[derive(Serialize)]
+pub struct LastWeekStats {
+    pub from: String,
+    pub to: String,
+    pub views: i64,
+    pub checks: i64,
+    pub solves: i64,
+}
+
+#[derive(Serialize)]
+pub struct TopPuzzle {
+    pub date_utc: String,
+    pub title: Option<String>,
+    pub solves: i64,
+}
+
+impl WeeklyDigest {
+    pub fn subject(&self) -> String {
+        format!(
+            "{} weekly digest, week of {}",
+            self.site_name, self.week_start
+        )
+    }
+
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "Last week ({} to {}): {} views, {} checks, {} solves.\n",
+            self.last_week.from,
+            self.last_week.to,
+            self.last_week.views,
+            self.last_week.checks,
+            self.last_week.solves,
+        ));
+        match &self.top_puzzle {
+            Some(top) => out.push_str(&format!(
+                "Most solved: {} ({}), {} solves.\n",
+                top.title.as_deref().unwrap_or("untitled"),
+                top.date_utc,
+                top.solves,
+            )),
+            None => out.push_str("No puzzles were solved last week.\n"),
+        }
+        if self.schedule_gaps.is_empty() {
+            out.push_str("Every day of the coming week has a puzzle.\n");
+        } else {
+            out.push_str(&format!(
+                "No puzzle yet for: {}.\n",
+                self.schedule_gaps.join(", ")
+            ));
+        }
+        if !self.drafts.is_empty() {
+            out.push_str(&format!("Still drafts: {}.\n", self.drafts.join(", ")));
+        }
+        out
+    }
+}
+
+/// Builds the digest for the week starting `today`, looking back over the
+/// seven days before it.
+pub async fn compose(db: &SqlitePool, today: NaiveDate) -> anyhow::Result<WeeklyDigest> {
+    let week_end = (today + DateDuration::days(6)).to_string();
+    let last_from = (today - DateDuration::days(7)).to_string();
+    let last_to = (today - DateDuration::days(1)).to_string();
+    let week_start = today.to_string();
+
+    let planned = store::puzzles_between(db, &week_start, &week_end).await?;
+    let scheduled: HashSet<&str> = planned.iter().map(|row| row.date_utc.as_str()).collect();
+    let schedule_gaps = (0..7)
+        .map(|offset| (today + DateDuration::days(offset)).to_string())
+        .filter(|date| !scheduled.contains(date.as_str()))
+        .collect();
+    let mut drafts: Vec<String> = planned
+        .iter()
+        .filter(|row| row.status == "draft")
+        .map(|row| row.date_utc.clone())
+        .collect();
+    drafts.sort();
+
+    let totals = store::stats_totals(db, &last_from, &last_to).await?;
+
+    let top_puzzle = store::most_solved(db, &last_from, &last_to)
+        .await?
+        .map(|row| TopPuzzle {
+            date_utc: row.date_utc,
+            title: row.title,
+            solves: row.solves,
+        });
+
+    Ok(WeeklyDigest {
+        site_name: crate::branding::load_or_default(db).await.site_name,
+        week_start,
+        schedule_gaps,
+        drafts,
+        last_week: LastWeekStats {
+            from: last_from,
+            to: last_to,
+            views: totals.views,
+            checks: totals.checks,
+            solves: totals.solves,
+        },
+        top_puzzle,
+    })
+}
+
+/// Posts the digest to the configured HTTP email API as
+/// `{ from, to, subject, text }`.
+pub async fn send(config: &Config, digest: &WeeklyDigest) -> anyhow::Result<()> {
+    let Some(url) = config.email_api_url.as_deref() else {
+        bail!("MAKUDOKU_EMAIL_API_URL is not set");
+    };
+    if config.digest_recipients.is_empty() {
+        bail!("MAKUDOKU_DIGEST_TO is not set");
+    }
+
+    let mut request = reqwest::Client::new().post(url).json(&serde_json::json!({
+        "from": config.email_from,
+        "to": config.digest_recipients,
+        "subject": digest.subject(),
+        "text": digest.text(),
+    }));
+    if let Some(key) = config.email_api_key.as_deref() {
+        request = request.bearer_auth(key);
+    }
+    request
+        .send()
+        .await
+        .context("email request failed")?
+        .error_for_status()
+        .context("email provider returned an error")?;
+    Ok(())
+}
+
+/// Sends the digest once per week, on Monday after `SEND_HOUR_UTC`. Sent
+/// weeks are recorded in `digest_log` so restarts don't send twice.
+pub async fn run_weekly_loop(db: SqlitePool, config: Arc<Config>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(15 * 60));
+    loop {
+        ticker.tick().await;
+        let now = Utc::now();
+        if now.weekday() != Weekday::Mon || now.hour() < SEND_HOUR_UTC {
+            continue;
+        }
+        if let Err(e) = send_if_due(&db, &config, now.date_naive()).await {
+            tracing::warn!("weekly digest failed: {e:#}");
+        }
+    }
+}
+
+async fn send_if_due(db: &SqlitePool, config: &Config, today: NaiveDate) -> anyhow::Result<()> {
+    let week_start = today.to_string();
+    if store::digest_sent(db, &week_start).await? {
+        return Ok(());
+    }
+
+    let digest = compose(db, today).await?;
+    send(config, &digest).await?;
+    store::record_digest(db, &week_start).await?;
+    tracing::info!("sent weekly digest for {week_start}");
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anyhow::Context;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::{path::PathBuf, time::Duration};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+use crate::config::Config;
+
+/// Events buffered between the handlers and the writer; further events are
+/// dropped while the buffer is full.
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// A batch is written once it reaches this size, or on the next flush tick.
+const BATCH_SIZE: usize = 500;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// One engagement event. Nothing identifying the player is recorded: no
+/// address, user agent, cookie or grid.
+#[derive(Serialize)]
+pub struct Event {
+    pub event: &'static str,
+    pub date_utc: String,
+    pub at_utc: String,
+}
+
+enum Sink {
+    Http {
+        client: reqwest::Client,
+        url: String,
+        token: Option<String>,
+    },
+    File(PathBuf),
+}
+
+/// Handle for queueing view/check/solve events to the configured NDJSON
+/// sink. Recording never waits on the sink.
+#[derive(Clone)]
+pub struct EventStream {
+    tx: mpsc::Sender<Event>,
+}
+
+impl EventStream {
+    /// Starts the writer task when `MAKUDOKU_EVENTS_URL` or
+    /// `MAKUDOKU_EVENTS_FILE` is set; the URL wins if both are.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let sink = match (&config.events_url, &config.events_file) {
+            (Some(url), _) => Sink::Http {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+                token: config.events_token.clone(),
+            },
+            (None, Some(path)) => Sink::File(PathBuf::from(path)),
+            (None, None) => return None,
+        };
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run_writer(sink, rx));
+        Some(Self { tx })
+    }
+
+    pub fn record(&self, event: &'static str, date_utc: &str) {
+        let event = Event {
+            event,
+            date_utc: date_utc.to_string(),
+            at_utc: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        };
+        if self.tx.try_send(event).is_err() {
+            tracing::debug!("event stream queue full, dropping event");
+        }
+    }
+}
+
+async fn run_writer(sink: Sink, mut rx: mpsc::Receiver<Event>) {
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+                batch.push(event);
+                if batch.len() < BATCH_SIZE {
+                    continue;
+                }
+            }
+            _ = ticker.tick() => {
+                if batch.is_empty() {
+                    continue;
+                }
+            }
+        }
+        // A failed batch is dropped rather than retried, so an unreachable
+        // endpoint can't grow memory without bound.
+        if let Err(e) = write_batch(&sink, &batch).await {
+            tracing::warn!("event stream write failed: {e:#}");
+        }
+        batch.clear();
+    }
+    if !batch.is_empty() {
+        let _ = write_batch(&sink, &batch).await;
+    }
+}
+
+async fn write_batch(sink: &Sink, batch: &[Event]) -> anyhow::Result<()> {
+    let mut body = String::new();
+    for event in batch {
+        body.push_str(&serde_json::to_string(event)?);
+        body.push('\n');
+    }
+
+    match sink {
+        Sink::Http { client, url, token } => {
+            let mut request = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                .body(body);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request
+                .send()
+                .await
+                .context("event request failed")?
+                .error_for_status()
+                .context("event endpoint returned an error")?;
+        }
+        Sink::File(path) => {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .with_context(|| format!("cannot open {}", path.display()))?;
+            file.write_all(body.as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
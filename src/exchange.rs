@@ -0,0 +1,201 @@
+use makudoku::VERSION as ENGINE_VERSION;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{bundle::BundlePuzzle, config::Config, signing::PuzzleSigner};
+
+/// Identifies an exchange document, bumped if the layout changes.
+pub const EXCHANGE_FORMAT: &str = "makudoku-exchange";
+pub const EXCHANGE_VERSION: u32 = 2;
+
+/// Prefix of every signed exchange message. Version 1 signed newline-joined
+/// fields, which didn't cover `variants`.
+const MESSAGE_PREFIX: &str = "makudoku-exchange-v2";
+
+/// The instance a document comes from.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ExchangeOrigin {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub key_id: Option<String>,
+    /// Base64 Ed25519 key the puzzle signatures verify against.
+    pub public_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExchangePuzzle {
+    /// The date the puzzle has on the exporting instance.
+    pub origin_date_utc: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub difficulty: Option<i64>,
+    #[serde(default)]
+    pub variants: Vec<String>,
+    /// The puzzle's `puzzle_json` as an object: givens, solution,
+    /// constraints and the rest.
+    pub puzzle: Value,
+    /// Base64 signature over `signed_message`, by the origin's key.
+    pub signature: Option<String>,
+}
+
+impl ExchangePuzzle {
+    /// `makudoku-exchange-v2\n<entry>`, the entry being every field but
+    /// `signature` as canonical JSON, so no field can change unsigned. New
+    /// fields must be added here too.
+    pub fn signed_message(&self) -> String {
+        let entry = serde_json::json!({
+            "origin_date_utc": self.origin_date_utc,
+            "title": self.title,
+            "author": self.author,
+            "difficulty": self.difficulty,
+            "variants": self.variants,
+            "puzzle": self.puzzle,
+        });
+        format!("{MESSAGE_PREFIX}\n{}", canonical_json(&entry))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExchangeDocument {
+    pub format: String,
+    pub version: u32,
+    pub exported_at_utc: String,
+    pub engine_version: String,
+    #[serde(default)]
+    pub origin: ExchangeOrigin,
+    pub puzzles: Vec<ExchangePuzzle>,
+}
+
+impl ExchangeDocument {
+    /// Rejects documents of another format or a newer version.
+    pub fn check_format(&self) -> Result<(), String> {
+        if self.format != EXCHANGE_FORMAT {
+            return Err(format!("format must be {EXCHANGE_FORMAT}"));
+        }
+        if self.version == 0 || self.version > EXCHANGE_VERSION {
+            return Err(format!(
+                "exchange version {} is not supported, this server reads up to {EXCHANGE_VERSION}",
+                self.version
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builds a document from exported rows, signing each puzzle when this
+/// instance has a signing key.
+pub fn export(
+    rows: Vec<BundlePuzzle>,
+    config: &Config,
+    signer: Option<&PuzzleSigner>,
+) -> Result<ExchangeDocument, String> {
+    let mut puzzles = Vec::with_capacity(rows.len());
+    for row in rows {
+        let puzzle = serde_json::from_str(&row.puzzle_json)
+            .map_err(|e| format!("puzzle_json of {} is invalid: {e}", row.date_utc))?;
+        let mut puzzle = ExchangePuzzle {
+            origin_date_utc: row.date_utc,
+            title: row.title,
+            author: row.author,
+            difficulty: row.difficulty,
+            variants: row.variants,
+            puzzle,
+            signature: None,
+        };
+        puzzle.signature = signer.map(|signer| signer.sign(&puzzle.signed_message()));
+        puzzles.push(puzzle);
+    }
+
+    Ok(ExchangeDocument {
+        format: EXCHANGE_FORMAT.to_string(),
+        version: EXCHANGE_VERSION,
+        exported_at_utc: crate::now_utc_string(),
+        engine_version: ENGINE_VERSION.to_string(),
+        origin: ExchangeOrigin {
+            name: config.instance_name.clone(),
+            url: config.public_url.clone(),
+            key_id: signer.map(|signer| signer.key_id().to_string()),
+            public_key: signer.map(|signer| signer.public_key_base64()),
+        },
+        puzzles,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// No signature, or no key in the document to check it with.
+    Unsigned,
+    /// The signature doesn't match the puzzle and the document's key.
+    Invalid,
+    /// Valid, but the document's key isn't in `MAKUDOKU_EXCHANGE_TRUSTED_KEYS`.
+    Untrusted,
+    Trusted,
+}
+
+impl SignatureCheck {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SignatureCheck::Unsigned => "unsigned",
+            SignatureCheck::Invalid => "invalid",
+            SignatureCheck::Untrusted => "untrusted",
+            SignatureCheck::Trusted => "trusted",
+        }
+    }
+}
+
+pub fn check_signature(
+    origin: &ExchangeOrigin,
+    puzzle: &ExchangePuzzle,
+    trusted_keys: &[String],
+) -> SignatureCheck {
+    let (Some(public_key), Some(signature)) = (&origin.public_key, &puzzle.signature) else {
+        return SignatureCheck::Unsigned;
+    };
+    if !crate::signing::verify(public_key, &puzzle.signed_message(), signature) {
+        SignatureCheck::Invalid
+    } else if trusted_keys.contains(public_key) {
+        SignatureCheck::Trusted
+    } else {
+        SignatureCheck::Untrusted
+    }
+}
+
+/// What an imported puzzle records about where it came from.
+pub fn origin_json(
+    origin: &ExchangeOrigin,
+    puzzle: &ExchangePuzzle,
+    signature: SignatureCheck,
+) -> String {
+    serde_json::json!({
+        "name": origin.name,
+        "url": origin.url,
+        "key_id": origin.key_id,
+        "date_utc": puzzle.origin_date_utc,
+        "signature": signature.as_str(),
+        "imported_at_utc": crate::now_utc_string(),
+    })
+    .to_string()
+}
+
+/// JSON with object keys sorted and no whitespace, so a signature doesn't
+/// depend on how either side orders fields.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// One published puzzle in an RSS feed.
+pub struct FeedItem {
+    pub date_utc: String,
+    pub title: Option<String>,
+    pub variants: Vec<String>,
+    pub published_at_utc: Option<String>,
+}
+
+/// Renders an RSS 2.0 document. Items link to the site at `base_url` (the
+/// public page always shows today's puzzle); without it links are
+/// site-relative. Untitled items are named after `site_name`.
+pub fn render_rss(
+    site_name: &str,
+    title: &str,
+    description: &str,
+    base_url: &str,
+    items: &[FeedItem],
+) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<rss version=\"2.0\">\n<channel>\n");
+    out.push_str(&format!("<title>{}</title>\n", escape(title)));
+    out.push_str(&format!("<link>{}/</link>\n", escape(base_url)));
+    out.push_str(&format!(
+        "<description>{}</description>\n",
+        escape(description)
+    ));
+
+    for item in items {
+        let title = item
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("{site_name} {}", item.date_utc));
+        out.push_str("<item>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape(&title)));
+        out.push_str(&format!("<link>{}/</link>\n", escape(base_url)));
+        out.push_str(&format!(
+            "<guid isPermaLink=\"false\">makudoku-{}</guid>\n",
+            escape(&item.date_utc)
+        ));
+        if let Some(date) = pub_date(item) {
+            out.push_str(&format!("<pubDate>{date}</pubDate>\n"));
+        }
+        out.push_str(&format!(
+            "<description>{}</description>\n",
+            escape(&item.variants.join(", "))
+        ));
+        for variant in &item.variants {
+            out.push_str(&format!("<category>{}</category>\n", escape(variant)));
+        }
+        out.push_str("</item>\n");
+    }
+
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+/// RFC 2822 date from `published_at_utc`, falling back to midnight of the
+/// puzzle's day.
+fn pub_date(item: &FeedItem) -> Option<String> {
+    let published = item
+        .published_at_utc
+        .as_deref()
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|v| v.with_timezone(&Utc));
+    let published = published.or_else(|| {
+        NaiveDate::parse_from_str(&item.date_utc, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|d| d.and_utc())
+    })?;
+    Some(published.to_rfc2822())
+}
+
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
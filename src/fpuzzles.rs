@@ -0,0 +1,556 @@
+use makudoku::{Diagonal, DiagonalDirection, SandwichLine, VariantSpec};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// A puzzle decoded from f-puzzles, mapped onto our constraint JSON.
+pub struct FPuzzle {
+    pub puzzle: String,
+    pub constraints: Vec<Value>,
+    pub regions: Option<Vec<Vec<(usize, usize)>>>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// f-puzzles features that have no equivalent here and were left out.
+    pub skipped: Vec<String>,
+}
+
+/// Decodes an f-puzzles link (`...?load=<data>`), its bare `load` payload
+/// (LZ-string compressed base64) or the decompressed JSON.
+pub fn decode(source: &str) -> Result<FPuzzle, String> {
+    let source = source.trim();
+    let json = if source.starts_with('{') {
+        source.to_string()
+    } else {
+        let payload = match source.split_once("load=") {
+            Some((_, rest)) => rest.split(['&', '#']).next().unwrap_or_default(),
+            None => source,
+        };
+        let payload = payload
+            .replace("%2B", "+")
+            .replace("%2F", "/")
+            .replace("%3D", "=")
+            .replace(' ', "+");
+        let utf16 = lz_str::decompress_from_base64(&payload)
+            .ok_or_else(|| "invalid f-puzzles data".to_string())?;
+        String::from_utf16(&utf16).map_err(|_| "invalid f-puzzles data".to_string())?
+    };
+    let value: Value =
+        serde_json::from_str(&json).map_err(|e| format!("invalid f-puzzles JSON: {e}"))?;
+    convert(&value)
+}
+
+fn convert(value: &Value) -> Result<FPuzzle, String> {
+    if value.get("size").and_then(|v| v.as_u64()) != Some(9) {
+        return Err("only 9x9 f-puzzles are supported".to_string());
+    }
+    let grid = value
+        .get("grid")
+        .and_then(|v| v.as_array())
+        .filter(|rows| rows.len() == 9)
+        .ok_or_else(|| "f-puzzles grid must have 9 rows".to_string())?;
+
+    let mut puzzle = String::with_capacity(81);
+    let mut region_cells: BTreeMap<u64, Vec<(usize, usize)>> = BTreeMap::new();
+    for (r, row) in grid.iter().enumerate() {
+        let row = row
+            .as_array()
+            .filter(|cells| cells.len() == 9)
+            .ok_or_else(|| "f-puzzles grid rows must have 9 cells".to_string())?;
+        for (c, cell) in row.iter().enumerate() {
+            let given = cell.get("given").and_then(|v| v.as_bool()) == Some(true);
+            let digit = cell
+                .get("value")
+                .and_then(|v| v.as_u64())
+                .filter(|d| (1..=9).contains(d));
+            match digit {
+                Some(d) if given => puzzle.push(char::from(b'0' + d as u8)),
+                _ => puzzle.push('.'),
+            }
+            let region = cell
+                .get("region")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(((r / 3) * 3 + c / 3) as u64);
+            region_cells.entry(region).or_default().push((r, c));
+        }
+    }
+    let standard = region_cells.iter().all(|(region, cells)| {
+        cells
+            .iter()
+            .all(|(r, c)| ((r / 3) * 3 + c / 3) as u64 == *region)
+    });
+    let regions = (!standard).then(|| region_cells.into_values().collect());
+
+    let mut out = FPuzzle {
+        puzzle,
+        constraints: Vec::new(),
+        regions,
+        title: text(value, "title"),
+        author: text(value, "author"),
+        skipped: Vec::new(),
+    };
+    out.convert_globals(value);
+    out.convert_lines(value)?;
+    out.convert_clues(value)?;
+    Ok(out)
+}
+
+fn text(value: &Value, field: &str) -> Option<String> {
+    value
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn items<'a>(value: &'a Value, field: &str) -> &'a [Value] {
+    value
+        .get(field)
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// f-puzzles cells are 1-based `R<row>C<col>`; clues outside the grid use
+/// row or column 0 or 10.
+fn parse_rc(cell: &Value) -> Result<(i64, i64), String> {
+    let text = cell
+        .as_str()
+        .ok_or_else(|| "f-puzzles cells must be strings".to_string())?;
+    let (row, col) = text
+        .strip_prefix('R')
+        .and_then(|rest| rest.split_once('C'))
+        .ok_or_else(|| format!("invalid f-puzzles cell {text}"))?;
+    match (row.parse(), col.parse()) {
+        (Ok(row), Ok(col)) => Ok((row, col)),
+        _ => Err(format!("invalid f-puzzles cell {text}")),
+    }
+}
+
+fn parse_grid_cell(cell: &Value) -> Result<[usize; 2], String> {
+    let (row, col) = parse_rc(cell)?;
+    if !(1..=9).contains(&row) || !(1..=9).contains(&col) {
+        return Err(format!("f-puzzles cell {cell} is outside the grid"));
+    }
+    Ok([row as usize - 1, col as usize - 1])
+}
+
+fn parse_cells(cells: &Value) -> Result<Vec<[usize; 2]>, String> {
+    cells
+        .as_array()
+        .ok_or_else(|| "f-puzzles cell lists must be arrays".to_string())?
+        .iter()
+        .map(parse_grid_cell)
+        .collect()
+}
+
+/// Clue values are stored as strings or numbers depending on the version.
+fn clue_value(item: &Value) -> Option<u64> {
+    match item.get("value")? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+impl FPuzzle {
+    fn skip(&mut self, feature: &str) {
+        if !self.skipped.iter().any(|s| s == feature) {
+            self.skipped.push(feature.to_string());
+        }
+    }
+
+    fn convert_globals(&mut self, value: &Value) {
+        let flag = |field: &str| value.get(field).and_then(|v| v.as_bool()) == Some(true);
+        let which = match (flag("diagonal-"), flag("diagonal+")) {
+            (true, true) => Some("both"),
+            (true, false) => Some("main"),
+            (false, true) => Some("anti"),
+            (false, false) => None,
+        };
+        if let Some(which) = which {
+            self.constraints
+                .push(json!({ "type": "diagonal", "which": which }));
+        }
+        for (field, kind) in [
+            ("antiking", "king"),
+            ("antiknight", "knight"),
+            ("disjointgroups", "disjoint_groups"),
+        ] {
+            if flag(field) {
+                self.constraints.push(json!({ "type": kind }));
+            }
+        }
+        if flag("nonconsecutive") {
+            self.skip("nonconsecutive");
+        }
+
+        let negative: Vec<&str> = items(value, "negative")
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        if negative.contains(&"ratio") && negative.contains(&"difference") {
+            self.constraints.push(json!({ "type": "kropki_negative" }));
+        } else if negative.contains(&"ratio") || negative.contains(&"difference") {
+            self.skip("one-sided kropki negative constraint");
+        }
+        if !items(value, "xv").is_empty() {
+            self.skip("xv");
+        }
+    }
+
+    fn convert_lines(&mut self, value: &Value) -> Result<(), String> {
+        for (field, kind) in [
+            ("thermometer", "thermo"),
+            ("renban", "renban"),
+            ("palindrome", "palindrome"),
+            ("betweenline", "between"),
+            ("whispers", "whisper"),
+        ] {
+            for item in items(value, field) {
+                // Only the standard German whispers difference of 5 maps onto
+                // our whisper lines.
+                if kind == "whisper" && clue_value(item).is_some_and(|v| v != 5) {
+                    self.skip("whispers with a difference other than 5");
+                    continue;
+                }
+                for line in items(item, "lines") {
+                    let path = parse_cells(line)?;
+                    self.constraints.push(json!({ "type": kind, "path": path }));
+                }
+            }
+        }
+
+        for item in items(value, "arrow") {
+            let circle = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+            let lines = items(item, "lines");
+            if lines.len() != 1 || circle.is_empty() || circle.len() > 2 {
+                self.skip("arrows with several shafts or large circles");
+                continue;
+            }
+            // Lines start inside the circle.
+            let shaft: Vec<[usize; 2]> = parse_cells(&lines[0])?
+                .into_iter()
+                .filter(|cell| !circle.contains(cell))
+                .collect();
+            if circle.len() == 1 {
+                let path: Vec<[usize; 2]> = circle.into_iter().chain(shaft).collect();
+                self.constraints
+                    .push(json!({ "type": "arrow", "path": path }));
+            } else {
+                self.constraints
+                    .push(json!({ "type": "arrow", "pill": circle, "path": shaft }));
+            }
+        }
+        Ok(())
+    }
+
+    fn convert_clues(&mut self, value: &Value) -> Result<(), String> {
+        for item in items(value, "killercage") {
+            let cells = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+            match clue_value(item) {
+                Some(sum) => self
+                    .constraints
+                    .push(json!({ "type": "killer", "cells": cells, "sum": sum })),
+                None => self.skip("killer cages without a sum"),
+            }
+        }
+
+        for (field, kind) in [("difference", "kropki_white"), ("ratio", "kropki_black")] {
+            for item in items(value, field) {
+                if clue_value(item).is_some_and(|v| v != if kind == "kropki_white" { 1 } else { 2 })
+                {
+                    self.skip("kropki dots with non-standard values");
+                    continue;
+                }
+                let cells = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+                let [a, b] = cells[..] else {
+                    return Err("f-puzzles dots must join two cells".to_string());
+                };
+                self.constraints
+                    .push(json!({ "type": kind, "a": a, "b": b }));
+            }
+        }
+
+        for item in items(value, "littlekillersum") {
+            let Some(sum) = clue_value(item) else {
+                self.skip("little killer clues without a sum");
+                continue;
+            };
+            let cells = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+            let direction = match item.get("direction").and_then(|v| v.as_str()) {
+                Some("DR") => "down_right",
+                Some("DL") => "down_left",
+                Some("UR") => "up_right",
+                Some("UL") => "up_left",
+                _ => return Err("invalid f-puzzles little killer direction".to_string()),
+            };
+            let first = cells
+                .first()
+                .ok_or_else(|| "f-puzzles little killer has no cells".to_string())?;
+            self.constraints.push(json!({
+                "type": "little_killer",
+                "cell": first,
+                "direction": direction,
+                "sum": sum,
+            }));
+        }
+
+        for item in items(value, "sandwichsum") {
+            let Some(sum) = clue_value(item) else {
+                continue;
+            };
+            let (row, col) = parse_rc(item.get("cell").unwrap_or(&Value::Null))?;
+            let clue = match (row, col) {
+                (0, 1..=9) => json!({ "type": "sandwich", "col": col - 1, "sum": sum }),
+                (1..=9, 0) => json!({ "type": "sandwich", "row": row - 1, "sum": sum }),
+                _ => {
+                    self.skip("sandwich clues on the bottom or right edge");
+                    continue;
+                }
+            };
+            self.constraints.push(clue);
+        }
+
+        for item in items(value, "quadruple") {
+            let cells = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+            let Some(top_left) = cells.iter().min().copied() else {
+                continue;
+            };
+            let digits: Vec<u64> = items(item, "values")
+                .iter()
+                .filter_map(|v| v.as_u64())
+                .collect();
+            self.constraints.push(json!({
+                "type": "quadruple",
+                "cell": top_left,
+                "digits": digits,
+            }));
+        }
+
+        for item in items(value, "extraregion") {
+            let cells = parse_cells(item.get("cells").unwrap_or(&Value::Null))?;
+            self.constraints
+                .push(json!({ "type": "extra_region", "cells": cells }));
+        }
+        Ok(())
+    }
+}
+
+/// What `encode` needs from a stored puzzle.
+pub struct ExportPuzzle<'a> {
+    pub puzzle: &'a str,
+    pub specs: &'a [VariantSpec],
+    pub regions: Option<&'a [Vec<(usize, usize)>]>,
+    pub title: Option<&'a str>,
+    pub author: Option<&'a str>,
+    pub rules: &'a [String],
+}
+
+/// Builds the f-puzzles JSON for a puzzle, LZ-string compressed to base64 as
+/// f-puzzles and SudokuPad expect it in links. Also returns the constraint
+/// kinds f-puzzles can't express, which are left out.
+pub fn encode(export: &ExportPuzzle) -> (String, Vec<String>) {
+    let mut skipped: Vec<String> = Vec::new();
+    let mut skip = |feature: &str| {
+        if !skipped.iter().any(|s| s == feature) {
+            skipped.push(feature.to_string());
+        }
+    };
+
+    let mut region_of = [[None; 9]; 9];
+    for (index, region) in export.regions.unwrap_or_default().iter().enumerate() {
+        for &(r, c) in region {
+            region_of[r][c] = Some(index);
+        }
+    }
+    let digits: Vec<char> = export.puzzle.chars().collect();
+    let grid: Vec<Vec<Value>> = (0..9)
+        .map(|r| {
+            (0..9)
+                .map(|c| {
+                    let mut cell = json!({});
+                    let given = digits
+                        .get(r * 9 + c)
+                        .and_then(|ch| ch.to_digit(10))
+                        .filter(|d| *d > 0);
+                    if let Some(d) = given {
+                        cell["value"] = json!(d);
+                        cell["given"] = json!(true);
+                    }
+                    if let Some(region) = region_of[r][c] {
+                        cell["region"] = json!(region);
+                    }
+                    cell
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut out = json!({ "size": 9, "grid": grid });
+    if let Some(title) = export.title {
+        out["title"] = json!(title);
+    }
+    if let Some(author) = export.author {
+        out["author"] = json!(author);
+    }
+    if !export.rules.is_empty() {
+        out["ruleset"] = json!(export.rules.join("\n"));
+    }
+
+    let mut lists: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    let mut negative: Vec<&str> = Vec::new();
+    for spec in export.specs {
+        match spec {
+            VariantSpec::KropkiWhite(a, b) => lists
+                .entry("difference")
+                .or_default()
+                .push(json!({ "cells": [rc(*a), rc(*b)] })),
+            VariantSpec::KropkiBlack(a, b) => lists
+                .entry("ratio")
+                .or_default()
+                .push(json!({ "cells": [rc(*a), rc(*b)] })),
+            VariantSpec::Thermo(path) => lists
+                .entry("thermometer")
+                .or_default()
+                .push(json!({ "lines": [rcs(path)] })),
+            VariantSpec::BranchedThermo { bulb, branches } => {
+                let lines: Vec<Vec<String>> = branches
+                    .iter()
+                    .map(|branch| rcs(&[vec![*bulb], branch.clone()].concat()))
+                    .collect();
+                lists
+                    .entry("thermometer")
+                    .or_default()
+                    .push(json!({ "lines": lines }));
+            }
+            VariantSpec::Arrow(path) => lists
+                .entry("arrow")
+                .or_default()
+                .push(json!({ "cells": [rc(path[0])], "lines": [rcs(path)] })),
+            VariantSpec::PillArrow { pill, path } => {
+                // The shaft is drawn from the pill cell nearest its start.
+                let start = path.first().copied().unwrap_or(pill[0]);
+                let distance =
+                    |cell: &&(usize, usize)| cell.0.abs_diff(start.0).max(cell.1.abs_diff(start.1));
+                let from = *pill.iter().min_by_key(distance).unwrap_or(&pill[0]);
+                let line = rcs(&[vec![from], path.clone()].concat());
+                lists
+                    .entry("arrow")
+                    .or_default()
+                    .push(json!({ "cells": rcs(pill), "lines": [line] }));
+            }
+            VariantSpec::Killer {
+                cells,
+                sum,
+                no_repeats,
+                style,
+            } => {
+                if !no_repeats {
+                    skip("killer cages allowing repeats");
+                }
+                let mut cage = json!({ "cells": rcs(cells) });
+                if style.show_sum {
+                    cage["value"] = json!(sum.to_string());
+                }
+                lists.entry("killercage").or_default().push(cage);
+            }
+            VariantSpec::Renban(path) => lists
+                .entry("renban")
+                .or_default()
+                .push(json!({ "lines": [rcs(path)] })),
+            VariantSpec::Whisper(path) => lists
+                .entry("whispers")
+                .or_default()
+                .push(json!({ "lines": [rcs(path)], "value": 5 })),
+            VariantSpec::Palindrome(path) => lists
+                .entry("palindrome")
+                .or_default()
+                .push(json!({ "lines": [rcs(path)] })),
+            VariantSpec::Between(path) => lists
+                .entry("betweenline")
+                .or_default()
+                .push(json!({ "lines": [rcs(path)] })),
+            VariantSpec::ColorRegion { .. } => skip("color_region"),
+            VariantSpec::Quadruple { cell, digits } => {
+                let (r, c) = *cell;
+                let cells = [(r, c), (r, c + 1), (r + 1, c), (r + 1, c + 1)];
+                lists
+                    .entry("quadruple")
+                    .or_default()
+                    .push(json!({ "cells": rcs(&cells), "values": digits }));
+            }
+            VariantSpec::LittleKiller {
+                start,
+                direction,
+                sum,
+            } => {
+                let (dr, dc, name) = match direction {
+                    DiagonalDirection::DownRight => (1, 1, "DR"),
+                    DiagonalDirection::DownLeft => (1, -1, "DL"),
+                    DiagonalDirection::UpRight => (-1, 1, "UR"),
+                    DiagonalDirection::UpLeft => (-1, -1, "UL"),
+                };
+                let (mut r, mut c) = (start.0 as isize, start.1 as isize);
+                let clue = format!("R{}C{}", r - dr + 1, c - dc + 1);
+                let mut cells = Vec::new();
+                while (0..9).contains(&r) && (0..9).contains(&c) {
+                    cells.push(rc((r as usize, c as usize)));
+                    r += dr;
+                    c += dc;
+                }
+                lists.entry("littlekillersum").or_default().push(json!({
+                    "cell": clue,
+                    "cells": cells,
+                    "direction": name,
+                    "value": sum.to_string(),
+                }));
+            }
+            VariantSpec::Sandwich { line, sum } => {
+                let cell = match line {
+                    SandwichLine::Row(row) => format!("R{}C0", row + 1),
+                    SandwichLine::Col(col) => format!("R0C{}", col + 1),
+                };
+                lists
+                    .entry("sandwichsum")
+                    .or_default()
+                    .push(json!({ "cell": cell, "value": sum.to_string() }));
+            }
+            VariantSpec::King => out["antiking"] = json!(true),
+            VariantSpec::Knight => out["antiknight"] = json!(true),
+            VariantSpec::Queen => skip("queen"),
+            VariantSpec::DisjointGroups => out["disjointgroups"] = json!(true),
+            VariantSpec::ExtraRegion(cells) => lists
+                .entry("extraregion")
+                .or_default()
+                .push(json!({ "cells": rcs(cells) })),
+            VariantSpec::Diagonal(which) => {
+                if matches!(which, Diagonal::Main | Diagonal::Both) {
+                    out["diagonal-"] = json!(true);
+                }
+                if matches!(which, Diagonal::Anti | Diagonal::Both) {
+                    out["diagonal+"] = json!(true);
+                }
+            }
+            VariantSpec::KropkiNegative => negative.extend(["ratio", "difference"]),
+            VariantSpec::XvNegative => negative.push("xv"),
+        }
+    }
+    for (field, items) in lists {
+        out[field] = Value::Array(items);
+    }
+    if !negative.is_empty() {
+        out["negative"] = json!(negative);
+    }
+
+    (
+        lz_str::compress_to_base64(out.to_string().as_str()),
+        skipped,
+    )
+}
+
+fn rc((r, c): (usize, usize)) -> String {
+    format!("R{}C{}", r + 1, c + 1)
+}
+
+fn rcs(cells: &[(usize, usize)]) -> Vec<String> {
+    cells.iter().copied().map(rc).collect()
+}
@@ -0,0 +1,77 @@
+/// Side of the only grid size the engine supports today.
+pub const CLASSIC_SIZE: usize = 9;
+
+/// Largest side the encoding covers, for 16x16 grids once the engine has
+/// them.
+const MAX_SIZE: usize = 16;
+
+/// Digits of the compact form: `1`-`9`, then `a`-`g` for 10 to 16.
+const DIGITS: &[u8] = b"123456789abcdefg";
+
+/// Decodes a player grid of `size` x `size` cells, row by row, in either
+/// of two forms:
+///
+/// - compact: one character per cell, `1`-`9` then `a`-`g` (case
+///   insensitive) for 10-16, with `.` or `0` for an empty cell;
+/// - comma separated: one decimal value per cell, empty, `.` or `0` for an
+///   empty cell, e.g. `16,,3,...`.
+///
+/// A grid with a comma is read as comma separated, so 9x9 grids can keep
+/// using the compact form.
+pub fn decode(grid: &str, size: usize) -> Result<Vec<Option<u8>>, String> {
+    debug_assert!(size <= MAX_SIZE);
+    let grid = grid.trim();
+    let cells = size * size;
+    if grid.contains(',') {
+        let values: Vec<&str> = grid.split(',').map(str::trim).collect();
+        if values.len() != cells {
+            return Err(format!(
+                "grid must have exactly {cells} comma separated values"
+            ));
+        }
+        values
+            .into_iter()
+            .map(|value| match value {
+                "" | "." | "0" => Ok(None),
+                _ => value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|digit| (1..=size as u8).contains(digit))
+                    .map(Some)
+                    .ok_or_else(|| format!("grid values must be 1-{size} or empty")),
+            })
+            .collect()
+    } else {
+        if grid.chars().count() != cells {
+            return Err(format!("grid must be exactly {cells} characters"));
+        }
+        let digits = &DIGITS[..size];
+        grid.chars()
+            .map(|ch| match ch {
+                '.' | '0' => Ok(None),
+                _ => digits
+                    .iter()
+                    .position(|d| *d as char == ch.to_ascii_lowercase())
+                    .map(|i| Some(i as u8 + 1))
+                    .ok_or_else(|| match size {
+                        ..=9 => format!("grid must contain digits 1-{size} or '.'"),
+                        _ => format!(
+                            "grid must contain digits 1-9, letters a-{} or '.'",
+                            DIGITS[size - 1] as char
+                        ),
+                    }),
+            })
+            .collect()
+    }
+}
+
+/// The compact form of decoded cells, `.` for empty ones.
+pub fn encode(cells: &[Option<u8>]) -> String {
+    cells
+        .iter()
+        .map(|cell| match cell {
+            Some(digit) => DIGITS[*digit as usize - 1] as char,
+            None => '.',
+        })
+        .collect()
+}
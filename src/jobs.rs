@@ -0,0 +1,369 @@
+use axum::response::sse::Event;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::{mpsc, watch};
+
+/// Least time between two progress events of one job.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a job that isn't running yet is looked up again.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `error` of a cancelled job.
+pub const CANCELLED: &str = "Cancelled";
+
+/// Generation from custom constraints; the request is an
+/// `AdminGenerateCustomRequest`.
+pub const KIND_GENERATE: &str = "generate";
+
+/// A `jobs` row as the admin API shows it.
+#[derive(Serialize)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    /// `queued`, `running`, `done` or `failed`.
+    pub status: String,
+    /// The job's output once it is `done`.
+    pub result: Option<serde_json::Value>,
+    /// Why the job `failed`.
+    pub error: Option<String>,
+    pub created_at_utc: String,
+    pub started_at_utc: Option<String>,
+    pub finished_at_utc: Option<String>,
+    /// When cancellation was asked for. A running job stops at its next
+    /// checkpoint, so this can come before `finished_at_utc`.
+    pub cancelled_at_utc: Option<String>,
+}
+
+/// A job taken off the queue by `claim_next`.
+pub struct ClaimedJob {
+    pub id: String,
+    pub kind: String,
+    pub request_json: String,
+}
+
+/// Queues a job and returns its id.
+pub async fn enqueue(
+    db: &SqlitePool,
+    kind: &str,
+    request_json: &str,
+) -> Result<String, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query!(
+        r#"INSERT INTO jobs (id, kind, request_json) VALUES (?, ?, ?)"#,
+        id,
+        kind,
+        request_json
+    )
+    .execute(db)
+    .await?;
+    Ok(id)
+}
+
+pub async fn get(db: &SqlitePool, id: &str) -> Result<Option<Job>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id AS "id!", kind, status, result_json, error, created_at_utc,
+               started_at_utc, finished_at_utc, cancelled_at_utc
+        FROM jobs
+        WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.map(|row| Job {
+        id: row.id,
+        kind: row.kind,
+        status: row.status,
+        result: row
+            .result_json
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        error: row.error,
+        created_at_utc: row.created_at_utc,
+        started_at_utc: row.started_at_utc,
+        finished_at_utc: row.finished_at_utc,
+        cancelled_at_utc: row.cancelled_at_utc,
+    }))
+}
+
+/// Marks the oldest queued job as running and returns it.
+pub async fn claim_next(db: &SqlitePool) -> Result<Option<ClaimedJob>, sqlx::Error> {
+    let now = crate::now_utc_string();
+    sqlx::query_as!(
+        ClaimedJob,
+        r#"
+        UPDATE jobs
+        SET status = 'running', started_at_utc = ?
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE status = 'queued'
+            ORDER BY created_at_utc, id
+            LIMIT 1
+        )
+        RETURNING id AS "id!", kind, request_json
+        "#,
+        now
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Records the outcome of a running job.
+pub async fn finish(
+    db: &SqlitePool,
+    id: &str,
+    outcome: Result<String, String>,
+) -> Result<(), sqlx::Error> {
+    let now = crate::now_utc_string();
+    let (status, result_json, error) = match outcome {
+        Ok(result_json) => ("done", Some(result_json), None),
+        Err(error) => ("failed", None, Some(error)),
+    };
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = ?, result_json = ?, error = ?, finished_at_utc = ?
+        WHERE id = ?
+        "#,
+        status,
+        result_json,
+        error,
+        now,
+        id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Cancels a job that hasn't started. Returns false when it isn't queued
+/// (any more).
+pub async fn cancel_queued(db: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+    let now = crate::now_utc_string();
+    let result = sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = 'failed', error = ?, cancelled_at_utc = ?, finished_at_utc = ?
+        WHERE id = ? AND status = 'queued'
+        "#,
+        CANCELLED,
+        now,
+        now,
+        id
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records that a running job was asked to stop; the worker stores the
+/// outcome once it does.
+pub async fn mark_cancel_requested(db: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+    let now = crate::now_utc_string();
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET cancelled_at_utc = COALESCE(cancelled_at_utc, ?)
+        WHERE id = ? AND status = 'running'
+        "#,
+        now,
+        id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Puts jobs that were running when the server stopped back on the queue,
+/// failing those that were being cancelled. Only safe before the worker
+/// starts.
+pub async fn requeue_interrupted(db: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let now = crate::now_utc_string();
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = 'failed', error = ?, finished_at_utc = ?
+        WHERE status = 'running' AND cancelled_at_utc IS NOT NULL
+        "#,
+        CANCELLED,
+        now
+    )
+    .execute(db)
+    .await?;
+    let result = sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = 'queued', started_at_utc = NULL
+        WHERE status = 'running'
+        "#
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// How far a running generation has got.
+#[derive(Serialize, Clone, Default)]
+pub struct JobProgress {
+    /// `solution` while the full grid is generated, then `clues` while
+    /// givens are removed, then `render`.
+    pub stage: &'static str,
+    /// Givens removed from the solution so far.
+    pub clues_removed: usize,
+    /// Givens that must go to reach the clue target. Removal can stop
+    /// short of it when no further given can go.
+    pub clues_to_remove: usize,
+    /// Uniqueness checks run so far.
+    pub uniqueness_checks: usize,
+}
+
+/// A job the worker is running: where it has got, and whether it should
+/// stop.
+pub struct RunningJob {
+    progress: watch::Sender<JobProgress>,
+    cancelled: AtomicBool,
+}
+
+impl RunningJob {
+    pub fn report(&self, update: impl FnOnce(&mut JobProgress)) {
+        self.progress.send_modify(update);
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Checked by the job between steps; a cancelled job returns
+    /// `CANCELLED` as its error.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The jobs running right now. Kept in memory only; a finished job's
+/// outcome is in the `jobs` table.
+#[derive(Default)]
+pub struct JobProgressBoard {
+    running: Mutex<HashMap<String, Arc<RunningJob>>>,
+}
+
+impl JobProgressBoard {
+    /// Registers a job as running. Watchers are told the job ended once it
+    /// is `remove`d and the returned handle is dropped.
+    pub fn start(&self, id: &str) -> Arc<RunningJob> {
+        let (progress, _) = watch::channel(JobProgress {
+            stage: "solution",
+            ..JobProgress::default()
+        });
+        let job = Arc::new(RunningJob {
+            progress,
+            cancelled: AtomicBool::new(false),
+        });
+        self.running
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), job.clone());
+        job
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.running.lock().unwrap().remove(id);
+    }
+
+    /// Watches a running job's progress.
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<JobProgress>> {
+        self.running
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|job| job.progress.subscribe())
+    }
+
+    /// Asks a running job to stop. Returns false when it isn't running here.
+    pub fn cancel(&self, id: &str) -> bool {
+        self.running
+            .lock()
+            .unwrap()
+            .get(id)
+            .inspect(|job| job.cancel())
+            .is_some()
+    }
+}
+
+/// Sends a job's Server-Sent Events to `tx`: `status` once while it is
+/// queued, `progress` while it runs, and finally `done` or `failed` with
+/// the job as `GET /api/admin/jobs/{id}` shows it. Ends after the last
+/// event or when the client goes away.
+pub async fn stream_events(
+    db: SqlitePool,
+    board: Arc<JobProgressBoard>,
+    id: String,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    let mut announced_queue = false;
+    while !tx.is_closed() {
+        let job = match get(&db, &id).await {
+            Ok(Some(job)) => job,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("job events for {id} failed: {e}");
+                return;
+            }
+        };
+        match job.status.as_str() {
+            "done" | "failed" => {
+                send_event(&tx, &job.status, &job).await;
+                return;
+            }
+            "queued" if !announced_queue => {
+                announced_queue = true;
+                let status = serde_json::json!({ "status": "queued" });
+                if !send_event(&tx, "status", &status).await {
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        let Some(mut progress) = board.subscribe(&id) else {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        };
+        loop {
+            let current = progress.borrow_and_update().clone();
+            if !send_event(&tx, "progress", &current).await {
+                return;
+            }
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            // Closed once the outcome is stored; the next lookup sends it.
+            if progress.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns whether the client is still listening.
+async fn send_event(
+    tx: &mpsc::Sender<Result<Event, Infallible>>,
+    name: &str,
+    data: &impl Serialize,
+) -> bool {
+    match Event::default().event(name).json_data(data) {
+        Ok(event) => tx.send(Ok(event)).await.is_ok(),
+        Err(e) => {
+            tracing::warn!("encoding job event failed: {e}");
+            false
+        }
+    }
+}
@@ -0,0 +1,100 @@
+/// Layers a puzzle SVG can be cut down to. Each is a top-level
+/// `<g id="...">` the renderer emits; everything else, constraint artwork
+/// and the player's `highlights`, `user-values` and `candidates` groups
+/// alike, is always kept. Only list ids the renderer really writes.
+pub const LAYERS: &[&str] = &["grid", "givens"];
+
+/// The layers to drop for a `layers` (keep only these) or `exclude` (drop
+/// these) list, each comma separated. Giving both is an error.
+pub fn hidden(layers: Option<&str>, exclude: Option<&str>) -> Result<Vec<&'static str>, String> {
+    match (layers, exclude) {
+        (Some(_), Some(_)) => Err("use either layers or exclude, not both".to_string()),
+        (Some(layers), None) => {
+            let keep = parse(layers)?;
+            Ok(LAYERS
+                .iter()
+                .copied()
+                .filter(|layer| !keep.contains(layer))
+                .collect())
+        }
+        (None, Some(exclude)) => parse(exclude),
+        (None, None) => Ok(Vec::new()),
+    }
+}
+
+fn parse(list: &str) -> Result<Vec<&'static str>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            LAYERS
+                .iter()
+                .copied()
+                .find(|layer| *layer == name)
+                .ok_or_else(|| format!("Unknown layer {name}; expected {}", LAYERS.join(", ")))
+        })
+        .collect()
+}
+
+/// Removes the top-level groups whose `id` is in `hidden`, nested groups
+/// and all. Markup outside those groups is copied as is.
+pub fn strip(svg: &str, hidden: &[&str]) -> String {
+    if hidden.is_empty() {
+        return svg.to_string();
+    }
+    let mut out = String::with_capacity(svg.len());
+    let mut copied_to = 0;
+    // Depth of `<g>` nesting, and where the group being dropped started.
+    let mut depth = 0usize;
+    let mut dropping: Option<usize> = None;
+
+    let mut pos = 0;
+    while let Some(offset) = svg[pos..].find('<') {
+        let start = pos + offset;
+        let Some(len) = svg[start..].find('>') else {
+            break;
+        };
+        let end = start + len + 1;
+        let tag = &svg[start..end];
+        pos = end;
+
+        if is_group_open(tag) {
+            let self_closing = tag.ends_with("/>");
+            if depth == 0 && group_id(tag).is_some_and(|id| hidden.contains(&id)) {
+                if self_closing {
+                    out.push_str(&svg[copied_to..start]);
+                    copied_to = end;
+                    continue;
+                }
+                dropping = Some(start);
+            }
+            if !self_closing {
+                depth += 1;
+            }
+        } else if tag.starts_with("</g>") || tag.starts_with("</g ") {
+            depth = depth.saturating_sub(1);
+            if let (0, Some(from)) = (depth, dropping) {
+                out.push_str(&svg[copied_to..from]);
+                copied_to = end;
+                dropping = None;
+            }
+        }
+    }
+    out.push_str(&svg[copied_to..]);
+    out
+}
+
+fn is_group_open(tag: &str) -> bool {
+    tag.strip_prefix("<g")
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|ch| ch.is_ascii_whitespace() || ch == '>' || ch == '/')
+}
+
+/// The `id` attribute of an opening tag.
+fn group_id(tag: &str) -> Option<&str> {
+    let idx = tag.find(" id=")?;
+    let rest = &tag[idx + 4..];
+    let quote = rest.chars().next().filter(|ch| *ch == '"' || *ch == '\'')?;
+    let rest = &rest[1..];
+    rest.find(quote).map(|end| &rest[..end])
+}
@@ -1,25 +1,111 @@
+mod activity;
+mod admin_auth;
+mod alerts;
+mod benchmarks;
+mod branding;
+mod bundle;
+mod check_limit;
+mod config;
+mod difficulty;
+mod digest;
+mod events;
+mod exchange;
+mod feed;
+mod fpuzzles;
+mod grid_codec;
+mod jobs;
+mod layers;
+mod migrate;
+mod mirror;
+mod profiles;
+mod random_options;
+mod random_pool;
+mod rate_limit;
+mod request_id;
+mod retention;
+mod rng;
+mod sanitize;
+mod schema;
+mod signing;
+mod social;
+mod store;
+mod today_cache;
+mod worksheet;
+
+use anyhow::Context;
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    routing::{get, post},
+    body::Body,
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, State},
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{
+            AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH,
+            SET_COOKIE,
+        },
+    },
+    middleware,
+    response::{
+        IntoResponse, Redirect, Response,
+        sse::{KeepAlive, Sse},
+    },
+    routing::{delete, get, post},
 };
-use chrono::{SecondsFormat, Utc};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{Datelike, NaiveDate, SecondsFormat, Timelike, Utc};
 use makudoku::{
-    Constraint, Engine, EngineRng, GenerationConfig, RenderOptions, SimpleRng, VariantSpec, NN,
-    add_all_sudoku_constraints, add_arrow, add_killer_cage, add_king_constraints,
-    add_knight_constraints, add_kropki_black, add_kropki_white, add_queen_constraints, add_thermo,
-    generate_full_solution_with, generate_random_variant_puzzle, render_puzzle_svg,
+    CageDash, CageStyle, Constraint, Diagonal, DiagonalDirection, Engine, EngineRng, NN,
+    RenderOptions, SandwichLine, SimpleRng, VERSION as ENGINE_VERSION, VariantSpec,
+    add_all_sudoku_constraints, add_arrow, add_between_line, add_branched_thermo, add_color_region,
+    add_diagonal_constraints, add_disjoint_groups, add_extra_region, add_killer_cage_styled,
+    add_king_constraints, add_knight_constraints, add_kropki_black, add_kropki_negative,
+    add_kropki_white, add_little_killer, add_palindrome, add_pill_arrow, add_quadruple,
+    add_queen_constraints, add_region, add_renban, add_row_col_constraints, add_sandwich,
+    add_thermo, add_whisper, add_xv_negative, generate_full_solution_with, render_puzzle_svg,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::{Sqlite, SqlitePool, migrate::MigrateDatabase, sqlite::SqlitePoolOptions};
-use std::{collections::HashSet, fs::create_dir_all, net::SocketAddr};
+use std::{
+    collections::{HashMap, HashSet, hash_map::DefaultHasher},
+    fs::create_dir_all,
+    hash::{Hash, Hasher},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_stream::wrappers::ReceiverStream;
 use tower_http::services::ServeDir;
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    activity::ActivityFeed,
+    admin_auth::AdminSessions,
+    check_limit::CheckLimiter,
+    config::Config,
+    events::EventStream,
+    jobs::{JobProgress, JobProgressBoard, RunningJob},
+    mirror::{SyncPuzzle, SyncRemoval, SyncResponse},
+    random_options::RandomOptions,
+    random_pool::{RandomPool, RandomPuzzle},
+    rate_limit::RateLimiter,
+    signing::PuzzleSigner,
+    today_cache::{CachedPuzzle, TodayCache},
+};
 
 #[derive(Clone)]
 struct AppState {
     db: SqlitePool,
+    config: Arc<Config>,
+    signer: Option<Arc<PuzzleSigner>>,
+    admin_sessions: Arc<AdminSessions>,
+    job_progress: Arc<JobProgressBoard>,
+    activity: Arc<ActivityFeed>,
+    events: Option<EventStream>,
+    check_limiter: Option<Arc<CheckLimiter>>,
+    /// Pre-generated random puzzles; mirrors don't serve random puzzles.
+    random_pool: Option<Arc<RandomPool>>,
+    today_cache: Arc<TodayCache>,
+    mirror_mode: bool,
 }
 
 #[derive(Serialize)]
@@ -28,11 +114,75 @@ struct PuzzleResponse {
     variants: Vec<String>,
     title: Option<String>,
     date_utc: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<ConstraintLabel>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    custom_rules: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_id: Option<String>,
+    /// Set when `today` serves yesterday's puzzle during the grace period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grace: Option<bool>,
+    /// Seed of a random puzzle generated from query options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// Passed back to check a random puzzle, until it expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    random_id: Option<String>,
+}
+
+/// Setter-facing `label`/`group` of a constraint; they never affect solving.
+#[derive(Serialize, Clone)]
+struct ConstraintLabel {
+    #[serde(rename = "type")]
+    kind: String,
+    label: Option<String>,
+    group: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SigningKeyResponse {
+    algorithm: &'static str,
+    key_id: String,
+    public_key: String,
 }
 
 #[derive(Deserialize)]
 struct CheckRequest {
     grid: String,
+    date_utc: Option<String>,
+    /// A random puzzle's `random_id`, checked instead of a dated puzzle.
+    random_id: Option<String>,
+}
+
+/// Admin-only override of the current date on the public today and check
+/// endpoints.
+#[derive(Deserialize)]
+struct AsOfQuery {
+    as_of: Option<String>,
+}
+
+/// Cuts the puzzle SVG down to some of its `layers::LAYERS`, e.g.
+/// `?exclude=givens` or `?layers=grid`.
+#[derive(Deserialize)]
+struct LayersQuery {
+    layers: Option<String>,
+    exclude: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RandomPuzzleQuery {
+    layers: Option<String>,
+    exclude: Option<String>,
+    /// Comma separated names from `random_options::RANDOM_VARIANTS`, or
+    /// `classic`.
+    variants: Option<String>,
+    clue_target: Option<usize>,
+    seed: Option<u64>,
+    /// `easy`, `medium` or `hard`, by the technique rating.
+    difficulty: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -53,18 +203,135 @@ struct StatsResponse {
     solves: i64,
 }
 
+/// Public stats of one puzzle, with progress toward its community goal.
+#[derive(Serialize)]
+struct PublicStatsResponse {
+    date_utc: String,
+    views: i64,
+    checks: i64,
+    solves: i64,
+    goal: Option<GoalProgress>,
+}
+
+#[derive(Serialize)]
+struct GoalProgress {
+    metric: String,
+    target: i64,
+    label: Option<String>,
+    current: i64,
+    /// `current / target`, capped at 1.
+    progress: f64,
+    reached: bool,
+}
+
+#[derive(Serialize)]
+struct StatsTimelineResponse {
+    date_utc: String,
+    hours: Vec<StatsHour>,
+}
+
+#[derive(Serialize)]
+struct StatsHour {
+    hour_utc: String,
+    views: i64,
+    checks: i64,
+    solves: i64,
+}
+
+#[derive(Deserialize)]
+struct StatsBatchRequest {
+    dates: Option<Vec<String>>,
+    month: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MetaResponse {
+    server_version: &'static str,
+    engine_version: &'static str,
+    constraint_types: Vec<&'static str>,
+    grid_sizes: &'static [usize],
+    features: MetaFeatures,
+    rng: MetaRng,
+}
+
+/// RNG used for generation, and how each generating endpoint seeds it:
+/// `request` seeds win; otherwise `entropy` or `fixed`.
+#[derive(Serialize)]
+struct MetaRng {
+    kind: &'static str,
+    custom_generation: &'static str,
+    /// `/api/puzzle/random`.
+    random_generation: &'static str,
+    /// `/api/admin/puzzles/generate`.
+    admin_generation: &'static str,
+}
+
+/// Optional capabilities that depend on how this instance is configured.
+#[derive(Serialize)]
+struct MetaFeatures {
+    mirror: bool,
+    signing: bool,
+    sync: bool,
+    feeds: bool,
+    png_rendering: bool,
+    social_posting: bool,
+}
+
+#[derive(Serialize)]
+struct ConstraintSchemaResponse {
+    common_fields: &'static [schema::FieldSchema],
+    types: &'static [schema::ConstraintSchema],
+}
+
+#[derive(Deserialize)]
+struct SyncQuery {
+    since: Option<String>,
+}
+
 #[derive(Serialize)]
 struct AdminGenerateResponse {
     puzzle_json: String,
     svg: String,
     variants: Vec<String>,
+    labels: Vec<ConstraintLabel>,
 }
 
 #[derive(Deserialize)]
+struct AdminGenerateQuery {
+    /// Day whose weekday profile applies, today by default.
+    date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
 struct AdminGenerateCustomRequest {
     constraints: serde_json::Value,
     clue_target: Option<usize>,
     seed: Option<u64>,
+    regenerate_solution: Option<bool>,
+    /// Gives up after this long, at most `MAKUDOKU_GENERATE_MAX_MILLIS`.
+    max_millis: Option<u64>,
+    /// Gives up after this many uniqueness checks while removing clues, at
+    /// most `MAKUDOKU_GENERATE_MAX_UNIQUENESS_CHECKS`.
+    max_uniqueness_checks: Option<usize>,
+    /// Digits the solution must have and the puzzle must keep as clues.
+    givens: Option<Vec<PinnedGiven>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PinnedGiven {
+    cell: (usize, usize),
+    digit: u8,
+}
+
+#[derive(Deserialize)]
+struct AdminRenderPreviewRequest {
+    constraints: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct AdminRenderPreviewResponse {
+    svg: String,
+    variants: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -78,11 +345,60 @@ struct AdminCreateRequest {
     author: Option<String>,
     difficulty: Option<i64>,
     overwrite: Option<bool>,
+    /// Without an `svg`, store none and render it on first read.
+    defer_render: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct AdminExportQuery {
+    /// `json` (default) or `ndjson`.
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AdminExportNdjsonQuery {
+    status: Option<String>,
+    /// Inclusive `YYYY-MM-DD` bounds.
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AdminRestoreQuery {
+    /// `skip_existing` (default) or `overwrite`.
+    mode: Option<String>,
+    dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct AdminRestoreItem {
+    date_utc: String,
+    /// `create`, `update`, `skip` or `fail`.
+    action: &'static str,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminRestoreResponse {
+    dry_run: bool,
+    created: usize,
+    updated: usize,
+    skipped: usize,
+    failed: usize,
+    items: Vec<AdminRestoreItem>,
 }
 
 #[derive(Deserialize)]
 struct AdminListQuery {
     status: Option<String>,
+    include: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PuzzleStatsSummary {
+    views: i64,
+    checks: i64,
+    solves: i64,
 }
 
 #[derive(Serialize)]
@@ -95,6 +411,12 @@ struct AdminPuzzleSummary {
     difficulty: Option<i64>,
     created_at_utc: String,
     published_at_utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deleted_at_utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<PuzzleStatsSummary>,
 }
 
 #[derive(Serialize)]
@@ -106,377 +428,5341 @@ struct AdminPuzzleResponse {
     puzzle_json: String,
     svg: Option<String>,
     variants: Vec<String>,
+    labels: Vec<ConstraintLabel>,
     difficulty: Option<i64>,
     created_at_utc: String,
     updated_at_utc: String,
     published_at_utc: Option<String>,
+    deleted_at_utc: Option<String>,
+    /// Where a puzzle imported from another instance came from.
+    origin: Option<serde_json::Value>,
 }
 
 #[derive(Debug)]
 struct ParsedPuzzleJson {
     puzzle: String,
     constraints: Vec<serde_json::Value>,
+    solution: Option<Vec<u8>>,
+    engine_version: Option<String>,
+    custom_rules: Vec<String>,
+    /// Jigsaw regions replacing the standard boxes.
+    regions: Option<Vec<Vec<(usize, usize)>>>,
+    /// False when the puzzle's rules go beyond its constraints, so a grid
+    /// can only be checked against the stored solution.
+    machine_checkable: bool,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    create_dir_all("data")?;
+#[derive(Deserialize)]
+struct AdminDryRunQuery {
+    dry_run: Option<bool>,
+}
 
-    let db_url = "sqlite:data/makudoku.db";
+#[derive(Serialize)]
+struct DifficultyBackfillItem {
+    date_utc: String,
+    difficulty: Option<i64>,
+    error: Option<String>,
+}
 
-    if !Sqlite::database_exists(db_url).await? {
-        Sqlite::create_database(db_url).await?;
-    }
+#[derive(Serialize)]
+struct DifficultyBackfillResponse {
+    dry_run: bool,
+    items: Vec<DifficultyBackfillItem>,
+}
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(10) // look into this!!!!
-        .connect(db_url)
-        .await?;
+#[derive(Deserialize)]
+struct AdminReverifyQuery {
+    all: Option<bool>,
+    dry_run: Option<bool>,
+}
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+#[derive(Serialize)]
+struct AdminReverifyItem {
+    date_utc: String,
+    engine_version: Option<String>,
+    ok: bool,
+    error: Option<String>,
+}
 
-    let state = AppState { db: pool };
+#[derive(Serialize)]
+struct AdminReverifyResponse {
+    engine_version: String,
+    dry_run: bool,
+    checked: usize,
+    failed: usize,
+    items: Vec<AdminReverifyItem>,
+}
 
-    let public_dir = ServeDir::new("public").append_index_html_on_directories(true);
-    let admin_dir = ServeDir::new("admin").append_index_html_on_directories(true);
+#[derive(Deserialize)]
+struct AdminSlugRequest {
+    slug: String,
+    date_utc: String,
+}
 
-    let app = Router::new()
-        .route("/api/puzzle/today", get(today_puzzle_handler))
-        .route("/api/puzzle/random", get(random_puzzle_handler))
-        .route("/api/puzzle/check", post(check_puzzle_handler))
-        .route("/api/puzzle/track", post(track_event_handler))
-        .route("/api/admin/puzzles/generate", post(admin_generate_handler))
-        .route(
-            "/api/admin/puzzles/generate/custom",
-            post(admin_generate_custom_handler),
-        )
-        .route("/api/admin/puzzles", post(admin_create_handler))
-        .route("/api/admin/puzzles", get(admin_list_handler))
-        .route("/api/admin/puzzles/{date_utc}", get(admin_get_handler))
-        .route("/api/admin/stats/{date_utc}", get(admin_stats_handler))
-        .route(
-            "/api/admin/puzzles/{date_utc}/publish",
-            post(admin_publish_handler),
-        )
-        .route(
-            "/api/admin/puzzles/{date_utc}/archive",
-            post(admin_archive_handler),
-        )
-        .with_state(state)
-        .nest_service("/admin", admin_dir)
-        .fallback_service(public_dir);
+#[derive(Serialize)]
+struct AdminSlug {
+    slug: String,
+    date_utc: String,
+    created_at_utc: String,
+}
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    println!("listening on http://{}", listener.local_addr()?);
+#[derive(Deserialize)]
+struct AdminPublishQuery {
+    /// Publish even when checklist items are unmet.
+    force: Option<bool>,
+}
 
-    axum::serve(listener, app).await?;
-    Ok(())
+#[derive(Serialize)]
+struct PublishChecklistResponse {
+    error: &'static str,
+    unmet: Vec<String>,
 }
 
-pub fn variant_kinds(input: &[VariantSpec]) -> Vec<String> {
-    let mut seen = HashSet::new();
+/// Lists the checklist items a forced publish skipped.
+const UNMET_CHECKLIST_HEADER: &str = "x-unmet-checklist";
 
-    input
-        .iter()
-        .filter_map(|v| {
-            let k = v.kind_str();
-            seen.insert(k).then_some(k.to_string())
-        })
-        .collect()
+#[derive(Serialize)]
+struct AdminSudokuPadExportResponse {
+    date_utc: String,
+    url: String,
+    /// Constraint kinds SudokuPad can't show, left out of the link.
+    skipped: Vec<String>,
 }
 
-async fn today_puzzle_handler(State(state): State<AppState>) -> impl IntoResponse {
-    // Compute today's UTC date
-    let today = Utc::now().date_naive().to_string();
+#[derive(Deserialize)]
+struct AdminTestSolveRequest {
+    /// Defaults to `admin`; each tester has one test solve per puzzle.
+    tester: Option<String>,
+    grid: String,
+    notes: Option<String>,
+    /// `pass` or `fail`; omitted while the solve is in progress.
+    verdict: Option<String>,
+}
 
-    let row = sqlx::query!(
-        r#"
-        SELECT svg, variants, title
-        FROM puzzles
-        WHERE date_utc = ? AND status = 'published'
-        "#,
-        today
-    )
-    .fetch_optional(&state.db)
-    .await;
+#[derive(Serialize)]
+struct AdminTestSolve {
+    tester: String,
+    grid: String,
+    notes: Option<String>,
+    verdict: Option<String>,
+    updated_at_utc: String,
+}
 
-    let row = match row {
-        Ok(Some(row)) => row,
-        Ok(None) => {
-            return (StatusCode::NOT_FOUND, "Today's puzzle is not published yet").into_response();
-        }
-        Err(e) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
-        }
-    };
+#[derive(Serialize)]
+struct AdminTestSolvesResponse {
+    date_utc: String,
+    test_solves: Vec<AdminTestSolve>,
+}
 
-    // variants is stored as JSON array string
-    let variants: Vec<String> =
-        serde_json::from_str(row.variants.as_deref().unwrap_or("[]")).unwrap_or_default();
+#[derive(Deserialize)]
+struct AdminScheduleQuery {
+    /// Days listed from today, 14 by default.
+    days: Option<usize>,
+}
 
-    Json(PuzzleResponse {
-        svg: row.svg,
-        variants,
-        title: row.title,
-        date_utc: Some(today),
-    })
-    .into_response()
+#[derive(Serialize)]
+struct ScheduleDay {
+    date_utc: String,
+    /// `missing`, `draft`, `scheduled` (published ahead of its date),
+    /// `published` or `archived`.
+    state: &'static str,
+    name: Option<String>,
 }
 
-async fn random_puzzle_handler() -> impl IntoResponse {
-    let cfg = GenerationConfig::default();
-    let render_options = RenderOptions::default();
+#[derive(Serialize)]
+struct AdminScheduleResponse {
+    today: String,
+    /// Consecutive days from today with a published puzzle.
+    runway_days: usize,
+    /// The same, also counting drafts still to be published.
+    runway_days_with_drafts: usize,
+    days: Vec<ScheduleDay>,
+}
 
-    let result = tokio::task::spawn_blocking(move || {
-        let puzzle = generate_random_variant_puzzle(cfg)?;
-        let puzzle_svg =
-            render_puzzle_svg(&puzzle.puzzle, &puzzle.engine.constraints, render_options)?;
-        let variants = variant_kinds(&puzzle.constraints);
-        Ok::<_, String>((puzzle_svg, variants))
-    })
-    .await;
+#[derive(Deserialize)]
+struct AdminDifficultyCurveQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
 
-    let result = match result {
-        Ok(result) => result,
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Generator task failed: {err}"),
-            )
-                .into_response();
-        }
-    };
+#[derive(Serialize)]
+struct DifficultyDay {
+    date_utc: String,
+    /// As in the schedule: `missing`, `draft`, `scheduled`, `published` or
+    /// `archived`.
+    state: &'static str,
+    difficulty: Option<i64>,
+    /// Set when the puzzle has no stored difficulty and it was estimated.
+    estimated: bool,
+    /// Variant kinds, sorted.
+    variants: Vec<String>,
+}
 
-    let (puzzle_svg, variants) = match result {
-        Ok(result) => result,
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to generate puzzle: {err}"),
-            )
-                .into_response();
-        }
-    };
+#[derive(Serialize)]
+struct CurveWarning {
+    /// `hard_streak` or `repeated_variants`.
+    kind: &'static str,
+    dates: Vec<String>,
+    message: String,
+}
 
-    Json(PuzzleResponse {
-        svg: Some(puzzle_svg),
-        variants,
-        title: None,
-        date_utc: None,
-    })
-    .into_response()
+#[derive(Serialize)]
+struct AdminDifficultyCurveResponse {
+    from: String,
+    to: String,
+    days: Vec<DifficultyDay>,
+    warnings: Vec<CurveWarning>,
 }
 
-fn parse_solution_from_json(value: &serde_json::Value) -> Result<Vec<u8>, String> {
-    let sol = value
-        .get("solution")
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| "solution missing".to_string())?;
-    if sol.len() != NN {
-        return Err("solution must have 81 digits".to_string());
-    }
-    let mut out = Vec::with_capacity(NN);
-    for item in sol {
-        let n = item
-            .as_u64()
-            .ok_or_else(|| "solution digits must be numbers".to_string())?;
-        if n < 1 || n > 9 {
+#[derive(Deserialize)]
+struct AdminGoalRequest {
+    /// `views`, `checks` or `solves` (default).
+    metric: Option<String>,
+    target: i64,
+    label: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminGoal {
+    date_utc: String,
+    metric: String,
+    target: i64,
+    label: Option<String>,
+    updated_at_utc: String,
+}
+
+#[derive(Deserialize)]
+struct AdminImportClassicRequest {
+    /// One puzzle per line: 81 characters of `1-9` and `.`/`0`, optionally
+    /// followed by a 1-10 difficulty. Blank lines and `#` comments are skipped.
+    text: String,
+    start_date: String,
+    status: Option<String>,
+    author: Option<String>,
+    overwrite: Option<bool>,
+    /// Store the puzzles without an SVG; each is rendered on first read.
+    defer_render: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct AdminImportItem {
+    line: usize,
+    date_utc: Option<String>,
+    ok: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminImportResponse {
+    imported: usize,
+    items: Vec<AdminImportItem>,
+}
+
+#[derive(Serialize)]
+struct ImportProblem {
+    /// `parse`, `duplicate_date`, `exists`, `invalid`, `verify` (not
+    /// unique, or a bad stored solution) or `internal`.
+    kind: &'static str,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct AdminImportValidateItem {
+    line: usize,
+    date_utc: Option<String>,
+    ok: bool,
+    problems: Vec<ImportProblem>,
+}
+
+#[derive(Serialize)]
+struct AdminImportValidateResponse {
+    valid: usize,
+    invalid: usize,
+    items: Vec<AdminImportValidateItem>,
+}
+
+#[derive(Deserialize)]
+struct AdminExchangeImportRequest {
+    document: exchange::ExchangeDocument,
+    /// Date of the first puzzle; the rest follow day by day.
+    start_date: String,
+    overwrite: Option<bool>,
+    /// Only import puzzles signed by a key in `MAKUDOKU_EXCHANGE_TRUSTED_KEYS`.
+    require_trusted: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct AdminExchangeImportItem {
+    origin_date_utc: String,
+    date_utc: String,
+    /// `trusted`, `untrusted`, `invalid` or `unsigned`.
+    signature: &'static str,
+    ok: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminExchangeImportResponse {
+    imported: usize,
+    items: Vec<AdminExchangeImportItem>,
+}
+
+#[derive(Deserialize)]
+struct AdminImportFpuzzlesRequest {
+    /// An f-puzzles link, its `load` payload or the decompressed JSON.
+    source: String,
+}
+
+#[derive(Serialize)]
+struct AdminImportFpuzzlesResponse {
+    puzzle_json: String,
+    svg: String,
+    variants: Vec<String>,
+    labels: Vec<ConstraintLabel>,
+    title: Option<String>,
+    author: Option<String>,
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct AdminDigestResponse {
+    dry_run: bool,
+    subject: String,
+    text: String,
+    digest: digest::WeeklyDigest,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let config = Config::from_env()?;
+    let signer = PuzzleSigner::from_config(&config)?.map(Arc::new);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mirror_mode = args.iter().any(|arg| arg == "--mirror");
+
+    create_dir_all("data")?;
+
+    let db_url = "sqlite:data/makudoku.db";
+
+    if !Sqlite::database_exists(db_url).await? {
+        Sqlite::create_database(db_url).await?;
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(10) // look into this!!!!
+        .connect(db_url)
+        .await?;
+
+    migrate::run_migrations(&pool).await?;
+    warn_on_engine_version_mismatch(&pool).await?;
+
+    if args.first().map(String::as_str) == Some("backfill-difficulty") {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let items = backfill_difficulty(&pool, dry_run).await?;
+        for item in &items {
+            match (&item.difficulty, &item.error) {
+                (Some(difficulty), _) => println!("{} {difficulty}", item.date_utc),
+                (None, Some(err)) => println!("{} error: {err}", item.date_utc),
+                (None, None) => {}
+            }
+        }
+        println!(
+            "{} puzzle(s) {}",
+            items
+                .iter()
+                .filter(|item| item.difficulty.is_some())
+                .count(),
+            if dry_run { "would be rated" } else { "rated" }
+        );
+        return Ok(());
+    }
+
+    if args.first().map(String::as_str) == Some("benchmark") {
+        let samples = args
+            .iter()
+            .position(|arg| arg == "--samples")
+            .and_then(|i| args.get(i + 1))
+            .map(|value| value.parse())
+            .transpose()
+            .context("--samples must be a number")?;
+        let options = benchmarks::BenchmarkOptions {
+            samples,
+            ..Default::default()
+        };
+        let (cases, samples) = benchmarks::plan(&options).map_err(anyhow::Error::msg)?;
+        let run = benchmarks::run_and_save(&pool, cases, samples)
+            .await
+            .map_err(anyhow::Error::msg)?;
+        let ms = |value: Option<f64>| value.map_or("-".to_string(), |ms| format!("{ms:.1}ms"));
+        for case in &run.cases {
+            println!(
+                "{} {} generate {} unique {} ({} failed)",
+                case.variant_set,
+                case.clue_target,
+                ms(case.generate_ms),
+                ms(case.unique_check_ms),
+                case.failed
+            );
+        }
+        println!("run {} on engine {}", run.id, run.engine_version);
+        return Ok(());
+    }
+
+    let events = EventStream::from_config(&config);
+    let check_limiter = CheckLimiter::new(
+        Duration::from_secs(config.check_window_secs),
+        config.check_limits.clone(),
+    )
+    .map(Arc::new);
+    let rate_limiter = RateLimiter::new(
+        config
+            .rate_limit_cheap
+            .map(|(limit, secs)| (limit, Duration::from_secs(secs))),
+        config
+            .rate_limit_expensive
+            .map(|(limit, secs)| (limit, Duration::from_secs(secs))),
+        config.rate_limit_trust_proxy,
+    )
+    .map(Arc::new);
+    let random_pool = RandomPool::new(config.random_pool_size)
+        .filter(|_| !mirror_mode)
+        .map(Arc::new);
+    let state = AppState {
+        db: pool,
+        config: Arc::new(config),
+        signer,
+        admin_sessions: Arc::new(AdminSessions::default()),
+        job_progress: Arc::new(JobProgressBoard::default()),
+        activity: Arc::new(ActivityFeed::default()),
+        events,
+        check_limiter,
+        random_pool,
+        today_cache: Arc::new(TodayCache::default()),
+        mirror_mode,
+    };
+
+    let public_dir = ServeDir::new("public").append_index_html_on_directories(true);
+
+    // Mirrors count checks and solves too, so they keep solve records.
+    if state.config.solve_record_retention_days > 0 {
+        tokio::spawn(retention::run_retention_loop(
+            state.db.clone(),
+            state.config.solve_record_retention_days,
+        ));
+    }
+
+    let app = if mirror_mode {
+        let upstream = state
+            .config
+            .mirror_upstream
+            .clone()
+            .context("--mirror requires MAKUDOKU_MIRROR_UPSTREAM")?;
+        tracing::info!("running as a read-only mirror of {upstream}");
+        tokio::spawn(mirror::run_sync_loop(
+            state.db.clone(),
+            upstream,
+            state.config.mirror_token.clone(),
+            Duration::from_secs(state.config.mirror_interval_secs),
+        ));
+        mirror_router(state, public_dir)
+    } else {
+        if social::Network::ALL
+            .iter()
+            .any(|n| n.is_configured(&state.config))
+        {
+            tokio::spawn(social::run_daily_loop(
+                state.db.clone(),
+                state.config.clone(),
+            ));
+        }
+        tokio::spawn(run_job_worker(state.clone()));
+        if let Some(pool) = &state.random_pool {
+            tokio::spawn(random_pool::run_refill_loop(
+                pool.clone(),
+                state.config.clone(),
+            ));
+        }
+        if state.config.random_puzzle_ttl_hours > 0 {
+            tokio::spawn(retention::run_random_puzzle_expiry_loop(
+                state.db.clone(),
+                state.config.random_puzzle_ttl_hours,
+            ));
+        }
+        if state.config.auto_publish {
+            tokio::spawn(run_auto_publish_loop(state.clone()));
+        }
+        if state.config.low_queue_webhook_url.is_some() {
+            tokio::spawn(alerts::run_low_queue_loop(
+                state.db.clone(),
+                state.config.clone(),
+            ));
+        }
+        if !state.config.digest_recipients.is_empty() {
+            tokio::spawn(digest::run_weekly_loop(
+                state.db.clone(),
+                state.config.clone(),
+            ));
+        }
+        app_router(state, public_dir)
+    };
+    let app = match rate_limiter {
+        Some(limiter) => app.layer(middleware::from_fn_with_state(
+            limiter,
+            rate_limit::rate_limit_middleware,
+        )),
+        None => app,
+    };
+    let app = app.layer(middleware::from_fn(request_id::request_id_middleware));
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("listening on http://{}", listener.local_addr()?);
+
+    // Peer addresses key the rate limits.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+fn app_router(state: AppState, public_dir: ServeDir) -> Router {
+    let admin_dir = ServeDir::new("admin").append_index_html_on_directories(true);
+
+    Router::new()
+        .route("/api/puzzle/today", get(today_puzzle_handler))
+        .route("/api/puzzle/random", get(random_puzzle_handler))
+        .route("/api/puzzle/slug/{slug}", get(slug_puzzle_handler))
+        .route("/p/{slug}", get(slug_redirect_handler))
+        .route("/api/puzzle/check", post(check_puzzle_handler))
+        .route("/api/puzzle/candidates", post(candidates_handler))
+        .route("/api/puzzle/track", post(track_event_handler))
+        .route("/api/puzzle/{date_utc}/thumbnail.png", get(thumbnail_handler))
+        .route("/api/puzzle/{date_utc}/worksheet.pdf", get(worksheet_handler))
+        .route("/api/puzzle/{date_utc}/stats", get(public_stats_handler))
+        .route("/api/me/data", delete(delete_my_data_handler))
+        .route("/api/meta", get(meta_handler))
+        .route("/api/meta/branding", get(branding_handler))
+        .route("/api/meta/signing-key", get(signing_key_handler))
+        .route("/api/sync/puzzles", get(sync_puzzles_handler))
+        .route("/feed.xml", get(feed_handler))
+        .route("/feed/{file}", get(variant_feed_handler))
+        .route("/api/admin/login", post(admin_auth::login_handler))
+        .route("/api/admin/logout", post(admin_auth::logout_handler))
+        .merge(admin_router(state.clone()))
+        .with_state(state)
+        .nest_service("/admin", admin_dir)
+        .fallback_service(public_dir)
+}
+
+/// The admin API, behind `require_admin`. Login and logout stay outside it.
+fn admin_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/puzzles/generate", post(admin_generate_handler))
+        .route(
+            "/api/admin/puzzles/generate/custom",
+            post(admin_generate_custom_handler),
+        )
+        .route(
+            "/api/admin/jobs/generate",
+            post(admin_enqueue_generate_handler),
+        )
+        .route("/api/admin/jobs/{id}", get(admin_job_handler))
+        .route("/api/admin/jobs/{id}/events", get(admin_job_events_handler))
+        .route(
+            "/api/admin/jobs/{id}/cancel",
+            post(admin_job_cancel_handler),
+        )
+        .route(
+            "/api/admin/settings/generation-profiles",
+            get(admin_get_generation_profiles_handler).post(admin_save_generation_profiles_handler),
+        )
+        .route(
+            "/api/admin/settings/branding",
+            get(branding_handler).post(admin_save_branding_handler),
+        )
+        .route(
+            "/api/admin/constraints/render-preview",
+            post(admin_render_preview_handler),
+        )
+        .route("/api/admin/puzzles", post(admin_create_handler))
+        .route("/api/admin/puzzles", get(admin_list_handler))
+        .route(
+            "/api/admin/puzzles/{date_utc}",
+            get(admin_get_handler).delete(admin_delete_handler),
+        )
+        .route("/api/admin/stats/batch", post(admin_stats_batch_handler))
+        .route("/api/admin/stats/{date_utc}", get(admin_stats_handler))
+        .route(
+            "/api/admin/stats/{date_utc}/timeline",
+            get(admin_stats_timeline_handler),
+        )
+        .route(
+            "/api/admin/maintenance/reverify",
+            post(admin_reverify_handler),
+        )
+        .route(
+            "/api/admin/maintenance/backfill-difficulty",
+            post(admin_backfill_difficulty_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/export/sudokupad",
+            get(admin_export_sudokupad_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/test-solves",
+            get(admin_test_solves_handler).post(admin_save_test_solve_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/publish",
+            post(admin_publish_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/archive",
+            post(admin_archive_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/restore",
+            post(admin_restore_deleted_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/goal",
+            get(admin_goal_handler)
+                .post(admin_save_goal_handler)
+                .delete(admin_delete_goal_handler),
+        )
+        .route(
+            "/api/admin/puzzles/{date_utc}/social-preview",
+            get(admin_social_preview_handler),
+        )
+        .route(
+            "/api/admin/puzzles/analyze/minimality",
+            post(admin_minimality_handler),
+        )
+        .route(
+            "/api/admin/puzzles/analyze/difficulty",
+            post(admin_difficulty_handler),
+        )
+        .route(
+            "/api/admin/puzzles/analyze/path",
+            post(admin_solve_path_handler),
+        )
+        .route(
+            "/api/admin/puzzles/analyze/solutions",
+            post(admin_solution_count_handler),
+        )
+        .route("/api/admin/puzzles/solve", post(admin_solve_handler))
+        .route(
+            "/api/admin/puzzles/import/classic",
+            post(admin_import_classic_handler),
+        )
+        .route(
+            "/api/admin/puzzles/import/bulk",
+            post(admin_import_bulk_handler).layer(DefaultBodyLimit::max(MAX_BULK_IMPORT_BYTES)),
+        )
+        .route(
+            "/api/admin/puzzles/import/validate",
+            post(admin_import_validate_handler).layer(DefaultBodyLimit::max(MAX_BULK_IMPORT_BYTES)),
+        )
+        .route(
+            "/api/admin/puzzles/import/fpuzzles",
+            post(admin_import_fpuzzles_handler),
+        )
+        .route("/api/admin/schedule", get(admin_schedule_handler))
+        .route(
+            "/api/admin/schedule/difficulty",
+            get(admin_schedule_difficulty_handler),
+        )
+        .route("/api/admin/export", get(admin_export_handler))
+        .route(
+            "/api/admin/puzzles/export.ndjson",
+            get(admin_export_ndjson_handler),
+        )
+        .route(
+            "/api/admin/import",
+            post(admin_restore_handler).layer(DefaultBodyLimit::max(MAX_BULK_IMPORT_BYTES)),
+        )
+        .route(
+            "/api/admin/exchange/export",
+            get(admin_exchange_export_handler),
+        )
+        .route(
+            "/api/admin/exchange/import",
+            post(admin_exchange_import_handler).layer(DefaultBodyLimit::max(MAX_BULK_IMPORT_BYTES)),
+        )
+        .route("/api/admin/benchmarks", get(admin_benchmarks_handler))
+        .route(
+            "/api/admin/benchmarks/run",
+            post(admin_run_benchmark_handler),
+        )
+        .route(
+            "/api/admin/benchmarks/compare",
+            get(admin_benchmark_compare_handler),
+        )
+        .route(
+            "/api/admin/activity/stream",
+            get(admin_activity_stream_handler),
+        )
+        .route("/api/admin/slugs", get(admin_slugs_handler))
+        .route("/api/admin/slugs", post(admin_save_slug_handler))
+        .route("/api/admin/slugs/{slug}", delete(admin_delete_slug_handler))
+        .route(
+            "/api/admin/digest/weekly",
+            post(admin_weekly_digest_handler),
+        )
+        .route(
+            "/api/admin/constraints/schema",
+            get(admin_constraint_schema_handler),
+        )
+        .route("/api/admin/session", get(admin_auth::session_handler))
+        .route_layer(middleware::from_fn_with_state(
+            state,
+            admin_auth::require_admin,
+        ))
+}
+
+/// Public read API only: no admin, no generation.
+fn mirror_router(state: AppState, public_dir: ServeDir) -> Router {
+    Router::new()
+        .route("/api/puzzle/today", get(today_puzzle_handler))
+        .route("/api/puzzle/check", post(check_puzzle_handler))
+        .route("/api/puzzle/candidates", post(candidates_handler))
+        .route("/api/puzzle/track", post(track_event_handler))
+        .route("/api/me/data", delete(delete_my_data_handler))
+        .route("/api/meta", get(meta_handler))
+        .route("/api/meta/branding", get(branding_handler))
+        .with_state(state)
+        .fallback_service(public_dir)
+}
+
+/// Logs stored puzzles whose recorded engine version differs from the linked
+/// engine, since solver changes can silently break old seeds.
+async fn warn_on_engine_version_mismatch(pool: &SqlitePool) -> anyhow::Result<()> {
+    for row in store::other_engine_versions(pool, ENGINE_VERSION).await? {
+        match row.engine_version {
+            Some(version) => tracing::warn!(
+                "{} puzzle(s) were generated with engine {version}, linked engine is {ENGINE_VERSION}; \
+                 run POST /api/admin/maintenance/reverify",
+                row.count
+            ),
+            None => tracing::info!("{} puzzle(s) have no recorded engine version", row.count),
+        }
+    }
+    Ok(())
+}
+
+pub fn variant_kinds(input: &[VariantSpec]) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    input
+        .iter()
+        .filter_map(|v| {
+            let k = v.kind_str();
+            seen.insert(k).then_some(k.to_string())
+        })
+        .collect()
+}
+
+async fn today_puzzle_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AsOfQuery>,
+    Query(layer_query): Query<LayersQuery>,
+) -> Response {
+    let today = match effective_today(&state, &headers, query.as_of.as_deref()) {
+        Ok(today) => today,
+        Err(response) => return response,
+    };
+    let hidden = match layers::hidden(
+        layer_query.layers.as_deref(),
+        layer_query.exclude.as_deref(),
+    ) {
+        Ok(hidden) => hidden,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    let mut response = published_puzzle_response(
+        &state,
+        today.to_string(),
+        "Today's puzzle is not published yet",
+        false,
+        &hidden,
+    )
+    .await;
+    // A scheduler or admin running a few minutes late shouldn't leave
+    // players with a 404 right after midnight.
+    if response.status() == StatusCode::NOT_FOUND
+        && query.as_of.is_none()
+        && in_grace_period(&state.config)
+    {
+        let yesterday = today - chrono::Duration::days(1);
+        let fallback = published_puzzle_response(
+            &state,
+            yesterday.to_string(),
+            "Today's puzzle is not published yet",
+            true,
+            &hidden,
+        )
+        .await;
+        if fallback.status() != StatusCode::NOT_FOUND {
+            response = fallback;
+        }
+    }
+    if query.as_of.is_some() {
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    }
+    response
+}
+
+/// Whether the UTC day started less than `MAKUDOKU_TODAY_GRACE_MINUTES` ago.
+fn in_grace_period(config: &Config) -> bool {
+    u64::from(Utc::now().num_seconds_from_midnight()) < config.today_grace_minutes * 60
+}
+
+/// Today's UTC date, or the `as_of` date an admin passed to preview a
+/// rollover without changing the server clock.
+fn effective_today(
+    state: &AppState,
+    headers: &HeaderMap,
+    as_of: Option<&str>,
+) -> Result<NaiveDate, Response> {
+    let Some(as_of) = as_of else {
+        return Ok(Utc::now().date_naive());
+    };
+    if !admin_auth::is_admin(state, headers) {
+        return Err((StatusCode::FORBIDDEN, "as_of requires admin login").into_response());
+    }
+    NaiveDate::parse_from_str(as_of, "%Y-%m-%d")
+        .map_err(|_| (StatusCode::BAD_REQUEST, "as_of must be YYYY-MM-DD").into_response())
+}
+
+/// The public puzzle response for a published date; `grace` marks
+/// yesterday's puzzle standing in for today's. The `hidden` layers are cut
+/// from the SVG before it is signed. Today's puzzle is served from
+/// `today_cache` once loaded.
+async fn published_puzzle_response(
+    state: &AppState,
+    date_utc: String,
+    not_found: &'static str,
+    grace: bool,
+    hidden: &[&str],
+) -> Response {
+    // Only today's date is cached, so slugs of older puzzles don't evict it.
+    let is_today = date_utc == Utc::now().date_naive().to_string();
+    let cached = if is_today {
+        state.today_cache.get(&date_utc)
+    } else {
+        None
+    };
+    let puzzle = match cached {
+        Some(puzzle) => puzzle,
+        None => {
+            let generation = state.today_cache.generation();
+            let puzzle = match load_published_puzzle(state, &date_utc, not_found).await {
+                Ok(puzzle) => Arc::new(puzzle),
+                Err(response) => return response,
+            };
+            if is_today {
+                state.today_cache.put(generation, &date_utc, puzzle.clone());
+            }
+            puzzle
+        }
+    };
+
+    Json(sign_puzzle_response(
+        state,
+        PuzzleResponse {
+            svg: Some(layers::strip(&puzzle.svg, hidden)),
+            variants: puzzle.variants.clone(),
+            title: puzzle.title.clone(),
+            date_utc: Some(date_utc),
+            labels: puzzle.labels.clone(),
+            custom_rules: puzzle.custom_rules.clone(),
+            signature: None,
+            key_id: None,
+            grace: grace.then_some(true),
+            seed: None,
+            random_id: None,
+        },
+    ))
+    .into_response()
+}
+
+/// Reads a published puzzle's row into the fields its public response
+/// needs, rendering and storing its SVG if it has none yet.
+async fn load_published_puzzle(
+    state: &AppState,
+    date_utc: &str,
+    not_found: &'static str,
+) -> Result<CachedPuzzle, Response> {
+    let row = match store::get_public_puzzle(&state.db, date_utc, false, None).await {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return Err((StatusCode::NOT_FOUND, not_found).into_response());
+        }
+        Err(e) => {
+            return Err(
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+            );
+        }
+    };
+
+    // variants is stored as JSON array string
+    let variants: Vec<String> =
+        serde_json::from_str(row.variants.as_deref().unwrap_or("[]")).unwrap_or_default();
+    let labels = stored_constraint_labels(&row.puzzle_json);
+    let custom_rules = parse_puzzle_json(&row.puzzle_json)
+        .map(|parsed| parsed.custom_rules)
+        .unwrap_or_default();
+    let svg = ensure_svg(&state.db, date_utc, row.svg, &row.puzzle_json)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err).into_response())?;
+
+    Ok(CachedPuzzle {
+        svg,
+        variants,
+        title: row.title,
+        labels,
+        custom_rules,
+    })
+}
+
+/// The date a slug points to, once that puzzle is public: published and not
+/// scheduled for a later day.
+async fn resolve_slug(db: &SqlitePool, slug: &str) -> Result<Option<String>, sqlx::Error> {
+    let today = Utc::now().date_naive().to_string();
+    store::public_slug_date(db, slug, &today).await
+}
+
+async fn slug_puzzle_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(layer_query): Query<LayersQuery>,
+) -> Response {
+    let hidden = match layers::hidden(
+        layer_query.layers.as_deref(),
+        layer_query.exclude.as_deref(),
+    ) {
+        Ok(hidden) => hidden,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    match resolve_slug(&state.db, &slug).await {
+        Ok(Some(date_utc)) => {
+            published_puzzle_response(&state, date_utc, "Puzzle not found", false, &hidden).await
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+    }
+}
+
+/// `/p/{slug}` opens the player on the slug's puzzle.
+async fn slug_redirect_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Response {
+    match resolve_slug(&state.db, &slug).await {
+        Ok(Some(_)) => Redirect::temporary(&format!("/?p={slug}")).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+    }
+}
+
+/// A random puzzle. Without options it comes from the pool when there is
+/// one; `variants`, `clue_target` or `seed` generate one on request.
+/// `difficulty` keeps to puzzles rated within that band.
+async fn random_puzzle_handler(
+    State(state): State<AppState>,
+    Query(query): Query<RandomPuzzleQuery>,
+) -> impl IntoResponse {
+    let hidden = match layers::hidden(query.layers.as_deref(), query.exclude.as_deref()) {
+        Ok(hidden) => hidden,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    let options =
+        match RandomOptions::parse(query.variants.as_deref(), query.clue_target, query.seed) {
+            Ok(options) => options,
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        };
+    let band = match query.difficulty.as_deref().map(difficulty::Band::parse) {
+        Some(Ok(band)) => Some(band),
+        Some(Err(err)) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        None => None,
+    };
+
+    let pooled = match &options {
+        Some(_) => None,
+        None => state.random_pool.as_ref().and_then(|pool| pool.take(band)),
+    };
+    let puzzle = match pooled {
+        Some(puzzle) => puzzle,
+        None => {
+            // Options the player chose can make generation fail; without
+            // them a failure is the server's.
+            let status = if options.is_some() {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            let options = options.unwrap_or_default();
+            let config = state.config.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let limits = GenerationLimits::server(&config);
+                random_options::generate(&options, band, &limits, config.random_rng_seeding)
+            })
+            .await;
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Generator task failed: {err}"),
+                    )
+                        .into_response();
+                }
+            };
+            match result {
+                Ok(puzzle) => puzzle,
+                Err(err) => return (status, err).into_response(),
+            }
+        }
+    };
+
+    // Without a stored copy the puzzle can still be played, just not checked.
+    let random_id = uuid::Uuid::new_v4().to_string();
+    let random_id =
+        match store::insert_random_puzzle(&state.db, &random_id, &puzzle.puzzle_json).await {
+            Ok(()) => Some(random_id),
+            Err(e) => {
+                tracing::warn!("cannot store random puzzle: {e}");
+                None
+            }
+        };
+
+    Json(sign_puzzle_response(
+        &state,
+        PuzzleResponse {
+            svg: Some(layers::strip(&puzzle.svg, &hidden)),
+            variants: puzzle.variants,
+            title: None,
+            date_utc: None,
+            labels: Vec::new(),
+            custom_rules: Vec::new(),
+            signature: None,
+            key_id: None,
+            grace: None,
+            seed: Some(puzzle.seed),
+            random_id,
+        },
+    ))
+    .into_response()
+}
+
+/// Returns the stored SVG, first rendering and storing it for puzzles saved
+/// without one.
+async fn ensure_svg(
+    db: &SqlitePool,
+    date_utc: &str,
+    svg: Option<String>,
+    puzzle_json: &str,
+) -> Result<String, String> {
+    if let Some(svg) = svg {
+        return Ok(svg);
+    }
+
+    let puzzle_json = puzzle_json.to_string();
+    let svg = tokio::task::spawn_blocking(move || render_stored_svg(&puzzle_json))
+        .await
+        .map_err(|err| format!("Render task failed: {err}"))?
+        .map_err(|err| format!("Failed to render puzzle: {err}"))?;
+
+    if let Err(e) = store::set_missing_svg(db, date_utc, &svg).await {
+        tracing::warn!("failed to store rendered svg for {date_utc}: {e}");
+    }
+    Ok(svg)
+}
+
+fn render_stored_svg(puzzle_json: &str) -> Result<String, String> {
+    let parsed = parse_puzzle_json(puzzle_json)?;
+    let specs = constraints_from_json(&parsed.constraints)?;
+    let constraints = engine_constraints_from_specs(&specs, parsed.regions.as_deref());
+    render_puzzle_svg(&parsed.puzzle, &constraints, RenderOptions::default())
+}
+
+fn sign_puzzle_response(state: &AppState, mut response: PuzzleResponse) -> PuzzleResponse {
+    if let Some(signer) = &state.signer {
+        response.signature = Some(signer.sign_puzzle(
+            response.date_utc.as_deref(),
+            response.title.as_deref(),
+            &response.variants,
+            response.svg.as_deref(),
+        ));
+        response.key_id = Some(signer.key_id().to_string());
+    }
+    response
+}
+
+async fn sync_puzzles_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SyncQuery>,
+) -> impl IntoResponse {
+    let Some(expected) = state.config.sync_token.as_deref() else {
+        return (StatusCode::NOT_FOUND, "Sync is not enabled").into_response();
+    };
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| admin_auth::tokens_match(token, expected));
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Invalid sync token").into_response();
+    }
+
+    let rows = match store::changed_since(&state.db, query.since.as_deref()).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let (rows, removed): (Vec<_>, Vec<_>) = rows
+        .into_iter()
+        .partition(|row| row.status == "published" && row.deleted_at_utc.is_none());
+    let removed = removed
+        .into_iter()
+        .map(|row| SyncRemoval {
+            date_utc: row.date_utc,
+            status: row.status,
+            deleted_at_utc: row.deleted_at_utc,
+            updated_at_utc: row.updated_at_utc,
+        })
+        .collect();
+    let puzzles = rows
+        .into_iter()
+        .map(|row| SyncPuzzle {
+            date_utc: row.date_utc,
+            puzzle_json: row.puzzle_json,
+            svg: row.svg,
+            title: row.title,
+            author: row.author,
+            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
+                .unwrap_or_default(),
+            difficulty: row.difficulty,
+            published_at_utc: row.published_at_utc,
+            updated_at_utc: row.updated_at_utc,
+        })
+        .collect();
+
+    Json(SyncResponse { puzzles, removed }).into_response()
+}
+
+async fn meta_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let config = &state.config;
+    Json(MetaResponse {
+        server_version: env!("CARGO_PKG_VERSION"),
+        engine_version: ENGINE_VERSION,
+        constraint_types: schema::constraint_types(),
+        grid_sizes: &[grid_codec::CLASSIC_SIZE],
+        features: MetaFeatures {
+            mirror: state.mirror_mode,
+            signing: state.signer.is_some(),
+            sync: !state.mirror_mode && config.sync_token.is_some(),
+            feeds: !state.mirror_mode,
+            png_rendering: true,
+            social_posting: !state.mirror_mode
+                && social::Network::ALL.iter().any(|n| n.is_configured(config)),
+        },
+        rng: MetaRng {
+            kind: rng::RNG_KIND,
+            custom_generation: config.custom_rng_seeding.name(),
+            random_generation: config.random_rng_seeding.name(),
+            admin_generation: config.generate_rng_seeding.name(),
+        },
+    })
+}
+
+/// Site name, colors and footer links for the public page to apply.
+async fn branding_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match branding::load(&state.db).await {
+        Ok(branding) => Json(branding).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+async fn signing_key_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match &state.signer {
+        Some(signer) => Json(SigningKeyResponse {
+            algorithm: "ed25519",
+            key_id: signer.key_id().to_string(),
+            public_key: signer.public_key_base64(),
+        })
+        .into_response(),
+        None => (StatusCode::NOT_FOUND, "Payload signing is not enabled").into_response(),
+    }
+}
+
+/// Number of most recent puzzles listed in each feed.
+const FEED_LIMIT: i64 = 50;
+
+async fn feed_handler(State(state): State<AppState>) -> Response {
+    puzzle_feed(&state, None).await
+}
+
+/// `/feed/<variant>.xml`, listing only puzzles that use that variant.
+async fn variant_feed_handler(State(state): State<AppState>, Path(file): Path<String>) -> Response {
+    let variant = file
+        .strip_suffix(".xml")
+        .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_lowercase() || c == '_'));
+    match variant {
+        Some(variant) => puzzle_feed(&state, Some(variant)).await,
+        None => (StatusCode::NOT_FOUND, "Feed not found").into_response(),
+    }
+}
+
+async fn puzzle_feed(state: &AppState, variant: Option<&str>) -> Response {
+    let today = Utc::now().date_naive().to_string();
+    let rows = match store::feed_puzzles(&state.db, &today, variant, FEED_LIMIT).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let items: Vec<feed::FeedItem> = rows
+        .into_iter()
+        .map(|row| feed::FeedItem {
+            date_utc: row.date_utc,
+            title: row.title,
+            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
+                .unwrap_or_default(),
+            published_at_utc: row.published_at_utc,
+        })
+        .collect();
+
+    let site_name = branding::load_or_default(&state.db).await.site_name;
+    let (title, description) = match variant {
+        Some(variant) => (
+            format!("{site_name}: {} puzzles", variant.replace('_', " ")),
+            format!("Daily {site_name} puzzles with {variant} constraints"),
+        ),
+        None => (
+            site_name.clone(),
+            "Daily variant sudoku puzzles".to_string(),
+        ),
+    };
+    let base_url = state.config.public_url.as_deref().unwrap_or_default();
+    let body = feed::render_rss(&site_name, &title, &description, base_url, &items);
+    ([(CONTENT_TYPE, "application/rss+xml; charset=utf-8")], body).into_response()
+}
+
+fn parse_solution_from_json(value: &serde_json::Value) -> Result<Vec<u8>, String> {
+    let sol = value
+        .get("solution")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| "solution missing".to_string())?;
+    if sol.len() != NN {
+        return Err("solution must have 81 digits".to_string());
+    }
+    let mut out = Vec::with_capacity(NN);
+    for item in sol {
+        let n = item
+            .as_u64()
+            .ok_or_else(|| "solution digits must be numbers".to_string())?;
+        if n < 1 || n > 9 {
             return Err("solution digits must be 1-9".to_string());
         }
-        out.push(n as u8);
+        out.push(n as u8);
+    }
+    Ok(out)
+}
+
+/// The date a check or candidates request is for: today unless the player
+/// names an earlier day.
+fn requested_date(today: NaiveDate, date_utc: Option<&str>) -> Result<String, Response> {
+    match date_utc {
+        None => Ok(today.to_string()),
+        Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            // Future puzzles stay unverifiable so they can't be brute-forced early.
+            Ok(date) if date <= today => Ok(date.to_string()),
+            Ok(_) => Err((StatusCode::NOT_FOUND, "Puzzle not published").into_response()),
+            Err(_) => Err((StatusCode::BAD_REQUEST, "date_utc must be YYYY-MM-DD").into_response()),
+        },
+    }
+}
+
+/// The puzzle_json of a random puzzle that hasn't expired yet.
+async fn random_puzzle_json(state: &AppState, random_id: &str) -> Result<String, Response> {
+    let since = retention::random_puzzle_cutoff(state.config.random_puzzle_ttl_hours);
+    let row = store::get_random_puzzle(&state.db, random_id, since.as_deref().unwrap_or("")).await;
+    match row {
+        Ok(Some(puzzle_json)) => Ok(puzzle_json),
+        Ok(None) => {
+            Err((StatusCode::NOT_FOUND, "Random puzzle not found or expired").into_response())
+        }
+        Err(e) => {
+            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response())
+        }
+    }
+}
+
+/// Pencil marks for a partly filled grid of a published or random puzzle,
+/// so the client can fill them in without a solver of its own. Givens
+/// replace whatever the grid has in their cells.
+async fn candidates_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AsOfQuery>,
+    Json(req): Json<CheckRequest>,
+) -> Response {
+    let cells = match grid_codec::decode(&req.grid, grid_codec::CLASSIC_SIZE) {
+        Ok(cells) => cells,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    let puzzle_json = if let Some(random_id) = &req.random_id {
+        match random_puzzle_json(&state, random_id).await {
+            Ok(puzzle_json) => puzzle_json,
+            Err(response) => return response,
+        }
+    } else {
+        let today = match effective_today(&state, &headers, query.as_of.as_deref()) {
+            Ok(today) => today,
+            Err(response) => return response,
+        };
+        let date_utc = match requested_date(today, req.date_utc.as_deref()) {
+            Ok(date_utc) => date_utc,
+            Err(response) => return response,
+        };
+        match store::get_public_puzzle(&state.db, &date_utc, true, None).await {
+            Ok(Some(row)) => row.puzzle_json,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+            Err(e) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
+                    .into_response();
+            }
+        }
+    };
+    let parsed = parse_puzzle_json(&puzzle_json).and_then(|parsed| {
+        let givens = grid_codec::decode(&parsed.puzzle, grid_codec::CLASSIC_SIZE)?;
+        Ok((parsed, givens))
+    });
+    let (parsed, givens) = match parsed {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid puzzle data").into_response();
+        }
+    };
+
+    let cells: Vec<Option<u8>> = givens
+        .iter()
+        .zip(&cells)
+        .map(|(given, cell)| given.or(*cell))
+        .collect();
+    Json(difficulty::candidates(
+        &cells,
+        &parsed.constraints,
+        parsed.regions.as_deref(),
+    ))
+    .into_response()
+}
+
+/// Checks a player's grid against a dated or random puzzle, counting the
+/// check and, once, the solve against the player's session.
+async fn check_puzzle_handler(
+    State(state): State<AppState>,
+    peer: ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<AsOfQuery>,
+    Json(req): Json<CheckRequest>,
+) -> impl IntoResponse {
+    let cells = match grid_codec::decode(&req.grid, grid_codec::CLASSIC_SIZE) {
+        Ok(cells) => cells,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    if let Some(random_id) = &req.random_id {
+        return check_random_puzzle(&state, &headers, &peer, random_id, &cells).await;
+    }
+
+    let today = match effective_today(&state, &headers, query.as_of.as_deref()) {
+        Ok(today) => today,
+        Err(response) => return response,
+    };
+    // Previews with `as_of` leave stats and events alone.
+    let simulated = query.as_of.is_some();
+    let date_utc = match requested_date(today, req.date_utc.as_deref()) {
+        Ok(date_utc) => date_utc,
+        Err(response) => return response,
+    };
+
+    let row = match store::get_public_puzzle(&state.db, &date_utc, true, None).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
+                .into_response();
+        }
+    };
+
+    let parsed = match parse_puzzle_json(&row.puzzle_json) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Invalid puzzle data",
+            )
+                .into_response();
+        }
+    };
+
+    let (session, is_new) = match charge_check(&state, &headers, &peer, row.difficulty) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    if !simulated {
+        let _ = store::bump_stat(&state.db, &date_utc, store::Stat::Check).await;
+        if let Some(events) = &state.events {
+            events.record("check", &date_utc);
+        }
+    }
+
+    let status = match parsed.solution {
+        Some(solution) => check_grid_against_solution(&cells, &solution),
+        // The solver only knows the standard boxes, so jigsaw puzzles can't
+        // be solved here.
+        None if !parsed.machine_checkable || parsed.regions.is_some() => "unavailable",
+        None => {
+            // Imported puzzles may come without a solution; solve them once
+            // and keep the result in puzzle_json for later checks.
+            let result = tokio::task::spawn_blocking(move || {
+                solve_puzzle(&parsed.puzzle, &parsed.constraints)
+            })
+            .await;
+            match result {
+                Ok(Ok(solution)) => {
+                    store_solved_solution(&state.db, &date_utc, &row.puzzle_json, &solution).await;
+                    check_grid_against_solution(&cells, &solution)
+                }
+                Ok(Err(err)) => {
+                    tracing::warn!("cannot solve {date_utc} for checking: {err}");
+                    "unavailable"
+                }
+                Err(err) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Check task failed: {err}"),
+                    )
+                        .into_response();
+                }
+            }
+        }
+    };
+
+    if !simulated {
+        state.activity.publish("check", &date_utc, Some(status));
+    }
+    if status == "complete" && !simulated {
+        match store::record_solve(&state.db, &date_utc, &session).await {
+            Ok(true) => {
+                if let Some(events) = &state.events {
+                    events.record("solve", &date_utc);
+                }
+                state.activity.publish("solve", &date_utc, None);
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("cannot record solve of {date_utc}: {e}"),
+        }
+    }
+    let response = Json(CheckResponse {
+        status: status.to_string(),
+    })
+    .into_response();
+    // The session also keys solve records, so it is set even without a
+    // check limiter.
+    with_session_cookie(response, is_new, &session)
+}
+
+/// Checks a grid of a random puzzle against its stored solution. Random
+/// puzzles have no date, so nothing is counted in stats or solve records,
+/// but the check limiter still applies.
+async fn check_random_puzzle(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: &ConnectInfo<SocketAddr>,
+    random_id: &str,
+    cells: &[Option<u8>],
+) -> Response {
+    let puzzle_json = match random_puzzle_json(state, random_id).await {
+        Ok(puzzle_json) => puzzle_json,
+        Err(response) => return response,
+    };
+    let parsed = match parse_puzzle_json(&puzzle_json) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid puzzle data").into_response();
+        }
+    };
+
+    // The rating stored at generation picks the limiter tier.
+    let difficulty = serde_json::from_str::<serde_json::Value>(&puzzle_json)
+        .ok()
+        .and_then(|value| value.get("difficulty")?.as_i64());
+
+    let (session, is_new) = match charge_check(state, headers, peer, difficulty) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let status = match &parsed.solution {
+        Some(solution) => check_grid_against_solution(cells, solution),
+        None => "unavailable",
+    };
+    let response = Json(CheckResponse {
+        status: status.to_string(),
+    })
+    .into_response();
+    with_session_cookie(response, is_new, &session)
+}
+
+/// The caller's player session, minting one when it sent no cookie, and
+/// whether it is new. The check is charged to the check limiter first;
+/// `Err` is its `429`.
+fn charge_check(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: &ConnectInfo<SocketAddr>,
+    difficulty: Option<i64>,
+) -> Result<(String, bool), Response> {
+    let existing = check_limit::session_cookie(headers);
+    let is_new = existing.is_none();
+    let session = existing.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    if let Some(limiter) = &state.check_limiter {
+        let client =
+            rate_limit::client_address(headers, Some(peer), state.config.rate_limit_trust_proxy);
+        limiter
+            .try_check(&session, client, difficulty)
+            .map_err(IntoResponse::into_response)?;
+    }
+    Ok((session, is_new))
+}
+
+/// Sets the player session cookie on a check response for a new session.
+fn with_session_cookie(mut response: Response, is_new: bool, session: &str) -> Response {
+    if is_new {
+        let cookie = format!(
+            "{}={session}; Path=/; HttpOnly; SameSite=Lax; Max-Age=31536000",
+            check_limit::SESSION_COOKIE
+        );
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().insert(SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+#[derive(Serialize)]
+struct DeleteMyDataResponse {
+    solve_records: u64,
+}
+
+/// Deletes what the server keeps about the caller's player session, found
+/// by its cookie, and expires the cookie. Progress saved in the browser
+/// stays with the browser.
+async fn delete_my_data_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(session) = check_limit::session_cookie(&headers) else {
+        return Json(DeleteMyDataResponse { solve_records: 0 }).into_response();
+    };
+    let solve_records = match store::delete_session_data(&state.db, &session).await {
+        Ok(deleted) => deleted,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
+        }
+    };
+    if let Some(limiter) = &state.check_limiter {
+        limiter.forget(&session);
+    }
+
+    let cookie = format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        check_limit::SESSION_COOKIE
+    );
+    (
+        [(SET_COOKIE, cookie)],
+        Json(DeleteMyDataResponse { solve_records }),
+    )
+        .into_response()
+}
+
+fn check_grid_against_solution(cells: &[Option<u8>], solution: &[u8]) -> &'static str {
+    let mut incomplete = false;
+    for (cell, expected) in cells.iter().zip(solution) {
+        match cell {
+            None => incomplete = true,
+            Some(digit) if digit != expected => return "incorrect",
+            Some(_) => {}
+        }
+    }
+    if incomplete { "partial" } else { "complete" }
+}
+
+/// Solves a puzzle stored without a solution. The givens must have a unique
+/// solution, otherwise there is nothing to compare a grid against.
+fn solve_puzzle(puzzle: &str, constraints: &[serde_json::Value]) -> Result<[u8; NN], String> {
+    let specs = constraints_from_json(constraints)?;
+    let mut rng = SimpleRng::new();
+    if !has_unique_solution_with_specs(puzzle, &specs, None, &mut rng) {
+        return Err("puzzle does not have a unique solution".to_string());
+    }
+    generate_full_solution_with(rng, |eng| {
+        apply_variant_specs(eng, &specs);
+        let _ = eng.load_givens(puzzle);
+    })
+}
+
+/// Writes a solved grid into the stored puzzle_json, unless the puzzle was
+/// changed in the meantime.
+async fn store_solved_solution(
+    db: &SqlitePool,
+    date_utc: &str,
+    puzzle_json: &str,
+    solution: &[u8; NN],
+) {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(puzzle_json) else {
+        return;
+    };
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    fields.insert("solution".to_string(), serde_json::json!(solution.to_vec()));
+    let solved_json = value.to_string();
+
+    if let Err(e) = store::replace_puzzle_json(db, date_utc, &solved_json, puzzle_json).await {
+        tracing::warn!("failed to store solution for {date_utc}: {e}");
+    }
+}
+
+/// Thumbnails are rendered at this width for archive listings.
+const THUMBNAIL_WIDTH: u32 = 200;
+
+/// Serves a small PNG of a published or archived puzzle, rendering it on the
+/// first request and storing it until the puzzle's SVG changes.
+async fn thumbnail_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let today = Utc::now().date_naive().to_string();
+    let row = match store::get_public_thumbnail(&state.db, &date_utc, &today).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let png = match row.thumbnail_png {
+        Some(png) => png,
+        None => {
+            let svg = match ensure_svg(&state.db, &date_utc, row.svg, &row.puzzle_json).await {
+                Ok(svg) => svg,
+                Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+            };
+            let png =
+                tokio::task::spawn_blocking(move || social::render_png(&svg, THUMBNAIL_WIDTH))
+                    .await;
+            let png = match png {
+                Ok(Ok(png)) => png,
+                Ok(Err(err)) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to render thumbnail: {err:#}"),
+                    )
+                        .into_response();
+                }
+                Err(err) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Render task failed: {err}"),
+                    )
+                        .into_response();
+                }
+            };
+            if let Err(e) = store::set_thumbnail(&state.db, &date_utc, &png).await {
+                tracing::warn!("failed to store thumbnail for {date_utc}: {e}");
+            }
+            png
+        }
+    };
+
+    (
+        [
+            (CONTENT_TYPE, "image/png"),
+            (CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        png,
+    )
+        .into_response()
+}
+
+/// A printable PDF of a published or archived puzzle up to today, with an
+/// enlarged grid that has a tick box per candidate in every empty cell.
+async fn worksheet_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> Response {
+    let today = Utc::now().date_naive().to_string();
+    let row = match store::get_public_puzzle(&state.db, &date_utc, true, Some(&today)).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let parsed = match parse_puzzle_json(&row.puzzle_json) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid puzzle data").into_response();
+        }
+    };
+    let svg = match ensure_svg(&state.db, &date_utc, row.svg, &row.puzzle_json).await {
+        Ok(svg) => svg,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    };
+
+    let filename = format!("makudoku-{date_utc}.pdf");
+    let branding = branding::load_or_default(&state.db).await;
+    let pdf = tokio::task::spawn_blocking(move || {
+        worksheet::render_pdf(&worksheet::Worksheet {
+            site_name: &branding.site_name,
+            title: row.title.as_deref(),
+            date_utc: &date_utc,
+            svg: &svg,
+            givens: &parsed.puzzle,
+            regions: parsed.regions.as_deref(),
+            rules: &parsed.custom_rules,
+        })
+    })
+    .await;
+    let pdf = match pdf {
+        Ok(Ok(pdf)) => pdf,
+        Ok(Err(err)) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to render worksheet: {err:#}"),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Render task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    (
+        [
+            (CONTENT_TYPE, "application/pdf".to_string()),
+            (CACHE_CONTROL, "public, max-age=86400".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("inline; filename=\"{filename}\""),
+            ),
+        ],
+        pdf,
+    )
+        .into_response()
+}
+
+async fn track_event_handler(
+    State(state): State<AppState>,
+    Json(req): Json<TrackRequest>,
+) -> impl IntoResponse {
+    let today = Utc::now().date_naive().to_string();
+    let event = req.event.as_str();
+
+    let result = match event {
+        "view" => store::bump_stat(&state.db, &today, store::Stat::View).await,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "event must be one of: view",
+            )
+                .into_response();
+        }
+    };
+
+    if let Err(e) = result {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response();
+    }
+    if let Some(events) = &state.events {
+        events.record("view", &today);
+    }
+    state.activity.publish("view", &today, None);
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn admin_stats_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let row = match store::get_stats(&state.db, &date_utc).await {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return Json(StatsResponse {
+                date_utc,
+                views: 0,
+                checks: 0,
+                solves: 0,
+            })
+            .into_response();
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    Json(StatsResponse {
+        date_utc: row.date_utc,
+        views: row.views,
+        checks: row.checks,
+        solves: row.solves,
+    })
+    .into_response()
+}
+
+/// Stats of a public puzzle and, when one is set, how far the community is
+/// toward its goal.
+async fn public_stats_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let today = Utc::now().date_naive().to_string();
+    let row = match store::public_stats(&state.db, &date_utc, &today).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let views = row.views.unwrap_or(0);
+    let checks = row.checks.unwrap_or(0);
+    let solves = row.solves.unwrap_or(0);
+    let goal = row
+        .goal_metric
+        .zip(row.goal_target)
+        .map(|(metric, target)| {
+            let current = match metric.as_str() {
+                "views" => views,
+                "checks" => checks,
+                _ => solves,
+            };
+            GoalProgress {
+                metric,
+                target,
+                label: row.goal_label,
+                current,
+                progress: (current as f64 / target as f64).min(1.0),
+                reached: current >= target,
+            }
+        });
+
+    Json(PublicStatsResponse {
+        date_utc: row.date_utc,
+        views,
+        checks,
+        solves,
+        goal,
+    })
+    .into_response()
+}
+
+/// Views, checks and solves of a puzzle per UTC hour, for seeing when
+/// players turn up. Hours without activity are left out.
+async fn admin_stats_timeline_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let rows = match store::hourly_stats(&state.db, &date_utc).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    Json(StatsTimelineResponse {
+        date_utc,
+        hours: rows
+            .into_iter()
+            .map(|row| StatsHour {
+                hour_utc: row.hour_utc,
+                views: row.views,
+                checks: row.checks,
+                solves: row.solves,
+            })
+            .collect(),
+    })
+    .into_response()
+}
+
+const MAX_STATS_BATCH_DATES: usize = 366;
+
+fn month_dates(month: &str) -> Result<Vec<String>, String> {
+    let first = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d")
+        .map_err(|_| "month must be YYYY-MM".to_string())?;
+    Ok(first
+        .iter_days()
+        .take_while(|d| d.month() == first.month())
+        .map(|d| d.to_string())
+        .collect())
+}
+
+async fn admin_stats_batch_handler(
+    State(state): State<AppState>,
+    Json(req): Json<StatsBatchRequest>,
+) -> impl IntoResponse {
+    let dates = match (req.dates, req.month) {
+        (Some(dates), None) => dates,
+        (None, Some(month)) => match month_dates(&month) {
+            Ok(dates) => dates,
+            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+        },
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "provide exactly one of: dates, month",
+            )
+                .into_response();
+        }
+    };
+    if dates.len() > MAX_STATS_BATCH_DATES {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_STATS_BATCH_DATES} dates per request"),
+        )
+            .into_response();
+    }
+
+    let dates_json = match serde_json::to_string(&dates) {
+        Ok(v) => v,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to encode dates: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let rows = match store::stats_for_dates(&state.db, &dates_json).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let mut by_date: HashMap<String, (i64, i64, i64)> = rows
+        .into_iter()
+        .map(|row| (row.date_utc, (row.views, row.checks, row.solves)))
+        .collect();
+
+    let out: Vec<StatsResponse> = dates
+        .into_iter()
+        .map(|date_utc| {
+            let (views, checks, solves) = by_date.remove(&date_utc).unwrap_or_default();
+            StatsResponse {
+                date_utc,
+                views,
+                checks,
+                solves,
+            }
+        })
+        .collect();
+
+    Json(out).into_response()
+}
+
+fn now_utc_string() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn dedupe_variants(input: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for item in input {
+        if seen.insert(item.clone()) {
+            out.push(item);
+        }
+    }
+    out
+}
+
+fn parse_puzzle_json(puzzle_json: &str) -> Result<ParsedPuzzleJson, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(puzzle_json).map_err(|e| format!("invalid JSON: {e}"))?;
+    let puzzle = value
+        .get("puzzle")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "missing puzzle string".to_string())?
+        .to_string();
+
+    let constraints = value
+        .get("constraints")
+        .and_then(|v| v.as_array())
+        .map(|v| v.to_vec())
+        .unwrap_or_default();
+
+    let solution = parse_solution_from_json(&value).ok();
+
+    let engine_version = value
+        .get("engine_version")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string());
+
+    let custom_rules = parse_custom_rules(&value)?;
+    let regions = value.get("regions").map(parse_regions).transpose()?;
+    let machine_checkable = value
+        .get("machine_checkable")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(custom_rules.is_empty());
+
+    Ok(ParsedPuzzleJson {
+        puzzle,
+        constraints,
+        solution,
+        engine_version,
+        custom_rules,
+        regions,
+        machine_checkable,
+    })
+}
+
+/// Jigsaw layouts: nine regions of nine cells that together cover the grid
+/// exactly once.
+fn parse_regions(value: &serde_json::Value) -> Result<Vec<Vec<(usize, usize)>>, String> {
+    let arr = value
+        .as_array()
+        .ok_or_else(|| "regions must be an array of cell lists".to_string())?;
+    if arr.len() != 9 {
+        return Err("regions must have exactly 9 regions".to_string());
+    }
+    let mut seen = HashSet::new();
+    let mut out = Vec::with_capacity(9);
+    for region in arr {
+        let cells = parse_path(region)?;
+        if cells.len() != 9 {
+            return Err("each region must have exactly 9 cells".to_string());
+        }
+        for &(r, c) in &cells {
+            if r > 8 || c > 8 {
+                return Err("region cells must be within the grid".to_string());
+            }
+            if !seen.insert((r, c)) {
+                return Err(format!("cell [{r}, {c}] is in more than one region"));
+            }
+        }
+        out.push(cells);
+    }
+    Ok(out)
+}
+
+const MAX_CUSTOM_RULES: usize = 8;
+const MAX_CUSTOM_RULE_LEN: usize = 280;
+
+/// `custom_rules` are free-text rules shown alongside the generated rules
+/// text. The engine knows nothing about them.
+fn parse_custom_rules(value: &serde_json::Value) -> Result<Vec<String>, String> {
+    let Some(rules) = value.get("custom_rules") else {
+        return Ok(Vec::new());
+    };
+    let rules = rules
+        .as_array()
+        .ok_or_else(|| "custom_rules must be an array of strings".to_string())?;
+    if rules.len() > MAX_CUSTOM_RULES {
+        return Err(format!(
+            "custom_rules can have at most {MAX_CUSTOM_RULES} rules"
+        ));
+    }
+    let mut out = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let text = rule
+            .as_str()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && v.chars().count() <= MAX_CUSTOM_RULE_LEN)
+            .ok_or_else(|| {
+                format!(
+                    "custom_rules must be non-empty strings of at most {MAX_CUSTOM_RULE_LEN} characters"
+                )
+            })?;
+        out.push(text.to_string());
+    }
+    Ok(out)
+}
+
+fn variants_from_constraints(constraints: &[serde_json::Value]) -> Result<Vec<String>, String> {
+    let mut out = Vec::new();
+    for item in constraints {
+        let kind = item
+            .get("type")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "constraint missing type".to_string())?;
+        out.push(kind.to_string());
+    }
+    Ok(dedupe_variants(out))
+}
+
+fn parse_cell(value: &serde_json::Value) -> Result<(usize, usize), String> {
+    let arr = value
+        .as_array()
+        .ok_or_else(|| "cell must be a [row, col] array".to_string())?;
+    if arr.len() != 2 {
+        return Err("cell must have two elements".to_string());
+    }
+    let r = arr[0]
+        .as_u64()
+        .ok_or_else(|| "row must be an integer".to_string())? as usize;
+    let c = arr[1]
+        .as_u64()
+        .ok_or_else(|| "col must be an integer".to_string())? as usize;
+    Ok((r, c))
+}
+
+fn parse_path(value: &serde_json::Value) -> Result<Vec<(usize, usize)>, String> {
+    let arr = value
+        .as_array()
+        .ok_or_else(|| "path must be an array of cells".to_string())?;
+    let mut out = Vec::with_capacity(arr.len());
+    for cell in arr {
+        out.push(parse_cell(cell)?);
+    }
+    if out.is_empty() {
+        return Err("path must have at least one cell".to_string());
+    }
+    Ok(out)
+}
+
+/// Lines are drawn cell to cell, so each step must move to a neighbouring
+/// cell (diagonals included) and no cell may repeat.
+fn validate_line_path(kind: &str, path: &[(usize, usize)]) -> Result<(), String> {
+    if path.len() < 2 {
+        return Err(format!("{kind} lines need at least 2 cells"));
+    }
+    let unique: HashSet<(usize, usize)> = path.iter().copied().collect();
+    if unique.len() != path.len() {
+        return Err(format!("{kind} cells must not repeat"));
+    }
+    let connected = path
+        .windows(2)
+        .all(|pair| pair[0].0.abs_diff(pair[1].0) <= 1 && pair[0].1.abs_diff(pair[1].1) <= 1);
+    if !connected {
+        return Err(format!("{kind} cells must be adjacent along the line"));
+    }
+    Ok(())
+}
+
+/// Branches of a branched thermo, each a path leading away from the shared
+/// bulb (the bulb itself is not repeated in the branches).
+fn parse_thermo_branches(
+    value: &serde_json::Value,
+    bulb: (usize, usize),
+) -> Result<Vec<Vec<(usize, usize)>>, String> {
+    let arr = value
+        .as_array()
+        .ok_or_else(|| "branches must be an array of paths".to_string())?;
+    if arr.is_empty() {
+        return Err("thermo must have at least one branch".to_string());
+    }
+    let mut out = Vec::with_capacity(arr.len());
+    for branch in arr {
+        let path = parse_path(branch)?;
+        if path.contains(&bulb) {
+            return Err("thermo branches must not include the bulb".to_string());
+        }
+        out.push(path);
+    }
+    Ok(out)
+}
+
+const MAX_CONSTRAINT_LABEL_LEN: usize = 64;
+
+/// `label` and `group` are free text carried along for setters and the rules
+/// text; they are only checked for shape here.
+fn validate_constraint_annotations(item: &serde_json::Value) -> Result<(), String> {
+    for field in ["label", "group"] {
+        match item.get(field) {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::String(text))
+                if text.chars().count() <= MAX_CONSTRAINT_LABEL_LEN => {}
+            Some(_) => {
+                return Err(format!(
+                    "constraint {field} must be a string of at most {MAX_CONSTRAINT_LABEL_LEN} characters"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn constraint_labels(constraints: &[serde_json::Value]) -> Vec<ConstraintLabel> {
+    let text = |item: &serde_json::Value, field: &str| {
+        item.get(field)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    constraints
+        .iter()
+        .filter_map(|item| {
+            let label = text(item, "label");
+            let group = text(item, "group");
+            if label.is_none() && group.is_none() {
+                return None;
+            }
+            Some(ConstraintLabel {
+                kind: text(item, "type").unwrap_or_default(),
+                label,
+                group,
+            })
+        })
+        .collect()
+}
+
+fn stored_constraint_labels(puzzle_json: &str) -> Vec<ConstraintLabel> {
+    parse_puzzle_json(puzzle_json)
+        .map(|parsed| constraint_labels(&parsed.constraints))
+        .unwrap_or_default()
+}
+
+/// A pill holds a one- or two-digit sum read in cell order, so its cells must
+/// be orthogonally adjacent and kept off the arrow's shaft.
+fn validate_arrow_pill(pill: &[(usize, usize)], path: &[(usize, usize)]) -> Result<(), String> {
+    if pill.len() > 2 {
+        return Err("arrow pill must have one or two cells".to_string());
+    }
+    let adjacent = match pill {
+        [(r1, c1), (r2, c2)] => r1.abs_diff(*r2) + c1.abs_diff(*c2) == 1,
+        _ => true,
+    };
+    if !adjacent {
+        return Err("arrow pill cells must be orthogonally adjacent".to_string());
+    }
+    if pill.iter().any(|cell| path.contains(cell)) {
+        return Err("arrow path must not include pill cells".to_string());
+    }
+    Ok(())
+}
+
+/// Cage cells must be distinct and orthogonally connected so the outline can
+/// be traced around any shape, not just rectangles.
+fn validate_cage_cells(cells: &[(usize, usize)]) -> Result<(), String> {
+    let set: HashSet<(usize, usize)> = cells.iter().copied().collect();
+    if set.len() != cells.len() {
+        return Err("killer cells must not repeat".to_string());
+    }
+
+    let mut seen = HashSet::from([cells[0]]);
+    let mut stack = vec![cells[0]];
+    while let Some((r, c)) = stack.pop() {
+        let neighbours = [
+            (r.wrapping_sub(1), c),
+            (r + 1, c),
+            (r, c.wrapping_sub(1)),
+            (r, c + 1),
+        ];
+        for next in neighbours {
+            if set.contains(&next) && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    if seen.len() != set.len() {
+        return Err("killer cells must be orthogonally connected".to_string());
+    }
+    Ok(())
+}
+
+/// Accepts `#rgb`/`#rrggbb` hex colors or plain color names, which are safe
+/// to place in an SVG attribute.
+fn parse_svg_color(value: &serde_json::Value) -> Result<String, String> {
+    let color = value
+        .as_str()
+        .ok_or_else(|| "color must be a string".to_string())?;
+    let valid = match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => {
+            !color.is_empty() && color.len() <= 32 && color.chars().all(|c| c.is_ascii_alphabetic())
+        }
+    };
+    if !valid {
+        return Err(format!("invalid color: {color}"));
+    }
+    Ok(color.to_string())
+}
+
+fn parse_cage_style(item: &serde_json::Value) -> Result<CageStyle, String> {
+    let mut style = CageStyle::default();
+    if let Some(show_sum) = item.get("show_sum") {
+        style.show_sum = show_sum
+            .as_bool()
+            .ok_or_else(|| "show_sum must be a boolean".to_string())?;
+    }
+    let Some(value) = item.get("style") else {
+        return Ok(style);
+    };
+    if let Some(dash) = value.get("dash") {
+        style.dash = match dash.as_str() {
+            Some("dashed") => CageDash::Dashed,
+            Some("dotted") => CageDash::Dotted,
+            Some("solid") => CageDash::Solid,
+            _ => return Err("style.dash must be one of: dashed, dotted, solid".to_string()),
+        };
+    }
+    if let Some(color) = value.get("color").filter(|v| !v.is_null()) {
+        style.color = Some(parse_svg_color(color)?);
+    }
+    Ok(style)
+}
+
+fn cage_dash_str(dash: CageDash) -> &'static str {
+    match dash {
+        CageDash::Dashed => "dashed",
+        CageDash::Dotted => "dotted",
+        CageDash::Solid => "solid",
+    }
+}
+
+fn diagonal_step(direction: DiagonalDirection) -> (isize, isize) {
+    match direction {
+        DiagonalDirection::DownRight => (1, 1),
+        DiagonalDirection::DownLeft => (1, -1),
+        DiagonalDirection::UpRight => (-1, 1),
+        DiagonalDirection::UpLeft => (-1, -1),
+    }
+}
+
+/// Number of cells on a little killer diagonal. The clue is drawn just
+/// outside the grid, so the cell before `start` must be off the grid.
+fn little_killer_length(
+    start: (usize, usize),
+    direction: DiagonalDirection,
+) -> Result<usize, String> {
+    let (dr, dc) = diagonal_step(direction);
+    let on_grid = |r: isize, c: isize| (0..9).contains(&r) && (0..9).contains(&c);
+    let (mut r, mut c) = (start.0 as isize, start.1 as isize);
+    if on_grid(r - dr, c - dc) {
+        return Err("little_killer cell must be on the edge the clue points in from".to_string());
+    }
+    let mut len = 0;
+    while on_grid(r, c) {
+        len += 1;
+        r += dr;
+        c += dc;
+    }
+    Ok(len)
+}
+
+/// The four 3x3 windows of a windoku, each one cell in from the grid edge
+/// and separated by a single row and column.
+fn windoku_regions() -> Vec<Vec<(usize, usize)>> {
+    let mut regions = Vec::with_capacity(4);
+    for top in [1, 5] {
+        for left in [1, 5] {
+            let cells = (top..top + 3)
+                .flat_map(|r| (left..left + 3).map(move |c| (r, c)))
+                .collect();
+            regions.push(cells);
+        }
+    }
+    regions
+}
+
+/// Digits of a quadruple circle: 1 to 4 of them, each 1-9. A digit can occur
+/// at most twice, since the four cells cover two rows and two columns.
+fn parse_quadruple_digits(value: Option<&serde_json::Value>) -> Result<Vec<u8>, String> {
+    let arr = value
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| "quadruple digits must be an array".to_string())?;
+    if arr.is_empty() || arr.len() > 4 {
+        return Err("quadruple must list 1 to 4 digits".to_string());
+    }
+    let mut digits = Vec::with_capacity(arr.len());
+    for v in arr {
+        let digit = v
+            .as_u64()
+            .filter(|d| (1..=9).contains(d))
+            .ok_or_else(|| "quadruple digits must be 1-9".to_string())?;
+        digits.push(digit as u8);
+    }
+    if digits
+        .iter()
+        .any(|d| digits.iter().filter(|x| *x == d).count() > 2)
+    {
+        return Err("a quadruple digit can appear at most twice".to_string());
+    }
+    digits.sort_unstable();
+    Ok(digits)
+}
+
+fn parse_diagonal_direction(value: Option<&str>) -> Result<DiagonalDirection, String> {
+    match value {
+        Some("down_right") => Ok(DiagonalDirection::DownRight),
+        Some("down_left") => Ok(DiagonalDirection::DownLeft),
+        Some("up_right") => Ok(DiagonalDirection::UpRight),
+        Some("up_left") => Ok(DiagonalDirection::UpLeft),
+        _ => Err("direction must be down_right, down_left, up_right or up_left".to_string()),
+    }
+}
+
+fn diagonal_direction_str(direction: DiagonalDirection) -> &'static str {
+    match direction {
+        DiagonalDirection::DownRight => "down_right",
+        DiagonalDirection::DownLeft => "down_left",
+        DiagonalDirection::UpRight => "up_right",
+        DiagonalDirection::UpLeft => "up_left",
+    }
+}
+
+/// A sandwich clue sits outside exactly one row or column.
+fn parse_sandwich_line(item: &serde_json::Value) -> Result<SandwichLine, String> {
+    let index = |key: &str| {
+        item.get(key)
+            .map(|v| {
+                v.as_u64()
+                    .filter(|i| *i < 9)
+                    .map(|i| i as usize)
+                    .ok_or_else(|| format!("sandwich {key} must be 0-8"))
+            })
+            .transpose()
+    };
+    match (index("row")?, index("col")?) {
+        (Some(row), None) => Ok(SandwichLine::Row(row)),
+        (None, Some(col)) => Ok(SandwichLine::Col(col)),
+        _ => Err("sandwich needs exactly one of row or col".to_string()),
+    }
+}
+
+fn diagonal_str(which: Diagonal) -> &'static str {
+    match which {
+        Diagonal::Both => "both",
+        Diagonal::Main => "main",
+        Diagonal::Anti => "anti",
+    }
+}
+
+fn constraints_from_json(
+    constraints: &[serde_json::Value],
+) -> Result<Vec<VariantSpec>, String> {
+    let mut out = Vec::new();
+    for item in constraints {
+        let kind = item
+            .get("type")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "constraint missing type".to_string())?;
+        validate_constraint_annotations(item)?;
+        match kind {
+            "kropki_white" => {
+                let a = parse_cell(
+                    item.get("a")
+                        .ok_or_else(|| "kropki_white missing a".to_string())?,
+                )?;
+                let b = parse_cell(
+                    item.get("b")
+                        .ok_or_else(|| "kropki_white missing b".to_string())?,
+                )?;
+                out.push(VariantSpec::KropkiWhite(a, b));
+            }
+            "kropki_black" => {
+                let a = parse_cell(
+                    item.get("a")
+                        .ok_or_else(|| "kropki_black missing a".to_string())?,
+                )?;
+                let b = parse_cell(
+                    item.get("b")
+                        .ok_or_else(|| "kropki_black missing b".to_string())?,
+                )?;
+                out.push(VariantSpec::KropkiBlack(a, b));
+            }
+            "thermo" => {
+                if let Some(branches) = item.get("branches") {
+                    let bulb = parse_cell(
+                        item.get("bulb")
+                            .ok_or_else(|| "thermo with branches missing bulb".to_string())?,
+                    )?;
+                    let branches = parse_thermo_branches(branches, bulb)?;
+                    out.push(VariantSpec::BranchedThermo { bulb, branches });
+                } else {
+                    let path = parse_path(
+                        item.get("path")
+                            .ok_or_else(|| "thermo missing path".to_string())?,
+                    )?;
+                    out.push(VariantSpec::Thermo(path));
+                }
+            }
+            "arrow" => {
+                let path = parse_path(
+                    item.get("path")
+                        .ok_or_else(|| "arrow missing path".to_string())?,
+                )?;
+                match item.get("pill") {
+                    Some(pill) => {
+                        let pill = parse_path(pill)?;
+                        validate_arrow_pill(&pill, &path)?;
+                        if pill.len() == 1 {
+                            out.push(VariantSpec::Arrow([pill, path].concat()));
+                        } else {
+                            out.push(VariantSpec::PillArrow { pill, path });
+                        }
+                    }
+                    None => out.push(VariantSpec::Arrow(path)),
+                }
+            }
+            "renban" => {
+                let path = parse_path(
+                    item.get("path")
+                        .ok_or_else(|| "renban missing path".to_string())?,
+                )?;
+                validate_line_path("renban", &path)?;
+                if path.len() > 9 {
+                    return Err("renban lines can have at most 9 cells".to_string());
+                }
+                out.push(VariantSpec::Renban(path));
+            }
+            "whisper" => {
+                let path = parse_path(
+                    item.get("path")
+                        .ok_or_else(|| "whisper missing path".to_string())?,
+                )?;
+                validate_line_path("whisper", &path)?;
+                out.push(VariantSpec::Whisper(path));
+            }
+            "palindrome" => {
+                let path = parse_path(
+                    item.get("path")
+                        .ok_or_else(|| "palindrome missing path".to_string())?,
+                )?;
+                validate_line_path("palindrome", &path)?;
+                out.push(VariantSpec::Palindrome(path));
+            }
+            "between" => {
+                let path = parse_path(
+                    item.get("path")
+                        .ok_or_else(|| "between missing path".to_string())?,
+                )?;
+                validate_line_path("between", &path)?;
+                if path.len() < 3 {
+                    return Err("between lines need at least 3 cells".to_string());
+                }
+                out.push(VariantSpec::Between(path));
+            }
+            "killer" => {
+                let cells = parse_path(
+                    item.get("cells")
+                        .ok_or_else(|| "killer missing cells".to_string())?,
+                )?;
+                let sum = item
+                    .get("sum")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| "killer missing sum".to_string())?;
+                let no_repeats = item
+                    .get("no_repeats")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+                validate_cage_cells(&cells)?;
+                let style = parse_cage_style(item)?;
+                out.push(VariantSpec::Killer {
+                    cells,
+                    sum: sum as u8,
+                    no_repeats,
+                    style,
+                });
+            }
+            "color_region" => {
+                let cells = parse_path(
+                    item.get("cells")
+                        .ok_or_else(|| "color_region missing cells".to_string())?,
+                )?;
+                let unique: HashSet<(usize, usize)> = cells.iter().copied().collect();
+                if unique.len() != cells.len() {
+                    return Err("color_region cells must not repeat".to_string());
+                }
+                let color = parse_svg_color(
+                    item.get("color")
+                        .ok_or_else(|| "color_region missing color".to_string())?,
+                )?;
+                let distinct = item
+                    .get("distinct")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if distinct && cells.len() > 9 {
+                    return Err("a distinct color_region can have at most 9 cells".to_string());
+                }
+                out.push(VariantSpec::ColorRegion {
+                    cells,
+                    color,
+                    distinct,
+                });
+            }
+            "quadruple" => {
+                let cell = parse_cell(
+                    item.get("cell")
+                        .ok_or_else(|| "quadruple missing cell".to_string())?,
+                )?;
+                if cell.0 > 7 || cell.1 > 7 {
+                    return Err("quadruple cell must be the top left of a 2x2 block".to_string());
+                }
+                let digits = parse_quadruple_digits(item.get("digits"))?;
+                out.push(VariantSpec::Quadruple { cell, digits });
+            }
+            "little_killer" => {
+                let start = parse_cell(
+                    item.get("cell")
+                        .ok_or_else(|| "little_killer missing cell".to_string())?,
+                )?;
+                let direction =
+                    parse_diagonal_direction(item.get("direction").and_then(|v| v.as_str()))?;
+                let len = little_killer_length(start, direction)?;
+                let sum = item
+                    .get("sum")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| "little_killer missing sum".to_string())?;
+                if sum == 0 || sum > 9 * len as u64 {
+                    return Err(format!(
+                        "little_killer sum must be 1-{} for this diagonal",
+                        9 * len
+                    ));
+                }
+                out.push(VariantSpec::LittleKiller {
+                    start,
+                    direction,
+                    sum: sum as u16,
+                });
+            }
+            "sandwich" => {
+                let line = parse_sandwich_line(item)?;
+                let sum = item
+                    .get("sum")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| "sandwich missing sum".to_string())?;
+                // 2 through 8 is the widest possible sandwich.
+                if sum > 35 {
+                    return Err("sandwich sum must be 0-35".to_string());
+                }
+                let duplicate = out.iter().any(
+                    |spec| matches!(spec, VariantSpec::Sandwich { line: other, .. } if *other == line),
+                );
+                if duplicate {
+                    return Err("only one sandwich clue per row or column".to_string());
+                }
+                out.push(VariantSpec::Sandwich {
+                    line,
+                    sum: sum as u8,
+                });
+            }
+            "king" => out.push(VariantSpec::King),
+            "knight" => out.push(VariantSpec::Knight),
+            "queen" => out.push(VariantSpec::Queen),
+            "disjoint_groups" => out.push(VariantSpec::DisjointGroups),
+            "extra_region" => {
+                let cells = parse_path(
+                    item.get("cells")
+                        .ok_or_else(|| "extra_region missing cells".to_string())?,
+                )?;
+                let unique: HashSet<(usize, usize)> = cells.iter().copied().collect();
+                if cells.len() != 9 || unique.len() != 9 {
+                    return Err("extra_region must have 9 distinct cells".to_string());
+                }
+                out.push(VariantSpec::ExtraRegion(cells));
+            }
+            "windoku" => out.extend(windoku_regions().into_iter().map(VariantSpec::ExtraRegion)),
+            "diagonal" => {
+                let which = match item.get("which").and_then(|v| v.as_str()) {
+                    None | Some("both") => Diagonal::Both,
+                    Some("main") => Diagonal::Main,
+                    Some("anti") => Diagonal::Anti,
+                    Some(other) => {
+                        return Err(format!(
+                            "diagonal which must be both, main or anti, got {other}"
+                        ));
+                    }
+                };
+                out.push(VariantSpec::Diagonal(which));
+            }
+            "kropki_negative" => out.push(VariantSpec::KropkiNegative),
+            "xv_negative" => out.push(VariantSpec::XvNegative),
+            other => return Err(format!("unknown constraint type: {other}")),
+        }
+    }
+    Ok(out)
+}
+
+fn normalize_constraints_input(
+    input: serde_json::Value,
+) -> Result<Vec<serde_json::Value>, String> {
+    if let Some(arr) = input.as_array() {
+        return Ok(arr.to_vec());
+    }
+    if let Some(arr) = input.get("constraints").and_then(|v| v.as_array()) {
+        return Ok(arr.to_vec());
+    }
+    Err("constraints must be a JSON array".to_string())
+}
+
+fn apply_variant_specs(engine: &mut Engine, specs: &[VariantSpec]) {
+    for spec in specs {
+        match spec {
+            VariantSpec::KropkiWhite(a, b) => add_kropki_white(engine, *a, *b),
+            VariantSpec::KropkiBlack(a, b) => add_kropki_black(engine, *a, *b),
+            VariantSpec::Thermo(path) => add_thermo(engine, path),
+            VariantSpec::BranchedThermo { bulb, branches } => {
+                add_branched_thermo(engine, *bulb, branches)
+            }
+            VariantSpec::Arrow(path) => add_arrow(engine, path),
+            VariantSpec::PillArrow { pill, path } => add_pill_arrow(engine, pill, path),
+            VariantSpec::Renban(path) => add_renban(engine, path),
+            VariantSpec::Whisper(path) => add_whisper(engine, path),
+            VariantSpec::Palindrome(path) => add_palindrome(engine, path),
+            VariantSpec::Between(path) => add_between_line(engine, path),
+            VariantSpec::Killer {
+                cells,
+                sum,
+                no_repeats,
+                style,
+            } => add_killer_cage_styled(engine, cells, *sum, *no_repeats, style.clone()),
+            VariantSpec::LittleKiller {
+                start,
+                direction,
+                sum,
+            } => add_little_killer(engine, *start, *direction, *sum),
+            VariantSpec::ColorRegion {
+                cells,
+                color,
+                distinct,
+            } => add_color_region(engine, cells, color, *distinct),
+            VariantSpec::Quadruple { cell, digits } => add_quadruple(engine, *cell, digits),
+            VariantSpec::Sandwich { line, sum } => add_sandwich(engine, *line, *sum),
+            VariantSpec::King => add_king_constraints(engine),
+            VariantSpec::Knight => add_knight_constraints(engine),
+            VariantSpec::Queen => add_queen_constraints(engine),
+            VariantSpec::DisjointGroups => add_disjoint_groups(engine),
+            VariantSpec::ExtraRegion(cells) => add_extra_region(engine, cells),
+            VariantSpec::Diagonal(which) => add_diagonal_constraints(engine, *which),
+            VariantSpec::KropkiNegative => add_kropki_negative(engine, &kropki_pairs(specs)),
+            // No X/V marks exist yet, so every adjacent pair is unmarked.
+            VariantSpec::XvNegative => add_xv_negative(engine, &[]),
+        }
+    }
+}
+
+/// Cell pairs carrying a kropki dot, which the negative constraint exempts.
+fn kropki_pairs(specs: &[VariantSpec]) -> Vec<((usize, usize), (usize, usize))> {
+    specs
+        .iter()
+        .filter_map(|spec| match spec {
+            VariantSpec::KropkiWhite(a, b) | VariantSpec::KropkiBlack(a, b) => Some((*a, *b)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rows and columns, plus either the standard boxes or the given jigsaw
+/// regions.
+fn add_grid_constraints(engine: &mut Engine, regions: Option<&[Vec<(usize, usize)>]>) {
+    match regions {
+        Some(regions) => {
+            add_row_col_constraints(engine);
+            for region in regions {
+                add_region(engine, region);
+            }
+        }
+        None => add_all_sudoku_constraints(engine),
+    }
+}
+
+fn engine_constraints_from_specs(
+    specs: &[VariantSpec],
+    regions: Option<&[Vec<(usize, usize)>]>,
+) -> Vec<Constraint> {
+    let mut eng = Engine::new();
+    add_grid_constraints(&mut eng, regions);
+    apply_variant_specs(&mut eng, specs);
+    eng.constraints
+}
+
+fn variant_specs_to_json(specs: &[VariantSpec]) -> Vec<serde_json::Value> {
+    specs
+        .iter()
+        .map(|spec| match spec {
+            VariantSpec::KropkiWhite(a, b) => serde_json::json!({
+                "type": "kropki_white",
+                "a": [a.0, a.1],
+                "b": [b.0, b.1],
+            }),
+            VariantSpec::KropkiBlack(a, b) => serde_json::json!({
+                "type": "kropki_black",
+                "a": [a.0, a.1],
+                "b": [b.0, b.1],
+            }),
+            VariantSpec::Thermo(path) => serde_json::json!({
+                "type": "thermo",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::BranchedThermo { bulb, branches } => serde_json::json!({
+                "type": "thermo",
+                "bulb": [bulb.0, bulb.1],
+                "branches": branches
+                    .iter()
+                    .map(|path| {
+                        path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+            VariantSpec::Arrow(path) => serde_json::json!({
+                "type": "arrow",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::PillArrow { pill, path } => serde_json::json!({
+                "type": "arrow",
+                "pill": pill.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::Killer {
+                cells,
+                sum,
+                no_repeats,
+                style,
+            } => serde_json::json!({
+                "type": "killer",
+                "cells": cells.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+                "sum": sum,
+                "no_repeats": no_repeats,
+                "show_sum": style.show_sum,
+                "style": {
+                    "dash": cage_dash_str(style.dash),
+                    "color": style.color,
+                },
+            }),
+            VariantSpec::Renban(path) => serde_json::json!({
+                "type": "renban",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::Whisper(path) => serde_json::json!({
+                "type": "whisper",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::Palindrome(path) => serde_json::json!({
+                "type": "palindrome",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::Between(path) => serde_json::json!({
+                "type": "between",
+                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::ColorRegion {
+                cells,
+                color,
+                distinct,
+            } => serde_json::json!({
+                "type": "color_region",
+                "cells": cells.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+                "color": color,
+                "distinct": distinct,
+            }),
+            VariantSpec::Quadruple { cell, digits } => serde_json::json!({
+                "type": "quadruple",
+                "cell": [cell.0, cell.1],
+                "digits": digits,
+            }),
+            VariantSpec::LittleKiller {
+                start,
+                direction,
+                sum,
+            } => serde_json::json!({
+                "type": "little_killer",
+                "cell": [start.0, start.1],
+                "direction": diagonal_direction_str(*direction),
+                "sum": sum,
+            }),
+            VariantSpec::Sandwich { line, sum } => match line {
+                SandwichLine::Row(row) => {
+                    serde_json::json!({ "type": "sandwich", "row": row, "sum": sum })
+                }
+                SandwichLine::Col(col) => {
+                    serde_json::json!({ "type": "sandwich", "col": col, "sum": sum })
+                }
+            },
+            VariantSpec::King => serde_json::json!({ "type": "king" }),
+            VariantSpec::Knight => serde_json::json!({ "type": "knight" }),
+            VariantSpec::Queen => serde_json::json!({ "type": "queen" }),
+            VariantSpec::DisjointGroups => serde_json::json!({ "type": "disjoint_groups" }),
+            VariantSpec::ExtraRegion(cells) => serde_json::json!({
+                "type": "extra_region",
+                "cells": cells.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
+            }),
+            VariantSpec::Diagonal(which) => serde_json::json!({
+                "type": "diagonal",
+                "which": diagonal_str(*which),
+            }),
+            VariantSpec::KropkiNegative => serde_json::json!({ "type": "kropki_negative" }),
+            VariantSpec::XvNegative => serde_json::json!({ "type": "xv_negative" }),
+        })
+        .collect()
+}
+
+/// Generates a random puzzle for `date` (today by default) that fits the
+/// generation profile of its weekday, if one is set.
+async fn admin_generate_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminGenerateQuery>,
+) -> impl IntoResponse {
+    let date = match query.date.as_deref() {
+        Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return (StatusCode::BAD_REQUEST, "date must be YYYY-MM-DD").into_response(),
+        },
+        None => Utc::now().date_naive(),
+    };
+    let weekday = date.weekday();
+    let profile = match profiles::load(&state.db).await {
+        Ok(profiles) => profiles.for_weekday(weekday).cloned(),
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    };
+    let config = state.config.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let limits = GenerationLimits::server(&config);
+        // The rater only fails on puzzles it can't read, which count as
+        // hardest, as unsolved ones do.
+        let accept = |puzzle: &RandomPuzzle| {
+            profile
+                .as_ref()
+                .is_none_or(|p| p.accepts(puzzle.variants.len(), puzzle.difficulty.unwrap_or(10)))
+        };
+        random_options::generate_until(
+            &RandomOptions::default(),
+            &limits,
+            config.generate_rng_seeding,
+            accept,
+        )?
+        .ok_or_else(|| {
+            format!(
+                "no puzzle fit the {weekday} profile in {} attempts",
+                random_options::MAX_ATTEMPTS
+            )
+        })
+    })
+    .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Generator task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    let puzzle = match result {
+        Ok(puzzle) => puzzle,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to generate puzzle: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    Json(AdminGenerateResponse {
+        puzzle_json: puzzle.puzzle_json,
+        svg: puzzle.svg,
+        variants: puzzle.variants,
+        labels: Vec::new(),
+    })
+    .into_response()
+}
+
+async fn admin_get_generation_profiles_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match profiles::load(&state.db).await {
+        Ok(profiles) => Json(profiles).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+/// Replaces every weekday's profile; days left out have none.
+async fn admin_save_generation_profiles_handler(
+    State(state): State<AppState>,
+    Json(profiles): Json<profiles::WeekdayProfiles>,
+) -> impl IntoResponse {
+    if let Err(err) = profiles.validate() {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    match profiles::save(&state.db, &profiles).await {
+        Ok(()) => Json(profiles).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+/// Replaces the branding; fields left out go back to their defaults.
+async fn admin_save_branding_handler(
+    State(state): State<AppState>,
+    Json(branding): Json<branding::Branding>,
+) -> impl IntoResponse {
+    if let Err(err) = branding.validate() {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    match branding::save(&state.db, &branding).await {
+        Ok(()) => Json(branding).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+fn puzzle_vec_to_string(puzzle: &[Option<u8>]) -> String {
+    let mut s = String::with_capacity(NN);
+    for cell in puzzle.iter() {
+        match cell {
+            Some(d) => s.push((b'0' + *d) as char),
+            None => s.push('.'),
+        }
+    }
+    s
+}
+
+fn has_unique_solution_with_specs(
+    puzzle: &str,
+    specs: &[VariantSpec],
+    regions: Option<&[Vec<(usize, usize)>]>,
+    rng: &mut SimpleRng,
+) -> bool {
+    let mut eng = Engine::new();
+    add_grid_constraints(&mut eng, regions);
+    apply_variant_specs(&mut eng, specs);
+    if eng.load_givens(puzzle).is_err() {
+        return false;
+    }
+    eng.has_unique_solution_with_rng(rng)
+}
+
+/// Checks a stored puzzle against the linked engine: the givens must still
+/// have a unique solution and any stored solution must satisfy every
+/// constraint and agree with the givens.
+fn verify_puzzle_json(puzzle_json: &str) -> Result<(), String> {
+    let parsed = parse_puzzle_json(puzzle_json)?;
+    let specs = constraints_from_json(&parsed.constraints)?;
+    let mut rng = SimpleRng::new();
+
+    let regions = parsed.regions.as_deref();
+    if !has_unique_solution_with_specs(&parsed.puzzle, &specs, regions, &mut rng) {
+        return Err("puzzle does not have a unique solution".to_string());
+    }
+
+    if let Some(solution) = parsed.solution {
+        let givens_match = parsed
+            .puzzle
+            .chars()
+            .zip(solution.iter())
+            .all(|(ch, digit)| ch == '.' || ch == '0' || ch.to_digit(10) == Some(*digit as u32));
+        if !givens_match {
+            return Err("stored solution disagrees with the givens".to_string());
+        }
+        let solution_str: String = solution.iter().map(|d| (b'0' + d) as char).collect();
+        if !has_unique_solution_with_specs(&solution_str, &specs, regions, &mut rng) {
+            return Err("stored solution violates the constraints".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn shuffle_indices<T>(rng: &mut SimpleRng, positions: &mut [T]) {
+    if positions.len() <= 1 {
+        return;
+    }
+    for i in (1..positions.len()).rev() {
+        let j = rng.gen_range(0..i + 1);
+        positions.swap(i, j);
+    }
+}
+
+fn generate_puzzle_from_solution(
+    solution: &[u8; NN],
+    target_clues: usize,
+    specs: &[VariantSpec],
+    rng: &mut SimpleRng,
+    job: Option<&RunningJob>,
+    limits: &GenerationLimits,
+    keep: &[usize],
+) -> Result<String, String> {
+    if target_clues >= NN {
+        return Err("clue_target must be less than 81".to_string());
+    }
+
+    let mut puzzle: Vec<Option<u8>> = solution.iter().copied().map(Some).collect();
+    let mut positions: Vec<usize> = (0..NN).filter(|pos| !keep.contains(pos)).collect();
+    shuffle_indices(rng, &mut positions);
+    report_progress(job, |p| {
+        p.stage = "clues";
+        p.clues_to_remove = NN - target_clues;
+    });
+
+    for (checks, pos) in positions.into_iter().enumerate() {
+        check_cancelled(job)?;
+        limits.check(checks)?;
+        let saved = puzzle[pos];
+        puzzle[pos] = None;
+        let puzzle_str = puzzle_vec_to_string(&puzzle);
+        if !has_unique_solution_with_specs(&puzzle_str, specs, None, rng) {
+            puzzle[pos] = saved;
+        }
+        let clues_now = puzzle.iter().filter(|c| c.is_some()).count();
+        report_progress(job, |p| {
+            p.clues_removed = NN - clues_now;
+            p.uniqueness_checks = checks + 1;
+        });
+        if clues_now <= target_clues {
+            break;
+        }
+    }
+
+    Ok(puzzle_vec_to_string(&puzzle))
+}
+
+/// Pinned givens as an 81 character givens string, or `None` without any.
+/// Rejects digits that already clash by row, column or box, since the
+/// solver would otherwise only report that no solution exists.
+fn pinned_givens(givens: &[PinnedGiven]) -> Result<Option<String>, String> {
+    if givens.is_empty() {
+        return Ok(None);
+    }
+    let mut grid = ['.'; NN];
+    for given in givens {
+        let (r, c) = given.cell;
+        if r > 8 || c > 8 {
+            return Err(format!("given [{r}, {c}] is outside the grid"));
+        }
+        if !(1..=9).contains(&given.digit) {
+            return Err(format!("given [{r}, {c}] must be a digit from 1 to 9"));
+        }
+        if grid[r * 9 + c] != '.' {
+            return Err(format!("cell [{r}, {c}] is given more than once"));
+        }
+        let digit = (b'0' + given.digit) as char;
+        let clash = (0..NN).find(|&pos| {
+            let (r2, c2) = (pos / 9, pos % 9);
+            grid[pos] == digit && (r2 == r || c2 == c || (r2 / 3 == r / 3 && c2 / 3 == c / 3))
+        });
+        if let Some(pos) = clash {
+            return Err(format!(
+                "given {digit} at [{r}, {c}] clashes with the one at [{}, {}]",
+                pos / 9,
+                pos % 9
+            ));
+        }
+        grid[r * 9 + c] = digit;
+    }
+    Ok(Some(grid.iter().collect()))
+}
+
+/// Updates a generation job's progress; plain requests have no one to tell.
+fn report_progress(job: Option<&RunningJob>, update: impl FnOnce(&mut JobProgress)) {
+    if let Some(job) = job {
+        job.report(update);
+    }
+}
+
+/// How long one custom generation may run and how many uniqueness checks it
+/// may spend removing clues. Both are checked between steps, so a single
+/// slow solve or uniqueness check can still overrun `max_millis`.
+struct GenerationLimits {
+    started: Instant,
+    max_millis: Option<u64>,
+    max_uniqueness_checks: Option<usize>,
+}
+
+impl GenerationLimits {
+    fn unlimited() -> Self {
+        Self {
+            started: Instant::now(),
+            max_millis: None,
+            max_uniqueness_checks: None,
+        }
+    }
+
+    /// The server's limits, for requests that can't set their own.
+    fn server(config: &Config) -> Self {
+        Self {
+            started: Instant::now(),
+            max_millis: Some(config.generate_max_millis),
+            max_uniqueness_checks: Some(config.generate_max_uniqueness_checks),
+        }
+    }
+
+    /// The request's limits, defaulting to and capped by the server's.
+    fn for_request(req: &AdminGenerateCustomRequest, config: &Config) -> Result<Self, String> {
+        let max_millis = req.max_millis.unwrap_or(config.generate_max_millis);
+        if max_millis == 0 || max_millis > config.generate_max_millis {
+            return Err(format!(
+                "max_millis must be between 1 and {}",
+                config.generate_max_millis
+            ));
+        }
+        let max_checks = req
+            .max_uniqueness_checks
+            .unwrap_or(config.generate_max_uniqueness_checks);
+        if max_checks == 0 || max_checks > config.generate_max_uniqueness_checks {
+            return Err(format!(
+                "max_uniqueness_checks must be between 1 and {}",
+                config.generate_max_uniqueness_checks
+            ));
+        }
+        Ok(Self {
+            started: Instant::now(),
+            max_millis: Some(max_millis),
+            max_uniqueness_checks: Some(max_checks),
+        })
+    }
+
+    fn check(&self, uniqueness_checks: usize) -> Result<(), String> {
+        if self
+            .max_millis
+            .is_some_and(|max| self.started.elapsed() >= Duration::from_millis(max))
+        {
+            return Err(format!(
+                "Generation gave up after {} ms; the constraints may be too tight for the clue target",
+                self.max_millis.unwrap_or_default()
+            ));
+        }
+        if self
+            .max_uniqueness_checks
+            .is_some_and(|max| uniqueness_checks >= max)
+        {
+            return Err(format!(
+                "Generation gave up after {uniqueness_checks} uniqueness checks without reaching the clue target"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Stops a generation job that was cancelled.
+fn check_cancelled(job: Option<&RunningJob>) -> Result<(), String> {
+    if job.is_some_and(|job| job.is_cancelled()) {
+        return Err(jobs::CANCELLED.to_string());
+    }
+    Ok(())
+}
+
+async fn admin_generate_custom_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminGenerateCustomRequest>,
+) -> impl IntoResponse {
+    let config = state.config.clone();
+    let result = tokio::task::spawn_blocking(move || generate_custom(req, &config, None)).await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Generator task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+/// Generates a puzzle from custom constraints. Blocking; shared by the
+/// custom generate endpoint and the generation job worker, which passes
+/// `job` for progress and cancellation.
+fn generate_custom(
+    req: AdminGenerateCustomRequest,
+    config: &Config,
+    job: Option<&RunningJob>,
+) -> Result<AdminGenerateResponse, String> {
+    let limits = GenerationLimits::for_request(&req, config)?;
+    let seeding = config.custom_rng_seeding;
+    report_progress(job, |p| p.stage = "solution");
+    let regenerate_solution = req.regenerate_solution.unwrap_or(false);
+    // A previously generated puzzle_json can be submitted as `constraints`;
+    // its seed is the one a regeneration must move away from.
+    let previous_seed = req
+        .seed
+        .or_else(|| req.constraints.get("seed").and_then(|v| v.as_u64()));
+
+    let constraints = normalize_constraints_input(req.constraints)?;
+    let specs = constraints_from_json(&constraints)?;
+    let pinned = pinned_givens(req.givens.as_deref().unwrap_or_default())?;
+
+    let requested_seed = if regenerate_solution { None } else { req.seed };
+    // A regeneration must move to a new seed, so it never uses a fixed one.
+    let (mut rng, seed_source) = match requested_seed {
+        Some(seed) => (SimpleRng::from_seed(seed), "request"),
+        None if regenerate_solution => {
+            let mut rng = SimpleRng::new();
+            while Some(rng.seed()) == previous_seed {
+                rng = SimpleRng::new();
+            }
+            (rng, "entropy")
+        }
+        None => (seeding.rng(), seeding.name()),
+    };
+    let seed = requested_seed.unwrap_or_else(|| rng.seed());
+
+    let pinned_rejected = std::cell::Cell::new(false);
+    let solution = generate_full_solution_with(rng.clone(), |eng| {
+        apply_variant_specs(eng, &specs);
+        if pinned
+            .as_deref()
+            .is_some_and(|pinned| eng.load_givens(pinned).is_err())
+        {
+            pinned_rejected.set(true);
+        }
+    });
+    if pinned_rejected.get() {
+        return Err("the engine rejected the pinned givens".to_string());
+    }
+    let solution = solution.map_err(|err| match pinned {
+        Some(_) => format!("{err}; the pinned givens may not fit the constraints"),
+        None => err,
+    })?;
+    // Digging keeps the pinned cells, so they must hold the solution's
+    // digits or the puzzle would show wrong givens.
+    for given in req.givens.iter().flatten() {
+        let (r, c) = given.cell;
+        if solution[r * 9 + c] != given.digit {
+            return Err(format!(
+                "the solution has {} at [{r}, {c}], not the pinned {}",
+                solution[r * 9 + c],
+                given.digit
+            ));
+        }
+    }
+
+    let clue_target = req.clue_target.unwrap_or(30);
+    check_cancelled(job)?;
+    limits.check(0)?;
+    let keep: Vec<usize> = req
+        .givens
+        .iter()
+        .flatten()
+        .map(|given| given.cell.0 * 9 + given.cell.1)
+        .collect();
+    let puzzle = generate_puzzle_from_solution(
+        &solution,
+        clue_target,
+        &specs,
+        &mut rng,
+        job,
+        &limits,
+        &keep,
+    )?;
+
+    let labels = constraint_labels(&constraints);
+    let constraints_json = constraints;
+    let variants = variant_kinds(&specs);
+    let clue_count = puzzle.chars().filter(|c| *c != '.').count();
+
+    let puzzle_json = serde_json::json!({
+        "puzzle": puzzle,
+        "solution": solution.to_vec(),
+        "constraints": constraints_json,
+        "seed": seed,
+        "rng": rng::RNG_KIND,
+        "seed_source": seed_source,
+        "clue_count": clue_count,
+        "symmetry": null,
+        "engine_version": ENGINE_VERSION,
+    });
+
+    check_cancelled(job)?;
+    report_progress(job, |p| p.stage = "render");
+    let render_options = RenderOptions::default();
+    let constraints_render = engine_constraints_from_specs(&specs, None);
+    let puzzle_svg = render_puzzle_svg(&puzzle, &constraints_render, render_options)?;
+
+    Ok(AdminGenerateResponse {
+        puzzle_json: puzzle_json.to_string(),
+        svg: puzzle_svg,
+        variants,
+        labels,
+    })
+}
+
+#[derive(Serialize)]
+struct AdminJobQueuedResponse {
+    id: String,
+    status: &'static str,
+}
+
+/// Queues a custom generation and returns its job id at once. The request
+/// is the same as for `/api/admin/puzzles/generate/custom`; poll
+/// `/api/admin/jobs/{id}` for the result.
+async fn admin_enqueue_generate_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminGenerateCustomRequest>,
+) -> impl IntoResponse {
+    let request_json = match serde_json::to_string(&req) {
+        Ok(json) => json,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    match jobs::enqueue(&state.db, jobs::KIND_GENERATE, &request_json).await {
+        Ok(id) => (
+            StatusCode::ACCEPTED,
+            Json(AdminJobQueuedResponse {
+                id,
+                status: "queued",
+            }),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+    }
+}
+
+async fn admin_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match jobs::get(&state.db, &id).await {
+        Ok(Some(job)) => Json(job).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Job not found").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+    }
+}
+
+/// Cancels a queued job at once, or asks a running one to stop at its next
+/// checkpoint. Returns the job as it stands.
+async fn admin_job_cancel_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = match jobs::get(&state.db, &id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Job not found").into_response(),
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
+        }
+    };
+    if matches!(job.status.as_str(), "done" | "failed") {
+        return (StatusCode::CONFLICT, "Job has already finished").into_response();
+    }
+
+    let cancelled = match jobs::cancel_queued(&state.db, &id).await {
+        Ok(cancelled) => cancelled,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
+        }
+    };
+    if !cancelled {
+        // Claimed by the worker in the meantime. The mark comes first so a
+        // job the worker hasn't registered yet still sees it.
+        if let Err(e) = jobs::mark_cancel_requested(&state.db, &id).await {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
+        }
+        state.job_progress.cancel(&id);
+    }
+
+    match jobs::get(&state.db, &id).await {
+        Ok(Some(job)) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Job not found").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response(),
+    }
+}
+
+/// Streams a job's progress as Server-Sent Events until it finishes.
+async fn admin_job_events_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    match jobs::get(&state.db, &id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, "Job not found").into_response(),
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")).into_response();
+        }
+    }
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    tokio::spawn(jobs::stream_events(
+        state.db.clone(),
+        state.job_progress.clone(),
+        id,
+        tx,
+    ));
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct AdminActivityQuery {
+    /// Comma separated kinds, e.g. `check,solve`; every kind when unset.
+    events: Option<String>,
+    date: Option<String>,
+    /// Buffered events to replay before live ones, default 20.
+    recent: Option<usize>,
+}
+
+/// Player views, checks and solves as Server-Sent Events while they
+/// happen, for watching a launch without polling the stats endpoints.
+async fn admin_activity_stream_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminActivityQuery>,
+) -> Response {
+    let kinds: Vec<String> = query
+        .events
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|kind| kind.trim().to_string())
+        .filter(|kind| !kind.is_empty())
+        .collect();
+    if let Some(kind) = kinds
+        .iter()
+        .find(|kind| !activity::KINDS.contains(&kind.as_str()))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unknown event {kind}; expected {}",
+                activity::KINDS.join(", ")
+            ),
+        )
+            .into_response();
+    }
+    if query
+        .date
+        .as_deref()
+        .is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err())
+    {
+        return (StatusCode::BAD_REQUEST, "date must be YYYY-MM-DD").into_response();
+    }
+
+    let filter = activity::ActivityFilter {
+        kinds,
+        date_utc: query.date,
+    };
+    let recent = query.recent.unwrap_or(20);
+    let (tx, rx) = tokio::sync::mpsc::channel(64);
+    let feed = state.activity.clone();
+    tokio::spawn(async move { activity::stream(&feed, filter, recent, tx).await });
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn admin_constraint_schema_handler() -> impl IntoResponse {
+    Json(ConstraintSchemaResponse {
+        common_fields: schema::COMMON_FIELDS,
+        types: schema::CONSTRAINTS,
+    })
+}
+
+async fn admin_render_preview_handler(
+    Json(req): Json<AdminRenderPreviewRequest>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking(move || {
+        let constraints = normalize_constraints_input(req.constraints)?;
+        let specs = constraints_from_json(&constraints)?;
+        let variants = variant_kinds(&specs);
+
+        let empty_grid = ".".repeat(NN);
+        let constraints_render = engine_constraints_from_specs(&specs, None);
+        let svg = render_puzzle_svg(&empty_grid, &constraints_render, RenderOptions::default())?;
+
+        Ok::<_, String>((svg, variants))
+    })
+    .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Render task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    let (svg, variants) = match result {
+        Ok(result) => result,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    Json(AdminRenderPreviewResponse { svg, variants }).into_response()
+}
+
+#[derive(Deserialize)]
+struct AdminMinimalityRequest {
+    puzzle_json: String,
+}
+
+#[derive(Serialize)]
+struct GivenMinimality {
+    cell: [usize; 2],
+    digit: u8,
+    /// The puzzle stays unique without this given alone.
+    redundant: bool,
+}
+
+#[derive(Serialize)]
+struct AdminMinimalityResponse {
+    clue_count: usize,
+    /// No given can be removed without losing uniqueness.
+    minimal: bool,
+    givens: Vec<GivenMinimality>,
+}
+
+/// Checks each given of a unique puzzle for whether it could be removed on
+/// its own, so setters can trim clues before publishing.
+async fn admin_minimality_handler(Json(req): Json<AdminMinimalityRequest>) -> Response {
+    let result = tokio::task::spawn_blocking(move || minimality(&req.puzzle_json)).await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Analysis task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+/// One uniqueness check per given, each with only that given removed.
+fn minimality(puzzle_json: &str) -> Result<AdminMinimalityResponse, String> {
+    let parsed = parse_puzzle_json(puzzle_json)?;
+    let specs = constraints_from_json(&parsed.constraints)?;
+    let regions = parsed.regions.as_deref();
+    let mut rng = SimpleRng::new();
+    if !has_unique_solution_with_specs(&parsed.puzzle, &specs, regions, &mut rng) {
+        return Err("puzzle does not have a unique solution".to_string());
+    }
+
+    let cells: Vec<char> = parsed.puzzle.chars().collect();
+    let mut givens = Vec::new();
+    for (pos, ch) in cells.iter().enumerate() {
+        let Some(digit) = ch.to_digit(10).filter(|d| *d > 0) else {
+            continue;
+        };
+        let mut without = cells.clone();
+        without[pos] = '.';
+        let without: String = without.into_iter().collect();
+        givens.push(GivenMinimality {
+            cell: [pos / 9, pos % 9],
+            digit: digit as u8,
+            redundant: has_unique_solution_with_specs(&without, &specs, regions, &mut rng),
+        });
+    }
+
+    Ok(AdminMinimalityResponse {
+        clue_count: givens.len(),
+        minimal: givens.iter().all(|given| !given.redundant),
+        givens,
+    })
+}
+
+#[derive(Deserialize)]
+struct AdminDifficultyRequest {
+    puzzle_json: String,
+}
+
+/// The rater's steps in order, each with its technique, the cells it
+/// follows from and what it places or eliminates.
+async fn admin_solve_path_handler(Json(req): Json<AdminDifficultyRequest>) -> Response {
+    let result = tokio::task::spawn_blocking(move || {
+        let parsed = parse_puzzle_json(&req.puzzle_json)?;
+        difficulty::solve_path(
+            &parsed.puzzle,
+            &parsed.constraints,
+            parsed.regions.as_deref(),
+        )
+    })
+    .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Analysis task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(path) => Json(path).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+/// Rates a puzzle by the techniques a human needs to solve it, with how
+/// often each was used.
+async fn admin_difficulty_handler(Json(req): Json<AdminDifficultyRequest>) -> Response {
+    let result = tokio::task::spawn_blocking(move || rate_puzzle_json(&req.puzzle_json)).await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Analysis task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(rating) => Json(rating).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+const DEFAULT_SOLUTION_CAP: usize = 100;
+const MAX_SOLUTION_CAP: usize = 1000;
+/// Solutions returned in full, for comparing where they differ.
+const SOLUTION_EXAMPLES: usize = 3;
+
+#[derive(Deserialize)]
+struct AdminSolutionCountRequest {
+    puzzle_json: String,
+    cap: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct AdminSolutionCountResponse {
+    /// Solutions found; a lower bound when `capped` or `timed_out`.
+    solutions: usize,
+    capped: bool,
+    timed_out: bool,
+    /// Exactly one solution, with the search finished.
+    unique: bool,
+    /// The first few solutions as 81-digit strings.
+    examples: Vec<String>,
+}
+
+/// Counts the solutions of a puzzle up to `cap`, to confirm uniqueness or
+/// see how far a broken import is from it.
+async fn admin_solution_count_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminSolutionCountRequest>,
+) -> Response {
+    let cap = req.cap.unwrap_or(DEFAULT_SOLUTION_CAP);
+    if cap == 0 || cap > MAX_SOLUTION_CAP {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("cap must be between 1 and {MAX_SOLUTION_CAP}"),
+        )
+            .into_response();
+    }
+    let max_time = Duration::from_millis(state.config.generate_max_millis);
+    let result =
+        tokio::task::spawn_blocking(move || count_solutions(&req.puzzle_json, cap, max_time)).await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Analysis task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+/// Splits the puzzle on the first empty cell until each branch has no
+/// solution or exactly one, stopping at `cap` solutions or after
+/// `max_time`. Blocking.
+fn count_solutions(
+    puzzle_json: &str,
+    cap: usize,
+    max_time: Duration,
+) -> Result<AdminSolutionCountResponse, String> {
+    let parsed = parse_puzzle_json(puzzle_json)?;
+    // Finding a solution goes through the generator, which only knows the
+    // standard boxes.
+    if parsed.regions.is_some() {
+        return Err("solutions of jigsaw puzzles can't be counted".to_string());
+    }
+    let specs = constraints_from_json(&parsed.constraints)?;
+    let started = Instant::now();
+    let mut rng = SimpleRng::new();
+
+    let mut response = AdminSolutionCountResponse {
+        solutions: 0,
+        capped: false,
+        timed_out: false,
+        unique: false,
+        examples: Vec::new(),
+    };
+    let mut stack = vec![parsed.puzzle.clone()];
+    while let Some(puzzle) = stack.pop() {
+        if started.elapsed() >= max_time {
+            response.timed_out = true;
+            break;
+        }
+        let solution = generate_full_solution_with(rng.clone(), |eng| {
+            apply_variant_specs(eng, &specs);
+            let _ = eng.load_givens(&puzzle);
+        });
+        let Ok(solution) = solution else {
+            continue;
+        };
+        if has_unique_solution_with_specs(&puzzle, &specs, None, &mut rng) {
+            response.solutions += 1;
+            if response.examples.len() < SOLUTION_EXAMPLES {
+                response
+                    .examples
+                    .push(solution.iter().map(|d| (b'0' + d) as char).collect());
+            }
+            if response.solutions >= cap {
+                response.capped = true;
+                break;
+            }
+            continue;
+        }
+
+        let cells: Vec<char> = puzzle.chars().collect();
+        let Some(pos) = cells.iter().position(|ch| *ch == '.' || *ch == '0') else {
+            continue;
+        };
+        for digit in (1..=9u8).rev() {
+            let ch = (b'0' + digit) as char;
+            if clashes(&cells, pos, ch) {
+                continue;
+            }
+            let mut branch = cells.clone();
+            branch[pos] = ch;
+            stack.push(branch.into_iter().collect());
+        }
+    }
+
+    response.unique = response.solutions == 1 && stack.is_empty() && !response.timed_out;
+    Ok(response)
+}
+
+/// Whether `ch` already appears in the row, column or box of `pos`.
+fn clashes(cells: &[char], pos: usize, ch: char) -> bool {
+    let (r, c) = (pos / 9, pos % 9);
+    cells.iter().enumerate().any(|(other, &cell)| {
+        let (r2, c2) = (other / 9, other % 9);
+        cell == ch && (r2 == r || c2 == c || (r2 / 3 == r / 3 && c2 / 3 == c / 3))
+    })
+}
+
+#[derive(Deserialize)]
+struct AdminSolveRequest {
+    puzzle_json: String,
+}
+
+#[derive(Serialize)]
+struct AdminSolveResponse {
+    solution: Vec<u8>,
+    /// The request's puzzle_json with `solution` filled in, ready to store.
+    puzzle_json: String,
+}
+
+/// Solves givens and constraints imported from elsewhere without a
+/// solution. Any solution already in `puzzle_json` is replaced.
+async fn admin_solve_handler(Json(req): Json<AdminSolveRequest>) -> Response {
+    let result = tokio::task::spawn_blocking(move || {
+        let parsed = parse_puzzle_json(&req.puzzle_json)?;
+        // The solver only knows the standard boxes.
+        if parsed.regions.is_some() {
+            return Err("jigsaw puzzles can't be solved".to_string());
+        }
+        let solution = solve_puzzle(&parsed.puzzle, &parsed.constraints)?.to_vec();
+        let mut value: serde_json::Value =
+            serde_json::from_str(&req.puzzle_json).map_err(|e| format!("invalid JSON: {e}"))?;
+        value["solution"] = serde_json::json!(solution);
+        Ok::<_, String>(AdminSolveResponse {
+            solution,
+            puzzle_json: value.to_string(),
+        })
+    })
+    .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Solver task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+async fn admin_create_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminCreateRequest>,
+) -> Response {
+    let date_utc = req.date_utc.clone();
+    if let Err((status, err)) = create_puzzle(&state, req).await {
+        return (status, err).into_response();
+    }
+    admin_get_handler(State(state), Path(date_utc)).await
+}
+
+/// Validates and stores one puzzle for the create and bulk import endpoints.
+async fn create_puzzle(
+    state: &AppState,
+    req: AdminCreateRequest,
+) -> Result<(), (StatusCode, String)> {
+    let prepared = prepare_puzzle(state, req).await?;
+    store_puzzle(state, prepared).await
+}
+
+/// A create request that passed validation, ready to be written.
+struct PreparedPuzzle {
+    date_utc: String,
+    status: String,
+    puzzle_json: String,
+    svg: Option<String>,
+    name: Option<String>,
+    author: Option<String>,
+    difficulty: Option<i64>,
+    variants_json: String,
+    published_at: Option<String>,
+    /// The engine that generated or last verified the puzzle, if known.
+    engine_version: Option<String>,
+    extra_json: Option<String>,
+}
+
+/// Everything `create_puzzle` does short of writing: the overwrite check,
+/// size limits, field quarantine, variant detection and rendering.
+async fn prepare_puzzle(
+    state: &AppState,
+    req: AdminCreateRequest,
+) -> Result<PreparedPuzzle, (StatusCode, String)> {
+    let AdminCreateRequest {
+        date_utc,
+        puzzle_json,
+        svg,
+        variants,
+        status,
+        name,
+        author,
+        difficulty,
+        overwrite,
+        defer_render,
+    } = req;
+
+    let overwrite = overwrite.unwrap_or(true);
+    if !overwrite {
+        match store::puzzle_exists(&state.db, &date_utc).await {
+            Ok(true) => {
+                return Err((StatusCode::CONFLICT, "Puzzle already exists".to_string()));
+            }
+            Ok(false) => {}
+            Err(e) => {
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")));
+            }
+        }
+    }
+
+    if puzzle_json.len() > state.config.max_puzzle_json_bytes {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "puzzle_json exceeds {} bytes",
+                state.config.max_puzzle_json_bytes
+            ),
+        ));
+    }
+
+    let (puzzle_json, extra_json) = sanitize::quarantine_unknown_fields(&puzzle_json)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    if let Some(extra) = &extra_json {
+        tracing::warn!("quarantined unknown puzzle_json fields for {date_utc}: {extra}");
+    }
+
+    let parsed = parse_puzzle_json(&puzzle_json).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    let variants = match &variants {
+        Some(list) => dedupe_variants(list.clone()),
+        None => variants_from_constraints(&parsed.constraints)
+            .map_err(|err| (StatusCode::BAD_REQUEST, err))?,
+    };
+
+    // The stored SVG is inlined into public pages, so anything that could run
+    // script or load external content is replaced by a server-side render.
+    let svg = svg.filter(|svg| match sanitize::check_svg(svg) {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::warn!("re-rendering svg for {date_utc}, supplied svg rejected: {err}");
+            false
+        }
+    });
+
+    let svg = if let Some(svg) = svg {
+        Some(svg)
+    } else {
+        let specs = constraints_from_json(&parsed.constraints)
+            .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+        if defer_render.unwrap_or(false) {
+            // The constraints are still checked above; rendering waits for
+            // the first read.
+            None
+        } else {
+            let constraints = engine_constraints_from_specs(&specs, parsed.regions.as_deref());
+            let render_options = RenderOptions::default();
+            let svg = render_puzzle_svg(&parsed.puzzle, &constraints, render_options)
+                .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+            Some(svg)
+        }
+    };
+
+    if svg
+        .as_ref()
+        .is_some_and(|svg| svg.len() > state.config.max_svg_bytes)
+    {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("svg exceeds {} bytes", state.config.max_svg_bytes),
+        ));
+    }
+
+    let status = status.unwrap_or_else(|| "draft".to_string());
+    let published_at = if status == "published" {
+        Some(now_utc_string())
+    } else {
+        None
+    };
+
+    let variants_json = serde_json::to_string(&variants).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to encode variants: {e}"),
+        )
+    })?;
+
+    // Only the version the puzzle itself records: this engine hasn't checked
+    // it, so an unversioned puzzle stays unversioned for reverify to find.
+    let engine_version = parsed.engine_version;
+
+    let difficulty = match difficulty {
+        Some(difficulty) => Some(difficulty),
+        None => match difficulty::rate(
+            &parsed.puzzle,
+            &parsed.constraints,
+            parsed.regions.as_deref(),
+        ) {
+            Ok(rating) => Some(rating.difficulty),
+            Err(err) => {
+                tracing::warn!("cannot rate {date_utc}: {err}");
+                None
+            }
+        },
+    };
+
+    Ok(PreparedPuzzle {
+        date_utc,
+        status,
+        puzzle_json,
+        svg,
+        name,
+        author,
+        difficulty,
+        variants_json,
+        published_at,
+        engine_version,
+        extra_json,
+    })
+}
+
+async fn store_puzzle(
+    state: &AppState,
+    prepared: PreparedPuzzle,
+) -> Result<(), (StatusCode, String)> {
+    let PreparedPuzzle {
+        date_utc,
+        status,
+        puzzle_json,
+        svg,
+        name,
+        author,
+        difficulty,
+        variants_json,
+        published_at,
+        engine_version,
+        extra_json,
+    } = prepared;
+
+    let write = store::PuzzleWrite {
+        date_utc: &date_utc,
+        status: &status,
+        puzzle_json: &puzzle_json,
+        svg: svg.as_deref(),
+        title: name.as_deref(),
+        author: author.as_deref(),
+        difficulty,
+        variants_json: &variants_json,
+        published_at_utc: published_at.as_deref(),
+        engine_version: engine_version.as_deref(),
+        extra_json: extra_json.as_deref(),
+    };
+    // `prepare_puzzle` has already refused existing dates without overwrite.
+    if let Err(e) = store::upsert_puzzle(&state.db, &write, true).await {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}")));
+    }
+    state.today_cache.clear();
+
+    if status == "published" {
+        spawn_social_announcement(state, &date_utc);
+    }
+    Ok(())
+}
+
+async fn admin_list_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminListQuery>,
+) -> impl IntoResponse {
+    let include_stats = query
+        .include
+        .as_deref()
+        .is_some_and(|include| include.split(',').any(|v| v.trim() == "stats"));
+    let include_deleted = query
+        .include
+        .as_deref()
+        .is_some_and(|include| include.split(',').any(|v| v.trim() == "deleted"));
+
+    let status = query.status;
+
+    let etag = match admin_list_etag(&state.db, status.as_deref(), include_stats, include_deleted)
+        .await
+    {
+        Ok(etag) => etag,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+    if etag_matches(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    let rows = match store::list_puzzles(&state.db, status.as_deref(), include_deleted).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let out: Vec<AdminPuzzleSummary> = rows
+        .into_iter()
+        .map(|row| AdminPuzzleSummary {
+            thumbnail_url: matches!(row.status.as_str(), "published" | "archived")
+                .then(|| format!("/api/puzzle/{}/thumbnail.png", row.date_utc)),
+            date_utc: row.date_utc,
+            status: row.status,
+            name: row.title,
+            author: row.author,
+            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
+                .unwrap_or_default(),
+            difficulty: row.difficulty,
+            created_at_utc: row.created_at_utc,
+            published_at_utc: row.published_at_utc,
+            deleted_at_utc: row.deleted_at_utc,
+            stats: include_stats.then(|| PuzzleStatsSummary {
+                views: row.views.unwrap_or(0),
+                checks: row.checks.unwrap_or(0),
+                solves: row.solves.unwrap_or(0),
+            }),
+        })
+        .collect();
+
+    ([(ETAG, etag)], Json(out)).into_response()
+}
+
+/// Collection ETag for the admin list: changes whenever a matching puzzle is
+/// added, removed or updated, or (with stats) whenever its stats move.
+async fn admin_list_etag(
+    db: &SqlitePool,
+    status: Option<&str>,
+    include_stats: bool,
+    include_deleted: bool,
+) -> Result<String, sqlx::Error> {
+    let row = store::list_fingerprint(db, status, include_deleted).await?;
+
+    let mut hasher = DefaultHasher::new();
+    (status, include_deleted, row.count, row.updated_at_utc).hash(&mut hasher);
+    if include_stats {
+        row.last_seen_utc.hash(&mut hasher);
+    }
+    Ok(format!("W/\"{:016x}\"", hasher.finish()))
+}
+
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+async fn admin_get_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> Response {
+    let row = match store::get_puzzle(&state.db, &date_utc).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let variants: Vec<String> =
+        serde_json::from_str(row.variants.as_deref().unwrap_or("[]")).unwrap_or_default();
+    let labels = stored_constraint_labels(&row.puzzle_json);
+    let svg = match ensure_svg(&state.db, &date_utc, row.svg, &row.puzzle_json).await {
+        Ok(svg) => Some(svg),
+        Err(err) => {
+            tracing::warn!("svg for {date_utc} unavailable: {err}");
+            None
+        }
+    };
+
+    Json(AdminPuzzleResponse {
+        date_utc: row.date_utc,
+        status: row.status,
+        name: row.title,
+        author: row.author,
+        puzzle_json: row.puzzle_json,
+        svg,
+        variants,
+        labels,
+        difficulty: row.difficulty,
+        created_at_utc: row.created_at_utc,
+        updated_at_utc: row.updated_at_utc,
+        published_at_utc: row.published_at_utc,
+        deleted_at_utc: row.deleted_at_utc,
+        origin: row
+            .origin_json
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    })
+    .into_response()
+}
+
+/// Every puzzle with its SVG, metadata and stats, for backups and moving
+/// puzzles between instances.
+async fn admin_export_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminExportQuery>,
+) -> Response {
+    let ndjson = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("ndjson") => true,
+        Some(_) => {
+            return (StatusCode::BAD_REQUEST, "format must be json or ndjson").into_response();
+        }
+    };
+
+    if ndjson {
+        return ndjson_export_response(&state, bundle::BundleFilter::default());
+    }
+
+    let puzzles = match bundle::load_puzzles(&state.db, &bundle::BundleFilter::default()).await {
+        Ok(puzzles) => puzzles,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let body = serde_json::to_string(&bundle::Bundle {
+        format: bundle::BUNDLE_FORMAT.to_string(),
+        version: bundle::BUNDLE_VERSION,
+        exported_at_utc: now_utc_string(),
+        engine_version: ENGINE_VERSION.to_string(),
+        puzzles,
+    });
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to encode export: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    (
+        [
+            (CONTENT_TYPE, "application/json".to_string()),
+            (CONTENT_DISPOSITION, export_disposition("json")),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Streams puzzles as NDJSON, optionally filtered by status and date range,
+/// without holding the whole archive in memory.
+async fn admin_export_ndjson_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminExportNdjsonQuery>,
+) -> Response {
+    match export_filter(query.status, query.from, query.to) {
+        Ok(filter) => ndjson_export_response(&state, filter),
+        Err(response) => response,
+    }
+}
+
+fn export_filter(
+    status: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<bundle::BundleFilter, Response> {
+    let bad_status = status
+        .as_deref()
+        .is_some_and(|status| !matches!(status, "draft" | "published" | "archived"));
+    if bad_status {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "status must be draft, published or archived",
+        )
+            .into_response());
+    }
+    for date in [&from, &to].into_iter().flatten() {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Invalid date (expected YYYY-MM-DD)",
+            )
+                .into_response());
+        }
+    }
+    Ok(bundle::BundleFilter { status, from, to })
+}
+
+/// Feeds the NDJSON export from a background task, so rows go out as they
+/// are read.
+fn ndjson_export_response(state: &AppState, filter: bundle::BundleFilter) -> Response {
+    let (tx, rx) = tokio::sync::mpsc::channel(64);
+    tokio::spawn(bundle::stream_ndjson(state.db.clone(), filter, tx));
+    (
+        [
+            (CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (CONTENT_DISPOSITION, export_disposition("ndjson")),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+fn export_disposition(extension: &str) -> String {
+    format!(
+        "attachment; filename=\"makudoku-export-{}.{extension}\"",
+        Utc::now().date_naive()
+    )
+}
+
+/// Puzzles in the exchange format, for another instance to import.
+/// Published puzzles by default; signed when a signing key is configured.
+async fn admin_exchange_export_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminExportNdjsonQuery>,
+) -> Response {
+    let status = query.status.or_else(|| Some("published".to_string()));
+    let filter = match export_filter(status, query.from, query.to) {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+    let rows = match bundle::load_puzzles(&state.db, &filter).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+    match exchange::export(rows, &state.config, state.signer.as_deref()) {
+        Ok(document) => Json(document).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+/// Imports another instance's exchange document as drafts on consecutive
+/// days from `start_date`, checking each puzzle's signature and that it
+/// still has a unique solution.
+async fn admin_exchange_import_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminExchangeImportRequest>,
+) -> Response {
+    let AdminExchangeImportRequest {
+        document,
+        start_date,
+        overwrite,
+        require_trusted,
+    } = req;
+    if let Err(err) = document.check_format() {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+    if document.puzzles.len() > MAX_IMPORT_PUZZLES {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_IMPORT_PUZZLES} puzzles per import"),
+        )
+            .into_response();
+    }
+    let start_date = match NaiveDate::parse_from_str(&start_date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => {
+            return (StatusCode::BAD_REQUEST, "start_date must be YYYY-MM-DD").into_response();
+        }
+    };
+    let overwrite = overwrite.unwrap_or(false);
+    let require_trusted = require_trusted.unwrap_or(false);
+
+    let mut items = Vec::with_capacity(document.puzzles.len());
+    for (puzzle, date) in document.puzzles.iter().zip(start_date.iter_days()) {
+        let date_utc = date.to_string();
+        let signature = exchange::check_signature(
+            &document.origin,
+            puzzle,
+            &state.config.exchange_trusted_keys,
+        );
+        let result = import_exchange_puzzle(
+            &state,
+            &document.origin,
+            puzzle,
+            &date_utc,
+            signature,
+            overwrite,
+            require_trusted,
+        )
+        .await;
+        items.push(AdminExchangeImportItem {
+            origin_date_utc: puzzle.origin_date_utc.clone(),
+            date_utc,
+            signature: signature.as_str(),
+            ok: result.is_ok(),
+            error: result.err(),
+        });
+    }
+
+    Json(AdminExchangeImportResponse {
+        imported: items.iter().filter(|item| item.ok).count(),
+        items,
+    })
+    .into_response()
+}
+
+async fn import_exchange_puzzle(
+    state: &AppState,
+    origin: &exchange::ExchangeOrigin,
+    puzzle: &exchange::ExchangePuzzle,
+    date_utc: &str,
+    signature: exchange::SignatureCheck,
+    overwrite: bool,
+    require_trusted: bool,
+) -> Result<(), String> {
+    if signature == exchange::SignatureCheck::Invalid {
+        return Err("signature does not match the puzzle".to_string());
+    }
+    if require_trusted && signature != exchange::SignatureCheck::Trusted {
+        return Err(format!("signature is {}", signature.as_str()));
+    }
+    if !puzzle.puzzle.is_object() {
+        return Err("puzzle must be a JSON object".to_string());
+    }
+
+    let puzzle_json = puzzle.puzzle.to_string();
+    // Verified here, so the stored row carries this engine's version.
+    let verify_json = puzzle_json.clone();
+    match tokio::task::spawn_blocking(move || verify_puzzle_json(&verify_json)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => return Err(err),
+        Err(err) => return Err(format!("Verify task failed: {err}")),
+    }
+
+    let req = AdminCreateRequest {
+        date_utc: date_utc.to_string(),
+        puzzle_json,
+        svg: None,
+        variants: (!puzzle.variants.is_empty()).then(|| puzzle.variants.clone()),
+        status: Some("draft".to_string()),
+        name: puzzle.title.clone(),
+        author: puzzle.author.clone(),
+        difficulty: puzzle.difficulty,
+        overwrite: Some(overwrite),
+        defer_render: None,
+    };
+    let mut prepared = prepare_puzzle(state, req).await.map_err(|(_, err)| err)?;
+    prepared.engine_version = Some(ENGINE_VERSION.to_string());
+    store_puzzle(state, prepared)
+        .await
+        .map_err(|(_, err)| err)?;
+    let origin_json = exchange::origin_json(origin, puzzle, signature);
+    store::set_puzzle_origin(&state.db, date_utc, &origin_json)
+        .await
+        .map_err(|e| format!("DB error: {e}"))
+}
+
+/// Times generation and uniqueness checks over a matrix of variant sets and
+/// clue targets and stores the result. Takes as long as the matrix does.
+async fn admin_run_benchmark_handler(
+    State(state): State<AppState>,
+    Json(options): Json<benchmarks::BenchmarkOptions>,
+) -> Response {
+    let (cases, samples) = match benchmarks::plan(&options) {
+        Ok(plan) => plan,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    match benchmarks::run_and_save(&state.db, cases, samples).await {
+        Ok(run) => Json(run).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct AdminBenchmarksQuery {
+    engine_version: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn admin_benchmarks_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminBenchmarksQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(20).clamp(1, 200);
+    match benchmarks::list(&state.db, query.engine_version.as_deref(), limit).await {
+        Ok(runs) => Json(runs).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct AdminBenchmarkCompareQuery {
+    base: String,
+    head: String,
+}
+
+#[derive(Serialize)]
+struct AdminBenchmarkCompareResponse {
+    base_engine_version: String,
+    head_engine_version: String,
+    cases: Vec<benchmarks::CaseComparison>,
+}
+
+/// Per-case changes between two stored runs, e.g. the last run on the
+/// previous engine release as `base` and the newest as `head`.
+async fn admin_benchmark_compare_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminBenchmarkCompareQuery>,
+) -> Response {
+    let mut runs = Vec::with_capacity(2);
+    for id in [&query.base, &query.head] {
+        match benchmarks::get(&state.db, id).await {
+            Ok(Some(run)) => runs.push(run),
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, format!("No benchmark run {id}")).into_response();
+            }
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB error: {e}"),
+                )
+                    .into_response();
+            }
+        }
+    }
+    let (base, head) = (&runs[0], &runs[1]);
+    Json(AdminBenchmarkCompareResponse {
+        base_engine_version: base.engine_version.clone(),
+        head_engine_version: head.engine_version.clone(),
+        cases: benchmarks::compare(base, head),
+    })
+    .into_response()
+}
+
+/// Checks a bundled row before it is written, since it bypasses the create
+/// endpoint's validation.
+fn validate_bundle_puzzle(puzzle: &bundle::BundlePuzzle) -> Result<(), String> {
+    if puzzle.status == "published" && puzzle.svg.is_none() {
+        return Err("published puzzles need an svg".to_string());
+    }
+    validate_puzzle_row(
+        &puzzle.date_utc,
+        &puzzle.status,
+        &puzzle.puzzle_json,
+        puzzle.svg.as_deref(),
+    )
+}
+
+/// Checks a puzzle written as given rather than through the admin create
+/// path, from a bundle restore or a mirror sync. A missing SVG is rendered
+/// when first needed.
+fn validate_puzzle_row(
+    date_utc: &str,
+    status: &str,
+    puzzle_json: &str,
+    svg: Option<&str>,
+) -> Result<(), String> {
+    if NaiveDate::parse_from_str(date_utc, "%Y-%m-%d").is_err() {
+        return Err("date_utc must be YYYY-MM-DD".to_string());
+    }
+    if !matches!(status, "draft" | "published" | "archived") {
+        return Err("status must be draft, published or archived".to_string());
+    }
+    if let Some(svg) = svg {
+        sanitize::check_svg(svg)?;
+    }
+    parse_puzzle_json(puzzle_json).map(|_| ())
+}
+
+/// Restores a bundle from `GET /api/admin/export`. Existing dates are kept
+/// unless `mode=overwrite`; `dry_run` reports the actions without writing.
+async fn admin_restore_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminRestoreQuery>,
+    body: String,
+) -> Response {
+    let overwrite = match query.mode.as_deref() {
+        None | Some("skip_existing") => false,
+        Some("overwrite") => true,
+        Some(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "mode must be skip_existing or overwrite",
+            )
+                .into_response();
+        }
+    };
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let puzzles = match bundle::parse(&body) {
+        Ok(puzzles) => puzzles,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    let existing: HashSet<String> = match store::puzzle_dates(&state.db).await {
+        Ok(dates) => dates.into_iter().collect(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let mut items = Vec::with_capacity(puzzles.len());
+    for puzzle in &puzzles {
+        let exists = existing.contains(&puzzle.date_utc);
+        let action = match validate_bundle_puzzle(puzzle) {
+            Err(err) => Err(err),
+            Ok(()) if exists && !overwrite => Ok("skip"),
+            Ok(()) if exists => Ok("update"),
+            Ok(()) => Ok("create"),
+        };
+        let action = match action {
+            Ok(action) if dry_run || action == "skip" => Ok(action),
+            Ok(action) => store::restore_puzzle(&state.db, puzzle)
+                .await
+                .map(|()| action)
+                .map_err(|e| format!("DB error: {e}")),
+            Err(err) => Err(err),
+        };
+        items.push(match action {
+            Ok(action) => AdminRestoreItem {
+                date_utc: puzzle.date_utc.clone(),
+                action,
+                error: None,
+            },
+            Err(err) => AdminRestoreItem {
+                date_utc: puzzle.date_utc.clone(),
+                action: "fail",
+                error: Some(err),
+            },
+        });
+    }
+
+    if !dry_run {
+        state.today_cache.clear();
+    }
+    let count = |action: &str| items.iter().filter(|item| item.action == action).count();
+    Json(AdminRestoreResponse {
+        dry_run,
+        created: count("create"),
+        updated: count("update"),
+        skipped: count("skip"),
+        failed: count("fail"),
+        items,
+    })
+    .into_response()
+}
+
+const MAX_SLUG_LEN: usize = 64;
+
+/// Lowercase letters, digits and inner hyphens, e.g. `christmas-2025`.
+fn validate_slug(slug: &str) -> Result<(), String> {
+    let valid_chars = slug
+        .chars()
+        .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-');
+    if slug.is_empty() || slug.len() > MAX_SLUG_LEN || !valid_chars {
+        return Err(format!(
+            "slug must be 1-{MAX_SLUG_LEN} lowercase letters, digits or hyphens"
+        ));
+    }
+    if slug.starts_with('-') || slug.ends_with('-') {
+        return Err("slug must not start or end with a hyphen".to_string());
+    }
+    Ok(())
+}
+
+async fn admin_slugs_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match store::list_slugs(&state.db).await {
+        Ok(rows) => Json(
+            rows.into_iter()
+                .map(|row| AdminSlug {
+                    slug: row.slug,
+                    date_utc: row.date_utc,
+                    created_at_utc: row.created_at_utc,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Points a slug at a puzzle, moving it if it already names another date.
+async fn admin_save_slug_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminSlugRequest>,
+) -> impl IntoResponse {
+    let slug = req.slug.trim().to_string();
+    if let Err(err) = validate_slug(&slug) {
+        return (StatusCode::BAD_REQUEST, err).into_response();
+    }
+
+    match store::puzzle_exists(&state.db, &req.date_utc).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    }
+
+    let created_at = now_utc_string();
+    match store::save_slug(&state.db, &slug, &req.date_utc, &created_at).await {
+        Ok(_) => Json(AdminSlug {
+            slug,
+            date_utc: req.date_utc,
+            created_at_utc: created_at,
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+async fn admin_delete_slug_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> impl IntoResponse {
+    match store::delete_slug(&state.db, &slug).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "Slug not found").into_response(),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// SudokuPad loads f-puzzles data appended to this path.
+const SUDOKUPAD_FPUZZLES_URL: &str = "https://sudokupad.app/fpuzzles";
+
+async fn admin_export_sudokupad_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let row = match store::get_puzzle(&state.db, &date_utc).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let parsed = parse_puzzle_json(&row.puzzle_json)
+        .and_then(|parsed| Ok((constraints_from_json(&parsed.constraints)?, parsed)));
+    let (specs, parsed) = match parsed {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Invalid puzzle data",
+            )
+                .into_response();
+        }
+    };
+
+    let (data, skipped) = fpuzzles::encode(&fpuzzles::ExportPuzzle {
+        puzzle: &parsed.puzzle,
+        specs: &specs,
+        regions: parsed.regions.as_deref(),
+        title: row.title.as_deref(),
+        author: row.author.as_deref(),
+        rules: &parsed.custom_rules,
+    });
+    Json(AdminSudokuPadExportResponse {
+        date_utc,
+        url: format!("{SUDOKUPAD_FPUZZLES_URL}{data}"),
+        skipped,
+    })
+    .into_response()
+}
+
+const MAX_GOAL_LABEL_CHARS: usize = 120;
+
+async fn admin_goal_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    match store::get_goal(&state.db, &date_utc).await {
+        Ok(Some(row)) => Json(AdminGoal {
+            date_utc,
+            metric: row.metric,
+            target: row.target,
+            label: row.label,
+            updated_at_utc: row.updated_at_utc,
+        })
+        .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "No goal set").into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Sets or replaces the community goal of a puzzle.
+async fn admin_save_goal_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+    Json(req): Json<AdminGoalRequest>,
+) -> impl IntoResponse {
+    let metric = req.metric.unwrap_or_else(|| "solves".to_string());
+    if !matches!(metric.as_str(), "views" | "checks" | "solves") {
+        return (
+            StatusCode::BAD_REQUEST,
+            "metric must be views, checks or solves",
+        )
+            .into_response();
+    }
+    if req.target <= 0 {
+        return (StatusCode::BAD_REQUEST, "target must be positive").into_response();
+    }
+    let label = req
+        .label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty());
+    if label
+        .as_ref()
+        .is_some_and(|l| l.chars().count() > MAX_GOAL_LABEL_CHARS)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("label must be at most {MAX_GOAL_LABEL_CHARS} characters"),
+        )
+            .into_response();
+    }
+
+    match store::puzzle_exists(&state.db, &date_utc).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    }
+
+    let goal = store::GoalRow {
+        metric,
+        target: req.target,
+        label,
+        updated_at_utc: now_utc_string(),
+    };
+    match store::save_goal(&state.db, &date_utc, &goal).await {
+        Ok(()) => Json(AdminGoal {
+            date_utc,
+            metric: goal.metric,
+            target: goal.target,
+            label: goal.label,
+            updated_at_utc: goal.updated_at_utc,
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+async fn admin_delete_goal_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    match store::delete_goal(&state.db, &date_utc).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "No goal set").into_response(),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+const MAX_TESTER_CHARS: usize = 64;
+const MAX_TEST_NOTES_CHARS: usize = 4000;
+
+async fn admin_test_solves_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let rows = match store::list_test_solves(&state.db, &date_utc).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let test_solves = rows
+        .into_iter()
+        .map(|row| AdminTestSolve {
+            tester: row.tester,
+            grid: row.grid,
+            notes: row.notes,
+            verdict: row.verdict,
+            updated_at_utc: row.updated_at_utc,
+        })
+        .collect();
+    Json(AdminTestSolvesResponse {
+        date_utc,
+        test_solves,
+    })
+    .into_response()
+}
+
+/// Saves a tester's progress on a puzzle, replacing their previous save.
+async fn admin_save_test_solve_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+    Json(req): Json<AdminTestSolveRequest>,
+) -> impl IntoResponse {
+    let tester = req
+        .tester
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "admin".to_string());
+    if tester.chars().count() > MAX_TESTER_CHARS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("tester must be at most {MAX_TESTER_CHARS} characters"),
+        )
+            .into_response();
+    }
+    let grid = match grid_codec::decode(&req.grid, grid_codec::CLASSIC_SIZE) {
+        Ok(cells) => grid_codec::encode(&cells),
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+    let notes = req.notes.filter(|n| !n.trim().is_empty());
+    if notes
+        .as_ref()
+        .is_some_and(|n| n.chars().count() > MAX_TEST_NOTES_CHARS)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("notes must be at most {MAX_TEST_NOTES_CHARS} characters"),
+        )
+            .into_response();
+    }
+    let verdict = req.verdict;
+    if verdict
+        .as_deref()
+        .is_some_and(|v| v != "pass" && v != "fail")
+    {
+        return (StatusCode::BAD_REQUEST, "verdict must be pass or fail").into_response();
+    }
+
+    match store::puzzle_exists(&state.db, &date_utc).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
+        }
+    }
+
+    let solve = store::TestSolveRow {
+        tester,
+        grid,
+        notes,
+        verdict,
+        updated_at_utc: now_utc_string(),
+    };
+    match store::save_test_solve(&state.db, &date_utc, &solve).await {
+        Ok(()) => Json(AdminTestSolve {
+            tester: solve.tester,
+            grid: solve.grid,
+            notes: solve.notes,
+            verdict: solve.verdict,
+            updated_at_utc: solve.updated_at_utc,
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Checklist items from `MAKUDOKU_PUBLISH_CHECKLIST` the puzzle doesn't meet
+/// yet, or `None` if there is no such puzzle.
+async fn unmet_publish_checklist(
+    state: &AppState,
+    date_utc: &str,
+) -> Result<Option<Vec<String>>, String> {
+    let checklist = &state.config.publish_checklist;
+    if checklist.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+
+    let row = store::get_puzzle(&state.db, date_utc)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let mut unmet = Vec::new();
+    for item in checklist {
+        let met = match item.as_str() {
+            "unique" => {
+                let puzzle_json = row.puzzle_json.clone();
+                tokio::task::spawn_blocking(move || verify_puzzle_json(&puzzle_json))
+                    .await
+                    .map_err(|err| format!("Verify task failed: {err}"))?
+                    .is_ok()
+            }
+            "difficulty" => row.difficulty.is_some(),
+            "title" => row.title.as_deref().is_some_and(|t| !t.trim().is_empty()),
+            "test_solved" => store::has_passing_test_solve(&state.db, date_utc)
+                .await
+                .map_err(|e| format!("DB error: {e}"))?,
+            "rules" => parse_puzzle_json(&row.puzzle_json)
+                .is_ok_and(|parsed| !parsed.custom_rules.is_empty()),
+            _ => true,
+        };
+        if !met {
+            unmet.push(item.clone());
+        }
+    }
+    Ok(Some(unmet))
+}
+
+async fn admin_publish_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+    Query(query): Query<AdminPublishQuery>,
+) -> Response {
+    let unmet = match unmet_publish_checklist(&state, &date_utc).await {
+        Ok(Some(unmet)) => unmet,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    };
+    if !unmet.is_empty() && !query.force.unwrap_or(false) {
+        return (
+            StatusCode::CONFLICT,
+            Json(PublishChecklistResponse {
+                error: "Publish checklist incomplete",
+                unmet,
+            }),
+        )
+            .into_response();
+    }
+
+    let published_at = now_utc_string();
+    match store::publish_puzzle(&state.db, &date_utc, &published_at, false).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Ok(true) => {
+            state.today_cache.clear();
+            spawn_social_announcement(&state, &date_utc);
+            let mut response = admin_get_handler(State(state), Path(date_utc)).await;
+            // Published with `force` despite unmet items: report them.
+            let unmet = HeaderValue::from_str(&unmet.join(", "))
+                .ok()
+                .filter(|v| !v.is_empty());
+            if let Some(unmet) = unmet {
+                response.headers_mut().insert(UNMET_CHECKLIST_HEADER, unmet);
+            }
+            response
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
     }
-    Ok(out)
 }
 
-async fn check_puzzle_handler(
+/// Announces a freshly published puzzle in the background. Puzzles scheduled
+/// for a later date are left to the daily loop so they don't leak early.
+const MAX_SCHEDULE_DAYS: usize = 366;
+
+/// The publishing queue from today: what each upcoming day has and how many
+/// days are covered before the first gap.
+async fn admin_schedule_handler(
     State(state): State<AppState>,
-    Json(req): Json<CheckRequest>,
+    Query(query): Query<AdminScheduleQuery>,
 ) -> impl IntoResponse {
-    let grid = req.grid.trim().to_string();
-    if grid.chars().count() != NN {
+    let days = query.days.unwrap_or(14);
+    if days == 0 || days > MAX_SCHEDULE_DAYS {
         return (
             StatusCode::BAD_REQUEST,
-            "grid must be exactly 81 characters",
+            format!("days must be between 1 and {MAX_SCHEDULE_DAYS}"),
         )
             .into_response();
     }
 
-    let today = Utc::now().date_naive().to_string();
-    let row = sqlx::query!(
-        r#"
-        SELECT puzzle_json
-        FROM puzzles
-        WHERE date_utc = ? AND status = 'published'
-        "#,
-        today
-    )
-    .fetch_optional(&state.db)
-    .await;
-
-    let row = match row {
-        Ok(Some(row)) => row,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not published").into_response(),
+    let today = Utc::now().date_naive();
+    let today_value = today.to_string();
+    let rows = match store::puzzles_from(&state.db, &today_value).await {
+        Ok(rows) => rows,
         Err(e) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
-                .into_response();
-        }
-    };
-
-    let puzzle_json: serde_json::Value = match serde_json::from_str(&row.puzzle_json) {
-        Ok(val) => val,
-        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Invalid puzzle data",
+                format!("DB error: {e}"),
             )
                 .into_response();
         }
     };
+    let upcoming: HashMap<String, (String, Option<String>)> = rows
+        .into_iter()
+        .map(|row| (row.date_utc, (row.status, row.title)))
+        .collect();
 
-    let solution = match parse_solution_from_json(&puzzle_json) {
-        Ok(solution) => solution,
-        Err(_) => {
-            return Json(CheckResponse {
-                status: "unavailable".to_string(),
+    // Runway can reach past the listed days, so it walks every upcoming row.
+    let runway = |statuses: &[&str]| {
+        today
+            .iter_days()
+            .take_while(|date| {
+                upcoming
+                    .get(&date.to_string())
+                    .is_some_and(|(status, _)| statuses.contains(&status.as_str()))
             })
-            .into_response();
-        }
+            .count()
     };
+    let runway_days = runway(&["published"]);
+    let runway_days_with_drafts = runway(&["published", "draft"]);
 
-    let now_value = now_utc_string();
-    let _ = sqlx::query!(
-        r#"
-        INSERT INTO puzzle_stats (date_utc, checks, last_seen_utc)
-        VALUES (?, 1, ?)
-        ON CONFLICT(date_utc) DO UPDATE SET
-            checks = checks + 1,
-            last_seen_utc = excluded.last_seen_utc
-        "#,
-        today,
-        now_value,
-    )
-    .execute(&state.db)
-    .await;
-
-    let mut incomplete = false;
-    for (idx, ch) in grid.chars().enumerate() {
-        if ch == '.' || ch == '0' {
-            incomplete = true;
-            continue;
-        }
-        let digit = ch.to_digit(10);
-        let digit = match digit {
-            Some(d) if (1..=9).contains(&d) => d as u8,
-            _ => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    "grid must contain digits 1-9 or '.'",
-                )
-                    .into_response();
+    let days = today
+        .iter_days()
+        .take(days)
+        .map(|date| {
+            let date_utc = date.to_string();
+            let (state, name) = match upcoming.get(&date_utc) {
+                None => ("missing", None),
+                Some((status, title)) => (schedule_state(status, date, today), title.clone()),
+            };
+            ScheduleDay {
+                date_utc,
+                state,
+                name,
             }
-        };
-        if digit != solution[idx] {
-            return Json(CheckResponse {
-                status: "incorrect".to_string(),
-            })
-            .into_response();
-        }
-    }
+        })
+        .collect();
 
-    let status = if incomplete { "partial" } else { "complete" };
-    if status == "complete" {
-        let now_value = now_utc_string();
-        let _ = sqlx::query!(
-            r#"
-            INSERT INTO puzzle_stats (date_utc, solves, last_seen_utc)
-            VALUES (?, 1, ?)
-            ON CONFLICT(date_utc) DO UPDATE SET
-                solves = solves + 1,
-                last_seen_utc = excluded.last_seen_utc
-            "#,
-            today,
-            now_value,
-        )
-        .execute(&state.db)
-        .await;
-    }
-    Json(CheckResponse {
-        status: status.to_string(),
+    Json(AdminScheduleResponse {
+        today: today_value,
+        runway_days,
+        runway_days_with_drafts,
+        days,
     })
     .into_response()
 }
 
-async fn track_event_handler(
+/// How a puzzle on `date` shows in the schedule.
+fn schedule_state(status: &str, date: NaiveDate, today: NaiveDate) -> &'static str {
+    match status {
+        "published" if date > today => "scheduled",
+        "published" => "published",
+        "archived" => "archived",
+        _ => "draft",
+    }
+}
+
+/// Difficulty from which a day counts as hard.
+const HARD_DIFFICULTY: i64 = 7;
+/// Hard days in a row allowed before the curve report warns.
+const MAX_HARD_STREAK: usize = 2;
+/// A variant combination coming back within this many days is a repeat.
+const VARIANT_REPEAT_DAYS: usize = 7;
+
+/// The planned difficulty and variants of each day from `from` (today by
+/// default) to `to` (two weeks on), with warnings for runs of hard days
+/// and variant combinations repeated within a week.
+async fn admin_schedule_difficulty_handler(
     State(state): State<AppState>,
-    Json(req): Json<TrackRequest>,
+    Query(query): Query<AdminDifficultyCurveQuery>,
 ) -> impl IntoResponse {
-    let today = Utc::now().date_naive().to_string();
-    let now = now_utc_string();
-    let event = req.event.as_str();
-
-    let result = match event {
-        "view" => {
-            sqlx::query!(
-                r#"
-                INSERT INTO puzzle_stats (date_utc, views, last_seen_utc)
-                VALUES (?, 1, ?)
-                ON CONFLICT(date_utc) DO UPDATE SET
-                    views = views + 1,
-                    last_seen_utc = excluded.last_seen_utc
-                "#,
-                today,
-                now,
-            )
-            .execute(&state.db)
-            .await
-        }
-        _ => {
-            return (
-                StatusCode::BAD_REQUEST,
-                "event must be one of: view",
-            )
-                .into_response();
+    let today = Utc::now().date_naive();
+    let parse = |value: Option<&str>, name: &str| {
+        value
+            .map(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d"))
+            .transpose()
+            .map_err(|_| format!("{name} must be YYYY-MM-DD"))
+    };
+    let (from, to) = match (
+        parse(query.from.as_deref(), "from"),
+        parse(query.to.as_deref(), "to"),
+    ) {
+        (Ok(from), Ok(to)) => {
+            let from = from.unwrap_or(today);
+            (from, to.unwrap_or(from + chrono::Duration::days(13)))
         }
+        (Err(err), _) | (_, Err(err)) => return (StatusCode::BAD_REQUEST, err).into_response(),
     };
-
-    if let Err(e) = result {
+    if to < from || (to - from).num_days() >= MAX_SCHEDULE_DAYS as i64 {
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("DB error: {e}"),
+            StatusCode::BAD_REQUEST,
+            format!("to must be on or after from, and at most {MAX_SCHEDULE_DAYS} days on"),
         )
             .into_response();
     }
 
-    StatusCode::NO_CONTENT.into_response()
-}
-
-async fn admin_stats_handler(
-    State(state): State<AppState>,
-    Path(date_utc): Path<String>,
-) -> impl IntoResponse {
-    let row = sqlx::query!(
-        r#"
-        SELECT date_utc, views, checks, solves
-        FROM puzzle_stats
-        WHERE date_utc = ?
-        "#,
-        date_utc
-    )
-    .fetch_optional(&state.db)
-    .await;
-
-    let row = match row {
-        Ok(Some(row)) => row,
-        Ok(None) => {
-            return Json(StatsResponse {
-                date_utc,
-                views: 0,
-                checks: 0,
-                solves: 0,
-            })
-            .into_response();
-        }
+    let from_value = from.to_string();
+    let to_value = to.to_string();
+    let rows = match store::puzzles_between(&state.db, &from_value, &to_value).await {
+        Ok(rows) => rows,
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -485,741 +5771,941 @@ async fn admin_stats_handler(
                 .into_response();
         }
     };
+    let unrated: Vec<(String, String)> = rows
+        .iter()
+        .filter(|row| row.difficulty.is_none())
+        .map(|row| (row.date_utc.clone(), row.puzzle_json.clone()))
+        .collect();
+    let estimates = tokio::task::spawn_blocking(move || {
+        unrated
+            .into_iter()
+            .filter_map(|(date_utc, puzzle_json)| {
+                let rating = rate_puzzle_json(&puzzle_json).ok()?;
+                Some((date_utc, rating.difficulty))
+            })
+            .collect::<HashMap<String, i64>>()
+    })
+    .await;
+    let estimates = match estimates {
+        Ok(estimates) => estimates,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Rating task failed: {err}"),
+            )
+                .into_response();
+        }
+    };
+    let planned: HashMap<String, _> = rows
+        .into_iter()
+        .map(|row| (row.date_utc.clone(), row))
+        .collect();
 
-    Json(StatsResponse {
-        date_utc: row.date_utc.unwrap_or_default(),
-        views: row.views,
-        checks: row.checks,
-        solves: row.solves,
+    let days: Vec<DifficultyDay> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| {
+            let date_utc = date.to_string();
+            let Some(row) = planned.get(&date_utc) else {
+                return DifficultyDay {
+                    date_utc,
+                    state: "missing",
+                    difficulty: None,
+                    estimated: false,
+                    variants: Vec::new(),
+                };
+            };
+            let estimate = estimates.get(&date_utc).copied();
+            let mut variants = row
+                .variants
+                .as_deref()
+                .and_then(|v| serde_json::from_str::<Vec<String>>(v).ok())
+                .unwrap_or_default();
+            variants.sort();
+            DifficultyDay {
+                date_utc,
+                state: schedule_state(&row.status, date, today),
+                difficulty: row.difficulty.or(estimate),
+                estimated: estimate.is_some(),
+                variants,
+            }
+        })
+        .collect();
+
+    Json(AdminDifficultyCurveResponse {
+        from: from_value,
+        to: to_value,
+        warnings: difficulty_curve_warnings(&days),
+        days,
     })
     .into_response()
 }
 
-fn now_utc_string() -> String {
-    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
-}
+/// Days are consecutive, so positions in `days` count days apart.
+fn difficulty_curve_warnings(days: &[DifficultyDay]) -> Vec<CurveWarning> {
+    let mut warnings = Vec::new();
 
-fn dedupe_variants(input: Vec<String>) -> Vec<String> {
-    let mut seen = HashSet::new();
-    let mut out = Vec::new();
-    for item in input {
-        if seen.insert(item.clone()) {
-            out.push(item);
+    let is_hard = |day: &DifficultyDay| day.difficulty.is_some_and(|d| d >= HARD_DIFFICULTY);
+    let mut start = 0;
+    while start < days.len() {
+        let len = days[start..].iter().take_while(|day| is_hard(day)).count();
+        if len > MAX_HARD_STREAK {
+            let streak = &days[start..start + len];
+            warnings.push(CurveWarning {
+                kind: "hard_streak",
+                dates: streak.iter().map(|day| day.date_utc.clone()).collect(),
+                message: format!(
+                    "{len} hard days in a row from {} to {}",
+                    streak[0].date_utc,
+                    streak[len - 1].date_utc
+                ),
+            });
         }
+        start += len.max(1);
     }
-    out
-}
-
-fn parse_puzzle_json(puzzle_json: &str) -> Result<ParsedPuzzleJson, String> {
-    let value: serde_json::Value =
-        serde_json::from_str(puzzle_json).map_err(|e| format!("invalid JSON: {e}"))?;
-    let puzzle = value
-        .get("puzzle")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "missing puzzle string".to_string())?
-        .to_string();
-
-    let constraints = value
-        .get("constraints")
-        .and_then(|v| v.as_array())
-        .map(|v| v.to_vec())
-        .unwrap_or_default();
-
-    Ok(ParsedPuzzleJson { puzzle, constraints })
-}
 
-fn variants_from_constraints(constraints: &[serde_json::Value]) -> Result<Vec<String>, String> {
-    let mut out = Vec::new();
-    for item in constraints {
-        let kind = item
-            .get("type")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "constraint missing type".to_string())?;
-        out.push(kind.to_string());
+    let mut last_seen: HashMap<&[String], usize> = HashMap::new();
+    for (index, day) in days.iter().enumerate() {
+        if day.variants.is_empty() {
+            continue;
+        }
+        let repeat = last_seen
+            .insert(&day.variants, index)
+            .filter(|earlier| index - earlier < VARIANT_REPEAT_DAYS);
+        if let Some(earlier) = repeat {
+            let earlier = &days[earlier].date_utc;
+            warnings.push(CurveWarning {
+                kind: "repeated_variants",
+                dates: vec![earlier.clone(), day.date_utc.clone()],
+                message: format!(
+                    "{} on {earlier} is repeated on {}",
+                    day.variants.join(" + "),
+                    day.date_utc
+                ),
+            });
+        }
     }
-    Ok(dedupe_variants(out))
-}
 
-fn parse_cell(value: &serde_json::Value) -> Result<(usize, usize), String> {
-    let arr = value
-        .as_array()
-        .ok_or_else(|| "cell must be a [row, col] array".to_string())?;
-    if arr.len() != 2 {
-        return Err("cell must have two elements".to_string());
-    }
-    let r = arr[0]
-        .as_u64()
-        .ok_or_else(|| "row must be an integer".to_string())? as usize;
-    let c = arr[1]
-        .as_u64()
-        .ok_or_else(|| "col must be an integer".to_string())? as usize;
-    Ok((r, c))
+    warnings
 }
 
-fn parse_path(value: &serde_json::Value) -> Result<Vec<(usize, usize)>, String> {
-    let arr = value
-        .as_array()
-        .ok_or_else(|| "path must be an array of cells".to_string())?;
-    let mut out = Vec::with_capacity(arr.len());
-    for cell in arr {
-        out.push(parse_cell(cell)?);
-    }
-    if out.is_empty() {
-        return Err("path must have at least one cell".to_string());
+/// Runs queued jobs one at a time, looking for new ones every two seconds.
+/// Jobs a previous run left half done are started over.
+async fn run_job_worker(state: AppState) {
+    match jobs::requeue_interrupted(&state.db).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("requeued {count} interrupted job(s)"),
+        Err(e) => tracing::warn!("requeueing interrupted jobs failed: {e}"),
     }
-    Ok(out)
-}
-
-fn constraints_from_json(
-    constraints: &[serde_json::Value],
-) -> Result<Vec<VariantSpec>, String> {
-    let mut out = Vec::new();
-    for item in constraints {
-        let kind = item
-            .get("type")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "constraint missing type".to_string())?;
-        match kind {
-            "kropki_white" => {
-                let a = parse_cell(
-                    item.get("a")
-                        .ok_or_else(|| "kropki_white missing a".to_string())?,
-                )?;
-                let b = parse_cell(
-                    item.get("b")
-                        .ok_or_else(|| "kropki_white missing b".to_string())?,
-                )?;
-                out.push(VariantSpec::KropkiWhite(a, b));
-            }
-            "kropki_black" => {
-                let a = parse_cell(
-                    item.get("a")
-                        .ok_or_else(|| "kropki_black missing a".to_string())?,
-                )?;
-                let b = parse_cell(
-                    item.get("b")
-                        .ok_or_else(|| "kropki_black missing b".to_string())?,
-                )?;
-                out.push(VariantSpec::KropkiBlack(a, b));
-            }
-            "thermo" => {
-                let path = parse_path(
-                    item.get("path")
-                        .ok_or_else(|| "thermo missing path".to_string())?,
-                )?;
-                out.push(VariantSpec::Thermo(path));
-            }
-            "arrow" => {
-                let path = parse_path(
-                    item.get("path")
-                        .ok_or_else(|| "arrow missing path".to_string())?,
-                )?;
-                out.push(VariantSpec::Arrow(path));
-            }
-            "killer" => {
-                let cells = parse_path(
-                    item.get("cells")
-                        .ok_or_else(|| "killer missing cells".to_string())?,
-                )?;
-                let sum = item
-                    .get("sum")
-                    .and_then(|v| v.as_u64())
-                    .ok_or_else(|| "killer missing sum".to_string())?;
-                let no_repeats = item
-                    .get("no_repeats")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
-                out.push(VariantSpec::Killer {
-                    cells,
-                    sum: sum as u8,
-                    no_repeats,
-                });
+    let mut ticker = tokio::time::interval(Duration::from_secs(2));
+    loop {
+        ticker.tick().await;
+        loop {
+            let job = match jobs::claim_next(&state.db).await {
+                Ok(Some(job)) => job,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("claiming a job failed: {e}");
+                    break;
+                }
+            };
+            let running = state.job_progress.start(&job.id);
+            // A cancel between claiming and registering only reached the table.
+            if jobs::get(&state.db, &job.id)
+                .await
+                .is_ok_and(|row| row.is_some_and(|row| row.cancelled_at_utc.is_some()))
+            {
+                running.cancel();
             }
-            "king" => out.push(VariantSpec::King),
-            "knight" => out.push(VariantSpec::Knight),
-            "queen" => out.push(VariantSpec::Queen),
-            other => return Err(format!("unknown constraint type: {other}")),
+            let outcome = run_job(&state, &job, running).await;
+            if let Err(e) = &outcome {
+                tracing::warn!("job {} ({}) failed: {e}", job.id, job.kind);
+            }
+            if let Err(e) = jobs::finish(&state.db, &job.id, outcome).await {
+                tracing::warn!("recording job {} failed: {e}", job.id);
+            }
+            // Watchers re-read the job once this closes the progress channel.
+            state.job_progress.remove(&job.id);
         }
     }
-    Ok(out)
 }
 
-fn normalize_constraints_input(
-    input: serde_json::Value,
-) -> Result<Vec<serde_json::Value>, String> {
-    if let Some(arr) = input.as_array() {
-        return Ok(arr.to_vec());
-    }
-    if let Some(arr) = input.get("constraints").and_then(|v| v.as_array()) {
-        return Ok(arr.to_vec());
+/// Runs one job, returning its result as JSON.
+async fn run_job(
+    state: &AppState,
+    job: &jobs::ClaimedJob,
+    running: Arc<RunningJob>,
+) -> Result<String, String> {
+    match job.kind.as_str() {
+        jobs::KIND_GENERATE => {
+            let req: AdminGenerateCustomRequest = serde_json::from_str(&job.request_json)
+                .map_err(|e| format!("Invalid job request: {e}"))?;
+            let config = state.config.clone();
+            let response =
+                tokio::task::spawn_blocking(move || generate_custom(req, &config, Some(&running)))
+                    .await
+                    .map_err(|e| format!("Generator task failed: {e}"))??;
+            serde_json::to_string(&response).map_err(|e| e.to_string())
+        }
+        kind => Err(format!("Unknown job kind: {kind}")),
     }
-    Err("constraints must be a JSON array".to_string())
 }
 
-fn apply_variant_specs(engine: &mut Engine, specs: &[VariantSpec]) {
-    for spec in specs {
-        match spec {
-            VariantSpec::KropkiWhite(a, b) => add_kropki_white(engine, *a, *b),
-            VariantSpec::KropkiBlack(a, b) => add_kropki_black(engine, *a, *b),
-            VariantSpec::Thermo(path) => add_thermo(engine, path),
-            VariantSpec::Arrow(path) => add_arrow(engine, path),
-            VariantSpec::Killer {
-                cells,
-                sum,
-                no_repeats,
-            } => add_killer_cage(engine, cells, *sum, *no_repeats),
-            VariantSpec::King => add_king_constraints(engine),
-            VariantSpec::Knight => add_knight_constraints(engine),
-            VariantSpec::Queen => add_queen_constraints(engine),
+/// Publishes the draft dated today once the UTC day starts, so the daily
+/// puzzle doesn't depend on someone remembering to publish it. Runs at
+/// startup too, catching a midnight missed while the server was down.
+/// Drafts with unmet checklist items are left alone.
+async fn run_auto_publish_loop(state: AppState) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(60));
+    let mut done_for: Option<String> = None;
+    loop {
+        ticker.tick().await;
+        let today = Utc::now().date_naive().to_string();
+        if done_for.as_deref() == Some(today.as_str()) {
+            continue;
+        }
+        match auto_publish(&state, &today).await {
+            Ok(()) => done_for = Some(today),
+            Err(err) => tracing::warn!("auto-publish of {today} failed: {err}"),
         }
     }
 }
 
-fn engine_constraints_from_specs(specs: &[VariantSpec]) -> Vec<Constraint> {
-    let mut eng = Engine::new();
-    add_all_sudoku_constraints(&mut eng);
-    apply_variant_specs(&mut eng, specs);
-    eng.constraints
+async fn auto_publish(state: &AppState, today: &str) -> Result<(), String> {
+    let status = store::puzzle_status(&state.db, today)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?;
+    if status.as_deref() != Some("draft") {
+        return Ok(());
+    }
+
+    let unmet = unmet_publish_checklist(state, today)
+        .await?
+        .unwrap_or_default();
+    if !unmet.is_empty() {
+        tracing::warn!(
+            "not auto-publishing {today}, checklist items unmet: {}",
+            unmet.join(", ")
+        );
+        return Ok(());
+    }
+
+    let published_at = now_utc_string();
+    let published = store::publish_puzzle(&state.db, today, &published_at, true)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?;
+    if published {
+        tracing::info!("auto-published {today}");
+        state.today_cache.clear();
+        spawn_social_announcement(state, today);
+    }
+    Ok(())
 }
 
-fn variant_specs_to_json(specs: &[VariantSpec]) -> Vec<serde_json::Value> {
-    specs
-        .iter()
-        .map(|spec| match spec {
-            VariantSpec::KropkiWhite(a, b) => serde_json::json!({
-                "type": "kropki_white",
-                "a": [a.0, a.1],
-                "b": [b.0, b.1],
-            }),
-            VariantSpec::KropkiBlack(a, b) => serde_json::json!({
-                "type": "kropki_black",
-                "a": [a.0, a.1],
-                "b": [b.0, b.1],
-            }),
-            VariantSpec::Thermo(path) => serde_json::json!({
-                "type": "thermo",
-                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
-            }),
-            VariantSpec::Arrow(path) => serde_json::json!({
-                "type": "arrow",
-                "path": path.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
-            }),
-            VariantSpec::Killer {
-                cells,
-                sum,
-                no_repeats,
-            } => serde_json::json!({
-                "type": "killer",
-                "cells": cells.iter().map(|(r, c)| serde_json::json!([r, c])).collect::<Vec<_>>(),
-                "sum": sum,
-                "no_repeats": no_repeats,
-            }),
-            VariantSpec::King => serde_json::json!({ "type": "king" }),
-            VariantSpec::Knight => serde_json::json!({ "type": "knight" }),
-            VariantSpec::Queen => serde_json::json!({ "type": "queen" }),
-        })
-        .collect()
+fn spawn_social_announcement(state: &AppState, date_utc: &str) {
+    if date_utc > Utc::now().date_naive().to_string().as_str() {
+        return;
+    }
+    let db = state.db.clone();
+    let config = state.config.clone();
+    let date_utc = date_utc.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = social::announce(&db, &config, &date_utc).await {
+            tracing::warn!("social announcement for {date_utc} failed: {e:#}");
+        }
+    });
 }
 
-async fn admin_generate_handler() -> impl IntoResponse {
-    let cfg = GenerationConfig::default();
-    let render_options = RenderOptions::default();
+#[derive(Serialize)]
+struct SocialPreviewPost {
+    network: &'static str,
+    enabled: bool,
+    text: String,
+}
 
-    let result = tokio::task::spawn_blocking(move || {
-        let puzzle = generate_random_variant_puzzle(cfg)?;
-        let puzzle_svg =
-            render_puzzle_svg(&puzzle.puzzle, &puzzle.engine.constraints, render_options)?;
-        let variants = variant_kinds(&puzzle.constraints);
-        let constraints_json = variant_specs_to_json(&puzzle.constraints);
-        let puzzle_json = serde_json::json!({
-            "puzzle": puzzle.puzzle,
-            "solution": puzzle.solution.to_vec(),
-            "constraints": constraints_json,
-            "seed": puzzle.seed,
-            "clue_count": puzzle.clue_count,
-            "symmetry": puzzle.symmetry.map(|s| format!("{s:?}")),
-        });
-        Ok::<_, String>((puzzle_svg, variants, puzzle_json.to_string()))
-    })
-    .await;
+#[derive(Serialize)]
+struct SocialPreviewResponse {
+    date_utc: String,
+    posts: Vec<SocialPreviewPost>,
+    /// PNG attached to the posts, as a data URL.
+    image: String,
+}
 
-    let result = match result {
-        Ok(result) => result,
-        Err(err) => {
+async fn admin_social_preview_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> impl IntoResponse {
+    let puzzle = match social::Announcement::load(&state.db, &date_utc).await {
+        Ok(Some(puzzle)) => puzzle,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Generator task failed: {err}"),
+                format!("DB error: {e}"),
             )
                 .into_response();
         }
     };
 
-    let (puzzle_svg, variants, puzzle_json) = match result {
-        Ok(result) => result,
+    let posts = social::Network::ALL
+        .into_iter()
+        .map(|network| SocialPreviewPost {
+            network: network.name(),
+            enabled: network.is_configured(&state.config),
+            text: puzzle.text(&state.config, network),
+        })
+        .collect();
+
+    let svg = puzzle.card_svg();
+    let png =
+        tokio::task::spawn_blocking(move || social::render_png(&svg, social::IMAGE_WIDTH)).await;
+    let png = match png {
+        Ok(Ok(png)) => png,
+        Ok(Err(err)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Failed to render image: {err:#}"),
+            )
+                .into_response();
+        }
         Err(err) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to generate puzzle: {err}"),
+                format!("Render task failed: {err}"),
             )
                 .into_response();
         }
     };
 
-    Json(AdminGenerateResponse {
-        puzzle_json,
-        svg: puzzle_svg,
-        variants,
+    Json(SocialPreviewResponse {
+        date_utc,
+        posts,
+        image: format!("data:image/png;base64,{}", BASE64.encode(png)),
     })
     .into_response()
 }
 
-fn puzzle_vec_to_string(puzzle: &[Option<u8>]) -> String {
-    let mut s = String::with_capacity(NN);
-    for cell in puzzle.iter() {
-        match cell {
-            Some(d) => s.push((b'0' + *d) as char),
-            None => s.push('.'),
+async fn admin_archive_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> Response {
+    match store::archive_puzzle(&state.db, &date_utc).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Ok(true) => {
+            state.today_cache.clear();
+            admin_get_handler(State(state), Path(date_utc)).await
         }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
     }
-    s
 }
 
-fn has_unique_solution_with_specs(
-    puzzle: &str,
-    specs: &[VariantSpec],
-    rng: &mut SimpleRng,
-) -> bool {
-    let mut eng = Engine::new();
-    add_all_sudoku_constraints(&mut eng);
-    apply_variant_specs(&mut eng, specs);
-    if eng.load_givens(puzzle).is_err() {
-        return false;
+/// Soft-deletes a puzzle: it disappears from public endpoints and the admin
+/// list, but keeps its data until restored or overwritten. Deleting twice
+/// keeps the first deletion time.
+async fn admin_delete_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> Response {
+    let deleted_at = now_utc_string();
+    match store::delete_puzzle(&state.db, &date_utc, &deleted_at).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Ok(true) => {
+            state.today_cache.clear();
+            admin_get_handler(State(state), Path(date_utc)).await
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
     }
-    eng.has_unique_solution_with_rng(rng)
 }
 
-fn shuffle_indices(rng: &mut SimpleRng, positions: &mut [usize]) {
-    if positions.len() <= 1 {
-        return;
-    }
-    for i in (1..positions.len()).rev() {
-        let j = rng.gen_range(0..i + 1);
-        positions.swap(i, j);
+/// Undoes a soft delete, leaving the status as it was.
+async fn admin_restore_deleted_handler(
+    State(state): State<AppState>,
+    Path(date_utc): Path<String>,
+) -> Response {
+    match store::undelete_puzzle(&state.db, &date_utc).await {
+        Ok(false) => (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
+        Ok(true) => {
+            state.today_cache.clear();
+            admin_get_handler(State(state), Path(date_utc)).await
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
     }
 }
 
-fn generate_puzzle_from_solution(
-    solution: &[u8; NN],
-    target_clues: usize,
-    specs: &[VariantSpec],
-    rng: &mut SimpleRng,
-) -> Result<String, String> {
-    if target_clues >= NN {
-        return Err("clue_target must be less than 81".to_string());
-    }
-
-    let mut puzzle: Vec<Option<u8>> = solution.iter().copied().map(Some).collect();
-    let mut positions: Vec<usize> = (0..NN).collect();
-    shuffle_indices(rng, &mut positions);
+async fn admin_reverify_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminReverifyQuery>,
+) -> impl IntoResponse {
+    let all = query.all.unwrap_or(false);
+    let dry_run = query.dry_run.unwrap_or(false);
 
-    for pos in positions {
-        let saved = puzzle[pos];
-        puzzle[pos] = None;
-        let puzzle_str = puzzle_vec_to_string(&puzzle);
-        if !has_unique_solution_with_specs(&puzzle_str, specs, rng) {
-            puzzle[pos] = saved;
-        }
-        let clues_now = puzzle.iter().filter(|c| c.is_some()).count();
-        if clues_now <= target_clues {
-            break;
+    let rows = match store::puzzles_to_reverify(&state.db, all, ENGINE_VERSION).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {e}"),
+            )
+                .into_response();
         }
-    }
+    };
 
-    Ok(puzzle_vec_to_string(&puzzle))
-}
+    let mut items = Vec::with_capacity(rows.len());
+    for row in rows {
+        let date_utc = row.date_utc;
+        let puzzle_json = row.puzzle_json;
+        let result = tokio::task::spawn_blocking(move || verify_puzzle_json(&puzzle_json))
+            .await
+            .unwrap_or_else(|err| Err(format!("Verify task failed: {err}")));
 
-async fn admin_generate_custom_handler(
-    Json(req): Json<AdminGenerateCustomRequest>,
-) -> impl IntoResponse {
-    let result = tokio::task::spawn_blocking(move || {
-        let constraints = normalize_constraints_input(req.constraints)?;
-        let specs = constraints_from_json(&constraints)?;
+        if result.is_ok() && !dry_run {
+            if let Err(e) = store::set_engine_version(&state.db, &date_utc, ENGINE_VERSION).await {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB error: {e}"),
+                )
+                    .into_response();
+            }
+        }
 
-        let mut rng = match req.seed {
-            Some(seed) => SimpleRng::from_seed(seed),
-            None => SimpleRng::new(),
-        };
-        let seed = req.seed.unwrap_or_else(|| rng.seed());
+        items.push(AdminReverifyItem {
+            date_utc,
+            engine_version: row.engine_version,
+            ok: result.is_ok(),
+            error: result.err(),
+        });
+    }
 
-        let solution = generate_full_solution_with(rng.clone(), |eng| {
-            apply_variant_specs(eng, &specs);
-        })?;
+    let failed = items.iter().filter(|item| !item.ok).count();
+    Json(AdminReverifyResponse {
+        engine_version: ENGINE_VERSION.to_string(),
+        dry_run,
+        checked: items.len(),
+        failed,
+        items,
+    })
+    .into_response()
+}
 
-        let clue_target = req.clue_target.unwrap_or(30);
-        let puzzle = generate_puzzle_from_solution(&solution, clue_target, &specs, &mut rng)?;
+fn rate_puzzle_json(puzzle_json: &str) -> Result<difficulty::Rating, String> {
+    let parsed = parse_puzzle_json(puzzle_json)?;
+    difficulty::rate(
+        &parsed.puzzle,
+        &parsed.constraints,
+        parsed.regions.as_deref(),
+    )
+}
 
-        let constraints_json = constraints;
-        let variants = variant_kinds(&specs);
-        let clue_count = puzzle.chars().filter(|c| *c != '.').count();
+/// Rates every puzzle that has no difficulty yet, writing the results
+/// unless `dry_run` is set. Shared by the admin endpoint and the
+/// `backfill-difficulty` command.
+async fn backfill_difficulty(
+    db: &SqlitePool,
+    dry_run: bool,
+) -> Result<Vec<DifficultyBackfillItem>, sqlx::Error> {
+    let rows = store::unrated_puzzles(db).await?;
 
-        let puzzle_json = serde_json::json!({
-            "puzzle": puzzle,
-            "solution": solution.to_vec(),
-            "constraints": constraints_json,
-            "seed": seed,
-            "clue_count": clue_count,
-            "symmetry": null,
-        });
+    // One blocking task for the whole batch rather than one per puzzle.
+    let (dates, puzzle_jsons): (Vec<String>, Vec<String>) = rows
+        .into_iter()
+        .map(|row| (row.date_utc, row.puzzle_json))
+        .unzip();
+    let count = puzzle_jsons.len();
+    let ratings = tokio::task::spawn_blocking(move || {
+        puzzle_jsons
+            .iter()
+            .map(|puzzle_json| rate_puzzle_json(puzzle_json).map(|rating| rating.difficulty))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_else(|err| vec![Err(format!("Rating task failed: {err}")); count]);
 
-        let render_options = RenderOptions::default();
-        let constraints_render = engine_constraints_from_specs(&specs);
-        let puzzle_svg = render_puzzle_svg(&puzzle, &constraints_render, render_options)?;
+    let mut items = Vec::with_capacity(dates.len());
+    for (date_utc, rating) in dates.into_iter().zip(ratings) {
+        match rating {
+            Ok(difficulty) => {
+                if !dry_run {
+                    store::set_missing_difficulty(db, &date_utc, difficulty).await?;
+                }
+                items.push(DifficultyBackfillItem {
+                    date_utc,
+                    difficulty: Some(difficulty),
+                    error: None,
+                });
+            }
+            Err(err) => items.push(DifficultyBackfillItem {
+                date_utc,
+                difficulty: None,
+                error: Some(err),
+            }),
+        }
+    }
+    Ok(items)
+}
 
-        Ok::<_, String>((puzzle_svg, variants, puzzle_json.to_string()))
-    })
-    .await;
+async fn admin_backfill_difficulty_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> impl IntoResponse {
+    let dry_run = query.dry_run.unwrap_or(false);
+    match backfill_difficulty(&state.db, dry_run).await {
+        Ok(items) => Json(DifficultyBackfillResponse { dry_run, items }).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {e}"),
+        )
+            .into_response(),
+    }
+}
 
-    let result = match result {
-        Ok(result) => result,
-        Err(err) => {
+async fn admin_weekly_digest_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> impl IntoResponse {
+    let dry_run = query.dry_run.unwrap_or(false);
+    let today = Utc::now().date_naive();
+    let digest = match digest::compose(&state.db, today).await {
+        Ok(digest) => digest,
+        Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Generator task failed: {err}"),
+                format!("DB error: {e}"),
             )
                 .into_response();
         }
     };
 
-    let (puzzle_svg, variants, puzzle_json) = match result {
-        Ok(result) => result,
-        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    let sent = if dry_run {
+        Ok(())
+    } else {
+        digest::send(&state.config, &digest).await
     };
+    if let Err(e) = sent {
+        return (
+            StatusCode::BAD_GATEWAY,
+            format!("Failed to send digest: {e:#}"),
+        )
+            .into_response();
+    }
 
-    Json(AdminGenerateResponse {
-        puzzle_json,
-        svg: puzzle_svg,
-        variants,
+    Json(AdminDigestResponse {
+        dry_run,
+        subject: digest.subject(),
+        text: digest.text(),
+        digest,
     })
     .into_response()
 }
 
-async fn admin_create_handler(
-    State(state): State<AppState>,
-    Json(req): Json<AdminCreateRequest>,
-) -> Response {
-    let AdminCreateRequest {
-        date_utc,
-        puzzle_json,
-        svg,
-        variants,
-        status,
-        name,
-        author,
-        difficulty,
-        overwrite,
-    } = req;
+/// Most puzzles a single classic import may contain.
+const MAX_IMPORT_PUZZLES: usize = 1000;
 
-    let overwrite = overwrite.unwrap_or(true);
-    if !overwrite {
-        let date_utc_value = date_utc.clone();
-        let existing = sqlx::query!(
-            r#"SELECT date_utc FROM puzzles WHERE date_utc = ?"#,
-            date_utc_value
-        )
-        .fetch_optional(&state.db)
-        .await;
-        match existing {
-            Ok(Some(_)) => {
-                return (StatusCode::CONFLICT, "Puzzle already exists").into_response();
-            }
-            Ok(None) => {}
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("DB error: {e}"),
-                )
-                    .into_response();
-            }
-        }
+/// A classic puzzle line split into its grid and optional difficulty.
+fn parse_classic_line(line: &str) -> Result<(String, Option<i64>), String> {
+    let mut parts = line.split(|c: char| c.is_whitespace() || c == ',' || c == ';');
+    let puzzle = parts.next().unwrap_or_default();
+    if puzzle.chars().count() != NN {
+        return Err("puzzle must be exactly 81 characters".to_string());
     }
+    if !puzzle.chars().all(|c| c == '.' || c.is_ascii_digit()) {
+        return Err("puzzle must contain digits 1-9 or '.'".to_string());
+    }
+    let puzzle = puzzle.replace('0', ".");
 
-    let parsed = match parse_puzzle_json(&puzzle_json) {
-        Ok(parsed) => parsed,
-        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
-    };
-
-    let variants = match &variants {
-        Some(list) => dedupe_variants(list.clone()),
-        None => match variants_from_constraints(&parsed.constraints) {
-            Ok(list) => list,
-            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    let difficulty = match parts.find(|p| !p.is_empty()) {
+        None => None,
+        Some(value) => match value.parse::<i64>() {
+            Ok(d) if (1..=10).contains(&d) => Some(d),
+            _ => return Err(format!("difficulty must be 1-10, got {value}")),
         },
     };
+    Ok((puzzle, difficulty))
+}
 
-    let svg = if let Some(svg) = svg {
-        Some(svg)
-    } else {
-        let specs = match constraints_from_json(&parsed.constraints) {
-            Ok(specs) => specs,
-            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
-        };
-        let constraints = engine_constraints_from_specs(&specs);
-        let render_options = RenderOptions::default();
-        match render_puzzle_svg(&parsed.puzzle, &constraints, render_options) {
-            Ok(svg) => Some(svg),
-            Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
-        }
-    };
+/// Solves and, unless `render` is false, renders a classic puzzle, returning
+/// its puzzle_json and SVG.
+fn build_classic_puzzle(
+    puzzle: &str,
+    render: bool,
+) -> Result<(String, Option<String>, usize), String> {
+    let mut rng = SimpleRng::new();
+    if !has_unique_solution_with_specs(puzzle, &[], None, &mut rng) {
+        return Err("puzzle does not have a unique solution".to_string());
+    }
+    let solution = generate_full_solution_with(rng, |eng| {
+        let _ = eng.load_givens(puzzle);
+    })?;
 
-    let status = status.unwrap_or_else(|| "draft".to_string());
-    let published_at = if status == "published" {
-        Some(now_utc_string())
+    let clue_count = puzzle.chars().filter(|c| *c != '.').count();
+    let puzzle_json = serde_json::json!({
+        "puzzle": puzzle,
+        "solution": solution.to_vec(),
+        "constraints": [],
+        "seed": null,
+        "clue_count": clue_count,
+        "symmetry": null,
+        "engine_version": ENGINE_VERSION,
+    });
+    let svg = if render {
+        let constraints = engine_constraints_from_specs(&[], None);
+        Some(render_puzzle_svg(puzzle, &constraints, RenderOptions::default())?)
     } else {
         None
     };
+    Ok((puzzle_json.to_string(), svg, clue_count))
+}
 
-    let variants_json = match serde_json::to_string(&variants) {
-        Ok(v) => v,
-        Err(e) => {
+async fn admin_import_classic_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AdminImportClassicRequest>,
+) -> impl IntoResponse {
+    let start_date = match NaiveDate::parse_from_str(&req.start_date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => {
+            return (StatusCode::BAD_REQUEST, "start_date must be YYYY-MM-DD").into_response();
+        }
+    };
+    let status = req.status.unwrap_or_else(|| "draft".to_string());
+    if !matches!(status.as_str(), "draft" | "published" | "archived") {
+        return (
+            StatusCode::BAD_REQUEST,
+            "status must be draft, published or archived",
+        )
+            .into_response();
+    }
+    let overwrite = req.overwrite.unwrap_or(false);
+    let render = !req.defer_render.unwrap_or(false);
+
+    let lines: Vec<(usize, String)> = req
+        .text
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim().to_string()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if lines.len() > MAX_IMPORT_PUZZLES {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_IMPORT_PUZZLES} puzzles per import"),
+        )
+            .into_response();
+    }
+
+    let built = tokio::task::spawn_blocking(move || {
+        lines
+            .into_iter()
+            .map(|(line_no, line)| {
+                let result = parse_classic_line(&line).and_then(|(puzzle, difficulty)| {
+                    let (puzzle_json, svg, clue_count) = build_classic_puzzle(&puzzle, render)?;
+                    let difficulty = match difficulty {
+                        Some(difficulty) => difficulty,
+                        None => difficulty::rate(&puzzle, &[], None)?.difficulty,
+                    };
+                    Ok((puzzle_json, svg, difficulty))
+                });
+                (line_no, result)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+
+    let built = match built {
+        Ok(built) => built,
+        Err(err) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to encode variants: {e}"),
+                format!("Import task failed: {err}"),
             )
                 .into_response();
         }
     };
 
-    let date_utc_value = date_utc.clone();
-    let result = sqlx::query!(
-        r#"
-        INSERT INTO puzzles (
-            date_utc, status, puzzle_json, svg, render_version,
-            title, author, difficulty, variants, published_at_utc
-        )
-        VALUES (?, ?, ?, ?, 1, ?, ?, ?, ?, ?)
-        ON CONFLICT(date_utc) DO UPDATE SET
-            status = excluded.status,
-            puzzle_json = excluded.puzzle_json,
-            svg = excluded.svg,
-            render_version = excluded.render_version,
-            title = excluded.title,
-            author = excluded.author,
-            difficulty = excluded.difficulty,
-            variants = excluded.variants,
-            published_at_utc = excluded.published_at_utc
-        "#,
-        date_utc_value,
-        status,
-        puzzle_json,
-        svg,
-        name,
-        author,
-        difficulty,
-        variants_json,
-        published_at,
-    )
-    .execute(&state.db)
-    .await;
+    let published_at = (status == "published").then(now_utc_string);
+    let variants_json = "[]";
+    let mut items = Vec::with_capacity(built.len());
+    let mut date = start_date;
+    for (line, result) in built {
+        let (puzzle_json, svg, difficulty) = match result {
+            Ok(built) => built,
+            Err(err) => {
+                items.push(AdminImportItem {
+                    line,
+                    date_utc: None,
+                    ok: false,
+                    error: Some(err),
+                });
+                continue;
+            }
+        };
 
-    if let Err(e) = result {
+        // Dates are only used up by puzzles that parsed and solved.
+        let date_utc = date.to_string();
+        date = date.succ_opt().unwrap_or(date);
+
+        let write = store::PuzzleWrite {
+            date_utc: &date_utc,
+            status: &status,
+            puzzle_json: &puzzle_json,
+            svg: svg.as_deref(),
+            title: None,
+            author: req.author.as_deref(),
+            difficulty: Some(difficulty),
+            variants_json,
+            published_at_utc: published_at.as_deref(),
+            // `build_classic_puzzle` checked uniqueness on this engine.
+            engine_version: Some(ENGINE_VERSION),
+            extra_json: None,
+        };
+        let error = match store::upsert_puzzle(&state.db, &write, overwrite).await {
+            Ok(false) => Some("a puzzle already exists for this date".to_string()),
+            Ok(true) => None,
+            Err(e) => Some(format!("DB error: {e}")),
+        };
+        items.push(AdminImportItem {
+            line,
+            date_utc: Some(date_utc),
+            ok: error.is_none(),
+            error,
+        });
+    }
+
+    state.today_cache.clear();
+    Json(AdminImportResponse {
+        imported: items.iter().filter(|item| item.ok).count(),
+        items,
+    })
+    .into_response()
+}
+
+/// Bulk import bodies are larger than the default limit, holding up to
+/// `MAX_IMPORT_PUZZLES` records with their SVGs.
+const MAX_BULK_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// NDJSON body with one `AdminCreateRequest` per line; each record is stored
+/// as by the create endpoint and reported separately.
+async fn admin_import_bulk_handler(State(state): State<AppState>, body: String) -> Response {
+    let lines: Vec<(usize, &str)> = body
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    if lines.len() > MAX_IMPORT_PUZZLES {
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("DB error: {e}"),
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_IMPORT_PUZZLES} puzzles per import"),
         )
             .into_response();
     }
 
-    admin_get_handler(State(state), Path(date_utc)).await
+    let mut items = Vec::with_capacity(lines.len());
+    for (line, record) in lines {
+        let req = match serde_json::from_str::<AdminCreateRequest>(record) {
+            Ok(req) => req,
+            Err(e) => {
+                items.push(AdminImportItem {
+                    line,
+                    date_utc: None,
+                    ok: false,
+                    error: Some(format!("invalid record: {e}")),
+                });
+                continue;
+            }
+        };
+        let date_utc = req.date_utc.clone();
+        let error = create_puzzle(&state, req).await.err().map(|(_, err)| err);
+        items.push(AdminImportItem {
+            line,
+            date_utc: Some(date_utc),
+            ok: error.is_none(),
+            error,
+        });
+    }
+
+    Json(AdminImportResponse {
+        imported: items.iter().filter(|item| item.ok).count(),
+        items,
+    })
+    .into_response()
 }
 
-async fn admin_list_handler(
-    State(state): State<AppState>,
-    Query(query): Query<AdminListQuery>,
-) -> impl IntoResponse {
-    if let Some(status) = query.status {
-        let rows = sqlx::query!(
-            r#"
-            SELECT date_utc, status, title, author, variants, difficulty,
-                   created_at_utc, published_at_utc
-            FROM puzzles
-            WHERE status = ?
-            ORDER BY date_utc DESC
-            "#,
-            status
-        )
-        .fetch_all(&state.db)
-        .await;
+/// Runs a bulk import body through the same checks as
+/// `admin_import_bulk_handler`, plus a uniqueness check, without writing
+/// anything. Every problem with a record is reported, not just the first.
+async fn admin_import_validate_handler(State(state): State<AppState>, body: String) -> Response {
+    let lines: Vec<(usize, &str)> = body
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    if lines.len() > MAX_IMPORT_PUZZLES {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_IMPORT_PUZZLES} puzzles per import"),
+        )
+            .into_response();
+    }
 
-        let rows = match rows {
-            Ok(rows) => rows,
+    let mut first_line_for_date: HashMap<String, usize> = HashMap::new();
+    let mut items = Vec::with_capacity(lines.len());
+    for (line, record) in lines {
+        let mut problems = Vec::new();
+        let req = match serde_json::from_str::<AdminCreateRequest>(record) {
+            Ok(req) => req,
             Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("DB error: {e}"),
-                )
-                    .into_response();
+                problems.push(ImportProblem {
+                    kind: "parse",
+                    error: format!("invalid record: {e}"),
+                });
+                items.push(AdminImportValidateItem {
+                    line,
+                    date_utc: None,
+                    ok: false,
+                    problems,
+                });
+                continue;
             }
         };
 
-        let out: Vec<AdminPuzzleSummary> = rows
-            .into_iter()
-            .map(|row| AdminPuzzleSummary {
-                date_utc: row.date_utc.unwrap_or_default(),
-                status: row.status,
-                name: row.title,
-                author: row.author,
-                variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
-                    .unwrap_or_default(),
-                difficulty: row.difficulty,
-                created_at_utc: row.created_at_utc,
-                published_at_utc: row.published_at_utc,
-            })
-            .collect();
+        let date_utc = req.date_utc.clone();
+        if let Some(first) = first_line_for_date.get(&date_utc) {
+            problems.push(ImportProblem {
+                kind: "duplicate_date",
+                error: format!("date already used on line {first}"),
+            });
+        } else {
+            first_line_for_date.insert(date_utc.clone(), line);
+        }
+
+        match prepare_puzzle(&state, req).await {
+            Ok(prepared) => {
+                let puzzle_json = prepared.puzzle_json;
+                match tokio::task::spawn_blocking(move || verify_puzzle_json(&puzzle_json)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => problems.push(ImportProblem {
+                        kind: "verify",
+                        error: err,
+                    }),
+                    Err(err) => problems.push(ImportProblem {
+                        kind: "internal",
+                        error: format!("Verify task failed: {err}"),
+                    }),
+                }
+            }
+            Err((status, err)) => problems.push(ImportProblem {
+                kind: match status {
+                    StatusCode::CONFLICT => "exists",
+                    StatusCode::INTERNAL_SERVER_ERROR => "internal",
+                    _ => "invalid",
+                },
+                error: err,
+            }),
+        }
 
-        return Json(out).into_response();
+        items.push(AdminImportValidateItem {
+            line,
+            date_utc: Some(date_utc),
+            ok: problems.is_empty(),
+            problems,
+        });
     }
 
-    let rows = sqlx::query!(
-            r#"
-            SELECT date_utc, status, title, author, variants, difficulty,
-                   created_at_utc, published_at_utc
-            FROM puzzles
-            ORDER BY date_utc DESC
-            "#
-        )
-        .fetch_all(&state.db)
-        .await;
+    let valid = items.iter().filter(|item| item.ok).count();
+    Json(AdminImportValidateResponse {
+        valid,
+        invalid: items.len() - valid,
+        items,
+    })
+    .into_response()
+}
 
-    let rows = match rows {
-        Ok(rows) => rows,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("DB error: {e}"),
-            )
-                .into_response();
-        }
-    };
+/// Converts an f-puzzles puzzle into puzzle_json without storing it; the
+/// result is saved through `POST /api/admin/puzzles` like a generated one.
+async fn admin_import_fpuzzles_handler(
+    Json(req): Json<AdminImportFpuzzlesRequest>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking(move || {
+        let imported = fpuzzles::decode(&req.source)?;
+        let specs = constraints_from_json(&imported.constraints)?;
+        let regions = imported.regions.as_deref();
 
-    let out: Vec<AdminPuzzleSummary> = rows
-        .into_iter()
-        .map(|row| AdminPuzzleSummary {
-            date_utc: row.date_utc.unwrap_or_default(),
-            status: row.status,
-            name: row.title,
-            author: row.author,
-            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
-                .unwrap_or_default(),
-            difficulty: row.difficulty,
-            created_at_utc: row.created_at_utc,
-            published_at_utc: row.published_at_utc,
-        })
-        .collect();
+        // The solver can't fill jigsaw grids, so those are only checked for
+        // uniqueness and stored without a solution.
+        let solution = match regions {
+            Some(regions) => {
+                let mut rng = SimpleRng::new();
+                if !has_unique_solution_with_specs(
+                    &imported.puzzle,
+                    &specs,
+                    Some(regions),
+                    &mut rng,
+                ) {
+                    return Err("puzzle does not have a unique solution".to_string());
+                }
+                None
+            }
+            None => Some(solve_puzzle(&imported.puzzle, &imported.constraints)?.to_vec()),
+        };
 
-    Json(out).into_response()
-}
+        let clue_count = imported.puzzle.chars().filter(|c| *c != '.').count();
+        let mut puzzle_json = serde_json::json!({
+            "puzzle": imported.puzzle,
+            "solution": solution,
+            "constraints": imported.constraints,
+            "seed": null,
+            "clue_count": clue_count,
+            "symmetry": null,
+            "engine_version": ENGINE_VERSION,
+        });
+        if let Some(regions) = regions {
+            puzzle_json["regions"] = serde_json::json!(regions);
+        }
 
-async fn admin_get_handler(
-    State(state): State<AppState>,
-    Path(date_utc): Path<String>,
-) -> Response {
-    let row = sqlx::query!(
-        r#"
-        SELECT date_utc, status, title, author, puzzle_json, svg, variants,
-               difficulty, created_at_utc, updated_at_utc, published_at_utc
-        FROM puzzles
-        WHERE date_utc = ?
-        "#,
-        date_utc
-    )
-    .fetch_optional(&state.db)
+        let constraints_render = engine_constraints_from_specs(&specs, regions);
+        let svg = render_puzzle_svg(
+            &imported.puzzle,
+            &constraints_render,
+            RenderOptions::default(),
+        )?;
+
+        Ok::<_, String>(AdminImportFpuzzlesResponse {
+            puzzle_json: puzzle_json.to_string(),
+            svg,
+            variants: variant_kinds(&specs),
+            labels: constraint_labels(&imported.constraints),
+            title: imported.title,
+            author: imported.author,
+            skipped: imported.skipped,
+        })
+    })
     .await;
 
-    let row = match row {
-        Ok(Some(row)) => row,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Puzzle not found").into_response(),
-        Err(e) => {
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("DB error: {e}"),
+                format!("Import task failed: {err}"),
             )
                 .into_response();
         }
     };
 
-    let variants: Vec<String> =
-        serde_json::from_str(row.variants.as_deref().unwrap_or("[]")).unwrap_or_default();
-
-    Json(AdminPuzzleResponse {
-        date_utc: row.date_utc.unwrap_or(date_utc),
-        status: row.status,
-        name: row.title,
-        author: row.author,
-        puzzle_json: row.puzzle_json,
-        svg: row.svg,
-        variants,
-        difficulty: row.difficulty,
-        created_at_utc: row.created_at_utc,
-        updated_at_utc: row.updated_at_utc,
-        published_at_utc: row.published_at_utc,
-    })
-    .into_response()
-}
-
-async fn admin_publish_handler(
-    State(state): State<AppState>,
-    Path(date_utc): Path<String>,
-) -> Response {
-    let published_at = now_utc_string();
-    let result = sqlx::query!(
-        r#"
-        UPDATE puzzles
-        SET status = 'published', published_at_utc = ?
-        WHERE date_utc = ?
-        "#,
-        published_at,
-        date_utc
-    )
-    .execute(&state.db)
-    .await;
-
-    match result {
-        Ok(result) if result.rows_affected() == 0 => {
-            (StatusCode::NOT_FOUND, "Puzzle not found").into_response()
-        }
-        Ok(_) => admin_get_handler(State(state), Path(date_utc)).await,
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("DB error: {e}"),
-        )
-            .into_response(),
-    }
-}
-
-async fn admin_archive_handler(
-    State(state): State<AppState>,
-    Path(date_utc): Path<String>,
-) -> Response {
-    let result = sqlx::query!(
-        r#"
-        UPDATE puzzles
-        SET status = 'archived'
-        WHERE date_utc = ?
-        "#,
-        date_utc
-    )
-    .execute(&state.db)
-    .await;
-
     match result {
-        Ok(result) if result.rows_affected() == 0 => {
-            (StatusCode::NOT_FOUND, "Puzzle not found").into_response()
-        }
-        Ok(_) => admin_get_handler(State(state), Path(date_utc)).await,
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("DB error: {e}"),
-        )
-            .into_response(),
+        Ok(response) => Json(response).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
     }
 }
@@ -0,0 +1,88 @@
+use anyhow::{Context, bail};
+use chrono::Utc;
+use sqlx::{SqlitePool, migrate::Migrator};
+use std::{collections::HashSet, fs::create_dir_all, path::Path};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+const BACKUP_DIR: &str = "data/backups";
+
+/// Runs pending migrations, refusing to start on a partially applied
+/// migration and snapshotting the database before changing its schema.
+pub async fn run_migrations(pool: &SqlitePool) -> anyhow::Result<()> {
+    let applied = applied_migrations(pool).await?;
+
+    if let Some((version, description, _)) = applied.iter().find(|(_, _, success)| !success) {
+        bail!(
+            "migration {version} ({description}) did not complete and the database may be \
+             half-migrated. Restore the newest snapshot from {BACKUP_DIR}/ over the database \
+             file, or repair the schema by hand and delete version {version} from \
+             _sqlx_migrations, then start the server again."
+        );
+    }
+
+    let known: HashSet<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+    if let Some((version, description, _)) = applied.iter().find(|(v, _, _)| !known.contains(v)) {
+        bail!(
+            "the database has migration {version} ({description}) applied, which this build \
+             does not know about. It was migrated by a newer version of makudoku-web; run that \
+             version or restore a snapshot from {BACKUP_DIR}/."
+        );
+    }
+
+    let applied_versions: HashSet<i64> = applied.iter().map(|(v, _, _)| *v).collect();
+    let target = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .filter(|v| !applied_versions.contains(v))
+        .max();
+
+    // A fresh database has nothing worth snapshotting.
+    if let (Some(target), false) = (target, applied.is_empty()) {
+        let path = snapshot(pool, target).await?;
+        tracing::info!("snapshotted database to {path} before migrating to {target}");
+    }
+
+    MIGRATOR
+        .run(pool)
+        .await
+        .context("failed to run migrations")?;
+    Ok(())
+}
+
+async fn applied_migrations(pool: &SqlitePool) -> anyhow::Result<Vec<(i64, String, bool)>> {
+    let table: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if table.is_none() {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query_as(
+        "SELECT version, description, success FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Writes a consistent copy of the database to
+/// `data/backups/pre-migrate-<version>.db` with `VACUUM INTO`.
+async fn snapshot(pool: &SqlitePool, version: i64) -> anyhow::Result<String> {
+    create_dir_all(BACKUP_DIR)?;
+    let mut path = format!("{BACKUP_DIR}/pre-migrate-{version}.db");
+    if Path::new(&path).exists() {
+        let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+        path = format!("{BACKUP_DIR}/pre-migrate-{version}-{stamp}.db");
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(&path)
+        .execute(pool)
+        .await
+        .with_context(|| format!("failed to snapshot database to {path}"))?;
+    Ok(path)
+}
@@ -0,0 +1,151 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::store;
+
+/// A published puzzle as exchanged over `GET /api/sync/puzzles`.
+#[derive(Serialize, Deserialize)]
+pub struct SyncPuzzle {
+    pub date_utc: String,
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub variants: Vec<String>,
+    pub difficulty: Option<i64>,
+    pub published_at_utc: Option<String>,
+    pub updated_at_utc: String,
+}
+
+/// A puzzle that stopped being public upstream: archived, set back to
+/// draft or deleted. Mirrors apply the status so they stop serving it.
+#[derive(Serialize, Deserialize)]
+pub struct SyncRemoval {
+    pub date_utc: String,
+    pub status: String,
+    pub deleted_at_utc: Option<String>,
+    pub updated_at_utc: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SyncResponse {
+    pub puzzles: Vec<SyncPuzzle>,
+    #[serde(default)]
+    pub removed: Vec<SyncRemoval>,
+}
+
+/// Pulls published puzzles and removals from the upstream instance every
+/// `interval`, only asking for rows updated since the previous successful
+/// pull.
+pub async fn run_sync_loop(
+    db: SqlitePool,
+    upstream: String,
+    token: Option<String>,
+    interval: Duration,
+) {
+    let client = reqwest::Client::new();
+    let mut since: Option<String> = None;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        match sync_once(&client, &db, &upstream, token.as_deref(), since.as_deref()).await {
+            Ok(Some(latest)) => since = Some(latest),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("mirror sync failed: {e:#}"),
+        }
+    }
+}
+
+async fn sync_once(
+    client: &reqwest::Client,
+    db: &SqlitePool,
+    upstream: &str,
+    token: Option<&str>,
+    since: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let url = format!("{}/api/sync/puzzles", upstream.trim_end_matches('/'));
+    let mut request = client.get(url);
+    if let Some(since) = since {
+        request = request.query(&[("since", since)]);
+    }
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    let response: SyncResponse = request
+        .send()
+        .await
+        .context("upstream request failed")?
+        .error_for_status()
+        .context("upstream returned an error")?
+        .json()
+        .await
+        .context("invalid sync payload")?;
+
+    for puzzle in &response.puzzles {
+        // Skipped rather than failing the pull, which would retry the same
+        // rows forever.
+        if let Err(err) = crate::validate_puzzle_row(
+            &puzzle.date_utc,
+            "published",
+            &puzzle.puzzle_json,
+            puzzle.svg.as_deref(),
+        ) {
+            tracing::warn!("skipping mirrored puzzle {}: {err}", puzzle.date_utc);
+            continue;
+        }
+        store_puzzle(db, puzzle)
+            .await
+            .with_context(|| format!("failed to store {}", puzzle.date_utc))?;
+    }
+    for removal in &response.removed {
+        store::set_mirrored_status(
+            db,
+            &removal.date_utc,
+            &removal.status,
+            removal.deleted_at_utc.as_deref(),
+        )
+        .await
+        .with_context(|| format!("failed to take down {}", removal.date_utc))?;
+    }
+
+    if !response.puzzles.is_empty() || !response.removed.is_empty() {
+        tracing::info!(
+            count = response.puzzles.len(),
+            removed = response.removed.len(),
+            "mirrored puzzles from upstream"
+        );
+    }
+    // Both lists are in update order.
+    let latest = [
+        response.puzzles.last().map(|p| &p.updated_at_utc),
+        response.removed.last().map(|r| &r.updated_at_utc),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+    .cloned();
+    Ok(latest)
+}
+
+async fn store_puzzle(db: &SqlitePool, puzzle: &SyncPuzzle) -> anyhow::Result<()> {
+    let variants_json = serde_json::to_string(&puzzle.variants)?;
+    let write = store::PuzzleWrite {
+        date_utc: &puzzle.date_utc,
+        status: "published",
+        puzzle_json: &puzzle.puzzle_json,
+        svg: puzzle.svg.as_deref(),
+        title: puzzle.title.as_deref(),
+        author: puzzle.author.as_deref(),
+        difficulty: puzzle.difficulty,
+        variants_json: &variants_json,
+        published_at_utc: puzzle.published_at_utc.as_deref(),
+        engine_version: None,
+        extra_json: None,
+    };
+    store::upsert_puzzle(db, &write, true).await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::store;
+
+/// `settings` key the weekday profiles are stored under.
+const SETTINGS_KEY: &str = "generation_profiles";
+
+const MAX_LABEL_CHARS: usize = 64;
+
+/// What a generated puzzle must look like to be offered for a day, e.g.
+/// an easy classic on Monday or a hard two-variant puzzle on Friday.
+/// Unset bounds don't restrict anything.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GenerationProfile {
+    /// Shown to editors, e.g. "easy classic".
+    pub label: Option<String>,
+    /// Bounds on the number of distinct variant kinds; 0 means classic.
+    pub min_variants: Option<usize>,
+    pub max_variants: Option<usize>,
+    /// Bounds on the 1-10 difficulty rating.
+    pub min_difficulty: Option<i64>,
+    pub max_difficulty: Option<i64>,
+}
+
+impl GenerationProfile {
+    pub fn accepts(&self, variant_count: usize, difficulty: i64) -> bool {
+        self.min_variants.is_none_or(|min| variant_count >= min)
+            && self.max_variants.is_none_or(|max| variant_count <= max)
+            && self.min_difficulty.is_none_or(|min| difficulty >= min)
+            && self.max_difficulty.is_none_or(|max| difficulty <= max)
+    }
+
+    fn validate(&self, day: &str) -> Result<(), String> {
+        if self
+            .label
+            .as_ref()
+            .is_some_and(|label| label.chars().count() > MAX_LABEL_CHARS)
+        {
+            return Err(format!(
+                "{day}: label must be at most {MAX_LABEL_CHARS} characters"
+            ));
+        }
+        if self
+            .min_variants
+            .zip(self.max_variants)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err(format!("{day}: min_variants is above max_variants"));
+        }
+        for difficulty in [self.min_difficulty, self.max_difficulty]
+            .into_iter()
+            .flatten()
+        {
+            if !(1..=10).contains(&difficulty) {
+                return Err(format!("{day}: difficulty bounds must be between 1 and 10"));
+            }
+        }
+        if self
+            .min_difficulty
+            .zip(self.max_difficulty)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err(format!("{day}: min_difficulty is above max_difficulty"));
+        }
+        Ok(())
+    }
+}
+
+/// The default profile of each weekday; days without one generate freely.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeekdayProfiles {
+    pub monday: Option<GenerationProfile>,
+    pub tuesday: Option<GenerationProfile>,
+    pub wednesday: Option<GenerationProfile>,
+    pub thursday: Option<GenerationProfile>,
+    pub friday: Option<GenerationProfile>,
+    pub saturday: Option<GenerationProfile>,
+    pub sunday: Option<GenerationProfile>,
+}
+
+impl WeekdayProfiles {
+    pub fn for_weekday(&self, weekday: Weekday) -> Option<&GenerationProfile> {
+        match weekday {
+            Weekday::Mon => self.monday.as_ref(),
+            Weekday::Tue => self.tuesday.as_ref(),
+            Weekday::Wed => self.wednesday.as_ref(),
+            Weekday::Thu => self.thursday.as_ref(),
+            Weekday::Fri => self.friday.as_ref(),
+            Weekday::Sat => self.saturday.as_ref(),
+            Weekday::Sun => self.sunday.as_ref(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let days = [
+            ("monday", &self.monday),
+            ("tuesday", &self.tuesday),
+            ("wednesday", &self.wednesday),
+            ("thursday", &self.thursday),
+            ("friday", &self.friday),
+            ("saturday", &self.saturday),
+            ("sunday", &self.sunday),
+        ];
+        for (day, profile) in days {
+            if let Some(profile) = profile {
+                profile.validate(day)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The stored profiles, or none when they were never set.
+pub async fn load(db: &SqlitePool) -> Result<WeekdayProfiles, String> {
+    let Some(json) = store::get_setting(db, SETTINGS_KEY)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?
+    else {
+        return Ok(WeekdayProfiles::default());
+    };
+    serde_json::from_str(&json).map_err(|e| format!("Stored generation profiles are invalid: {e}"))
+}
+
+pub async fn save(db: &SqlitePool, profiles: &WeekdayProfiles) -> Result<(), String> {
+    let json = serde_json::to_string(profiles).map_err(|e| e.to_string())?;
+    store::set_setting(db, SETTINGS_KEY, &json)
+        .await
+        .map_err(|e| format!("DB error: {e}"))
+}
@@ -0,0 +1,377 @@
+use makudoku::{
+    EngineRng, NN, RenderOptions, SimpleRng, VERSION as ENGINE_VERSION,
+    generate_full_solution_with, render_puzzle_svg,
+};
+use serde_json::{Value, json};
+
+use crate::{difficulty, difficulty::Band, random_pool::RandomPuzzle, rng::Seeding};
+
+/// Variants a random puzzle can ask for. The global ones are applied before
+/// the solution is generated; `kropki`, `thermo` and `killer` are drawn
+/// from the solution afterwards, so they always fit it.
+pub const RANDOM_VARIANTS: &[&str] = &[
+    "diagonal",
+    "king",
+    "knight",
+    "disjoint_groups",
+    "windoku",
+    "kropki",
+    "thermo",
+    "killer",
+];
+
+const GLOBAL_VARIANTS: &[&str] = &["diagonal", "king", "knight", "disjoint_groups", "windoku"];
+
+const DEFAULT_CLUE_TARGET: usize = 30;
+/// Most variants picked when a request leaves them to chance.
+const MAX_RANDOM_VARIANTS: usize = 2;
+
+/// Puzzles generated for a difficulty band or generation profile before
+/// giving up.
+pub const MAX_ATTEMPTS: usize = 25;
+
+const KROPKI_DOTS: usize = 8;
+const THERMOS: usize = 2;
+const KILLER_CAGES: usize = 6;
+
+/// What a player asked `/api/puzzle/random` for. Unset fields are left to
+/// the RNG: a mix of up to two variants, `DEFAULT_CLUE_TARGET` clues and a
+/// fresh seed.
+pub struct RandomOptions {
+    variants: Option<Vec<&'static str>>,
+    clue_target: usize,
+    seed: Option<u64>,
+}
+
+impl RandomOptions {
+    /// `None` when no option is given, so the request can be served from
+    /// the pool. `variants` is comma separated; `classic` asks for none.
+    pub fn parse(
+        variants: Option<&str>,
+        clue_target: Option<usize>,
+        seed: Option<u64>,
+    ) -> Result<Option<Self>, String> {
+        if variants.is_none() && clue_target.is_none() && seed.is_none() {
+            return Ok(None);
+        }
+        let variants = variants.map(parse_variants).transpose()?;
+        let clue_target = clue_target.unwrap_or(DEFAULT_CLUE_TARGET);
+        if !(17..=80).contains(&clue_target) {
+            return Err("clue_target must be between 17 and 80".to_string());
+        }
+        Ok(Some(Self {
+            variants,
+            clue_target,
+            seed,
+        }))
+    }
+}
+
+impl Default for RandomOptions {
+    /// Everything left to chance, as for pooled puzzles.
+    fn default() -> Self {
+        Self {
+            variants: None,
+            clue_target: DEFAULT_CLUE_TARGET,
+            seed: None,
+        }
+    }
+}
+
+fn parse_variants(list: &str) -> Result<Vec<&'static str>, String> {
+    if list.trim() == "classic" {
+        return Ok(Vec::new());
+    }
+    let mut variants = Vec::new();
+    for name in list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let variant = RANDOM_VARIANTS
+            .iter()
+            .copied()
+            .find(|variant| *variant == name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown variant {name}; expected classic or {}",
+                    RANDOM_VARIANTS.join(", ")
+                )
+            })?;
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    Ok(variants)
+}
+
+/// Generates a puzzle for `options`, rated within `band` when one is
+/// given. Blocking. The same seed and options give the same puzzle on the
+/// same engine version. Every random puzzle comes from here, pooled ones
+/// with `RandomOptions::default()`, so they are all stored alike.
+pub fn generate(
+    options: &RandomOptions,
+    band: Option<Band>,
+    limits: &crate::GenerationLimits,
+    seeding: Seeding,
+) -> Result<RandomPuzzle, String> {
+    generate_until(options, limits, seeding, |puzzle| puzzle.fits(band))?.ok_or_else(|| {
+        format!(
+            "no puzzle rated {} in {MAX_ATTEMPTS} attempts; try other options",
+            band.map(Band::name).unwrap_or_default()
+        )
+    })
+}
+
+/// Generates puzzles for `options` until one passes `accept`, or `None`
+/// after `MAX_ATTEMPTS`. Blocking. A `seed` in the options wins over
+/// `seeding`.
+pub fn generate_until(
+    options: &RandomOptions,
+    limits: &crate::GenerationLimits,
+    seeding: Seeding,
+    accept: impl Fn(&RandomPuzzle) -> bool,
+) -> Result<Option<RandomPuzzle>, String> {
+    let (mut rng, seed_source) = match options.seed {
+        Some(seed) => (SimpleRng::from_seed(seed), "request"),
+        None => (seeding.rng(), seeding.name()),
+    };
+    let seed = rng.seed();
+    for _ in 0..MAX_ATTEMPTS {
+        let (puzzle, solution, constraints) = attempt(options, &mut rng, limits)?;
+        let rating = difficulty::rate(&puzzle, &constraints, None)
+            .ok()
+            .map(|rating| rating.difficulty);
+        let specs = crate::constraints_from_json(&constraints)?;
+        let puzzle_json = json!({
+            "puzzle": puzzle,
+            "solution": solution.to_vec(),
+            "constraints": constraints,
+            "seed": seed,
+            "rng": crate::rng::RNG_KIND,
+            "seed_source": seed_source,
+            "clue_count": puzzle.chars().filter(|c| *c != '.').count(),
+            "difficulty": rating,
+            "engine_version": ENGINE_VERSION,
+        });
+        let candidate = RandomPuzzle {
+            svg: String::new(),
+            variants: crate::variant_kinds(&specs),
+            puzzle_json: puzzle_json.to_string(),
+            seed,
+            difficulty: rating,
+        };
+        if !accept(&candidate) {
+            continue;
+        }
+        let svg = render_puzzle_svg(
+            &puzzle,
+            &crate::engine_constraints_from_specs(&specs, None),
+            RenderOptions::default(),
+        )?;
+        return Ok(Some(RandomPuzzle { svg, ..candidate }));
+    }
+    Ok(None)
+}
+
+/// One puzzle, its solution and its constraints, drawing everything from
+/// `rng`.
+fn attempt(
+    options: &RandomOptions,
+    rng: &mut SimpleRng,
+    limits: &crate::GenerationLimits,
+) -> Result<(String, [u8; NN], Vec<Value>), String> {
+    let variants = match &options.variants {
+        Some(variants) => variants.clone(),
+        None => pick_variants(rng),
+    };
+
+    let globals: Vec<Value> = variants
+        .iter()
+        .filter(|variant| GLOBAL_VARIANTS.contains(variant))
+        .map(|variant| json!({ "type": variant }))
+        .collect();
+    let global_specs = crate::constraints_from_json(&globals)?;
+    let solution = generate_full_solution_with(rng.clone(), |eng| {
+        crate::apply_variant_specs(eng, &global_specs);
+    })?;
+
+    let mut constraints = globals;
+    for variant in &variants {
+        match *variant {
+            "kropki" => constraints.extend(kropki_dots(&solution, rng)),
+            "thermo" => constraints.extend(thermos(&solution, rng)),
+            "killer" => constraints.extend(killer_cages(&solution, rng)),
+            _ => {}
+        }
+    }
+    let specs = crate::constraints_from_json(&constraints)?;
+    let puzzle = crate::generate_puzzle_from_solution(
+        &solution,
+        options.clue_target,
+        &specs,
+        rng,
+        None,
+        limits,
+        &[],
+    )?;
+    Ok((puzzle, solution, constraints))
+}
+
+fn pick_variants(rng: &mut SimpleRng) -> Vec<&'static str> {
+    let mut variants = RANDOM_VARIANTS.to_vec();
+    crate::shuffle_indices(rng, &mut variants);
+    variants.truncate(rng.gen_range(0..MAX_RANDOM_VARIANTS + 1));
+    variants
+}
+
+fn cell(index: usize) -> (usize, usize) {
+    (index / 9, index % 9)
+}
+
+/// Orthogonal neighbours of a cell index.
+fn neighbours(index: usize) -> Vec<usize> {
+    let (r, c) = cell(index);
+    let mut out = Vec::with_capacity(4);
+    if r > 0 {
+        out.push(index - 9);
+    }
+    if r < 8 {
+        out.push(index + 9);
+    }
+    if c > 0 {
+        out.push(index - 1);
+    }
+    if c < 8 {
+        out.push(index + 1);
+    }
+    out
+}
+
+fn shuffled_cells(rng: &mut SimpleRng) -> Vec<usize> {
+    let mut cells: Vec<usize> = (0..NN).collect();
+    crate::shuffle_indices(rng, &mut cells);
+    cells
+}
+
+/// White dots between consecutive digits and black dots where one digit is
+/// double the other, on randomly chosen pairs.
+fn kropki_dots(solution: &[u8; NN], rng: &mut SimpleRng) -> Vec<Value> {
+    let mut pairs: Vec<(usize, usize)> = (0..NN)
+        .flat_map(|a| {
+            neighbours(a)
+                .into_iter()
+                .filter(move |b| *b > a)
+                .map(move |b| (a, b))
+        })
+        .filter(|&(a, b)| {
+            let (x, y) = (solution[a], solution[b]);
+            x.abs_diff(y) == 1 || x == 2 * y || y == 2 * x
+        })
+        .collect();
+    crate::shuffle_indices(rng, &mut pairs);
+    pairs
+        .into_iter()
+        .take(KROPKI_DOTS)
+        .map(|(a, b)| {
+            // 1 and 2 fit either dot; they get the white one.
+            let kind = if solution[a].abs_diff(solution[b]) == 1 {
+                "kropki_white"
+            } else {
+                "kropki_black"
+            };
+            let (a, b) = (cell(a), cell(b));
+            json!({ "type": kind, "a": [a.0, a.1], "b": [b.0, b.1] })
+        })
+        .collect()
+}
+
+/// Thermometers of 3 to 5 cells climbing through the solution, grown from
+/// random bulbs without sharing cells.
+fn thermos(solution: &[u8; NN], rng: &mut SimpleRng) -> Vec<Value> {
+    let mut used = [false; NN];
+    let mut out = Vec::new();
+    for bulb in shuffled_cells(rng) {
+        if out.len() == THERMOS {
+            break;
+        }
+        if used[bulb] {
+            continue;
+        }
+        let mut path = vec![bulb];
+        while path.len() < 5 {
+            let last = path[path.len() - 1];
+            let next: Vec<usize> = neighbours(last)
+                .into_iter()
+                .filter(|n| !used[*n] && !path.contains(n) && solution[*n] > solution[last])
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            path.push(next[rng.gen_range(0..next.len())]);
+        }
+        if path.len() < 3 {
+            continue;
+        }
+        for index in &path {
+            used[*index] = true;
+        }
+        let path: Vec<Value> = path
+            .into_iter()
+            .map(|index| {
+                let (r, c) = cell(index);
+                json!([r, c])
+            })
+            .collect();
+        out.push(json!({ "type": "thermo", "path": path }));
+    }
+    out
+}
+
+/// Cages of 2 to 4 cells without repeated digits, grown from random cells
+/// without overlapping.
+fn killer_cages(solution: &[u8; NN], rng: &mut SimpleRng) -> Vec<Value> {
+    let mut used = [false; NN];
+    let mut out = Vec::new();
+    for start in shuffled_cells(rng) {
+        if out.len() == KILLER_CAGES {
+            break;
+        }
+        if used[start] {
+            continue;
+        }
+        let size = 2 + rng.gen_range(0..3);
+        let mut cage = vec![start];
+        while cage.len() < size {
+            let next: Vec<usize> = cage
+                .iter()
+                .flat_map(|index| neighbours(*index))
+                .filter(|n| {
+                    !used[*n]
+                        && !cage.contains(n)
+                        && cage.iter().all(|index| solution[*index] != solution[*n])
+                })
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            cage.push(next[rng.gen_range(0..next.len())]);
+        }
+        if cage.len() < 2 {
+            continue;
+        }
+        for index in &cage {
+            used[*index] = true;
+        }
+        let sum: u32 = cage.iter().map(|index| u32::from(solution[*index])).sum();
+        let cells: Vec<Value> = cage
+            .into_iter()
+            .map(|index| {
+                let (r, c) = cell(index);
+                json!([r, c])
+            })
+            .collect();
+        out.push(json!({ "type": "killer", "cells": cells, "sum": sum }));
+    }
+    out
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+use crate::{
+    GenerationLimits,
+    config::Config,
+    difficulty::Band,
+    random_options::{self, RandomOptions},
+};
+
+/// Wait before retrying after a failed generation, so a broken generator
+/// doesn't spin.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A random puzzle rendered and ready to serve.
+pub struct RandomPuzzle {
+    /// Full SVG; layers are stripped when it's served.
+    pub svg: String,
+    pub variants: Vec<String>,
+    /// Givens, solution and constraints, stored when the puzzle is served
+    /// so it can be checked.
+    pub puzzle_json: String,
+    /// Asking for the same options with this seed gives the puzzle again.
+    pub seed: u64,
+    /// Rating from `difficulty::rate`, when the rater could read the puzzle.
+    pub difficulty: Option<i64>,
+}
+
+impl RandomPuzzle {
+    pub fn fits(&self, band: Option<Band>) -> bool {
+        band.is_none_or(|band| self.difficulty.is_some_and(|d| band.contains(d)))
+    }
+}
+
+/// Puzzles generated ahead of time for `/api/puzzle/random`, so requests
+/// don't wait on the generator. The refill loop tops it up after every
+/// `take`.
+pub struct RandomPool {
+    capacity: usize,
+    puzzles: Mutex<VecDeque<RandomPuzzle>>,
+    refill: Notify,
+}
+
+impl RandomPool {
+    /// Returns `None` for a capacity of 0, which turns the pool off.
+    pub fn new(capacity: usize) -> Option<Self> {
+        if capacity == 0 {
+            return None;
+        }
+        Some(Self {
+            capacity,
+            puzzles: Mutex::new(VecDeque::with_capacity(capacity)),
+            refill: Notify::new(),
+        })
+    }
+
+    /// The oldest pooled puzzle in `band`, or `None` when there isn't one.
+    pub fn take(&self, band: Option<Band>) -> Option<RandomPuzzle> {
+        let mut puzzles = self.puzzles.lock().unwrap();
+        let index = puzzles.iter().position(|puzzle| puzzle.fits(band))?;
+        let puzzle = puzzles.remove(index);
+        self.refill.notify_one();
+        puzzle
+    }
+
+    fn is_full(&self) -> bool {
+        self.puzzles.lock().unwrap().len() >= self.capacity
+    }
+}
+
+/// Fills the pool from startup, then waits for `take` to make room.
+pub async fn run_refill_loop(pool: Arc<RandomPool>, config: Arc<Config>) {
+    loop {
+        while !pool.is_full() {
+            let config = config.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                random_options::generate(
+                    &RandomOptions::default(),
+                    None,
+                    &GenerationLimits::server(&config),
+                    config.random_rng_seeding,
+                )
+            })
+            .await;
+            match result {
+                Ok(Ok(puzzle)) => pool.puzzles.lock().unwrap().push_back(puzzle),
+                Ok(Err(e)) => {
+                    tracing::warn!("random pool generation failed: {e}");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                Err(e) => {
+                    tracing::warn!("random pool task failed: {e}");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+        pool.refill.notified().await;
+    }
+}
@@ -0,0 +1,161 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::check_limit::Throttled;
+
+/// Addresses are pruned once the map grows past this many entries.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Endpoints that generate puzzles, which cost far more than reading one,
+/// and the admin login, which must not allow guessing the token quickly.
+const EXPENSIVE_PATHS: &[&str] = &[
+    "/api/puzzle/random",
+    "/api/admin/login",
+    "/api/admin/puzzles/generate",
+    "/api/admin/puzzles/generate/custom",
+    "/api/admin/jobs/generate",
+    "/api/admin/benchmarks/run",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Budget {
+    /// Public API reads and checks.
+    Cheap,
+    Expensive,
+}
+
+/// The budget a path counts against. Static files and admin endpoints
+/// other than generation and login aren't limited.
+fn budget_for(path: &str) -> Option<Budget> {
+    if EXPENSIVE_PATHS.contains(&path) {
+        Some(Budget::Expensive)
+    } else if path.starts_with("/api/") && !path.starts_with("/api/admin/") {
+        Some(Budget::Cheap)
+    } else {
+        None
+    }
+}
+
+/// Sliding-window request limits per client address, with separate
+/// budgets for cheap and expensive endpoints.
+pub struct RateLimiter {
+    /// `(limit, window)` of each budget; `None` leaves it unlimited.
+    cheap: Option<(usize, Duration)>,
+    expensive: Option<(usize, Duration)>,
+    /// Take the client address from the last `X-Forwarded-For` entry, for
+    /// instances behind a reverse proxy.
+    trust_proxy: bool,
+    clients: Mutex<HashMap<(IpAddr, Budget), VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Returns `None` when neither budget is limited.
+    pub fn new(
+        cheap: Option<(usize, Duration)>,
+        expensive: Option<(usize, Duration)>,
+        trust_proxy: bool,
+    ) -> Option<Self> {
+        if cheap.is_none() && expensive.is_none() {
+            return None;
+        }
+        Some(Self {
+            cheap,
+            expensive,
+            trust_proxy,
+            clients: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn try_request(&self, client: IpAddr, budget: Budget) -> Result<(), Throttled> {
+        let limits = match budget {
+            Budget::Cheap => self.cheap,
+            Budget::Expensive => self.expensive,
+        };
+        let Some((limit, window)) = limits else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() > PRUNE_THRESHOLD {
+            // Keep anything younger than the longest window.
+            let longest = [self.cheap, self.expensive]
+                .into_iter()
+                .flatten()
+                .map(|(_, w)| w)
+                .max()
+                .unwrap_or(window);
+            clients.retain(|_, hits| hits.back().is_some_and(|t| now - *t < longest));
+        }
+
+        let hits = clients.entry((client, budget)).or_default();
+        while hits.front().is_some_and(|t| now - *t >= window) {
+            hits.pop_front();
+        }
+        if hits.len() >= limit {
+            let oldest = hits.front().copied().unwrap_or(now);
+            return Err(Throttled {
+                what: "requests",
+                retry_after: window.saturating_sub(now - oldest),
+                limit,
+                window,
+            });
+        }
+        hits.push_back(now);
+        Ok(())
+    }
+
+    fn client(&self, req: &Request) -> Option<IpAddr> {
+        client_address(
+            req.headers(),
+            req.extensions().get::<ConnectInfo<SocketAddr>>(),
+            self.trust_proxy,
+        )
+    }
+}
+
+/// The client's address: the last `X-Forwarded-For` entry when
+/// `trust_proxy` is set and the header is there, otherwise the peer.
+pub fn client_address(
+    headers: &HeaderMap,
+    peer: Option<&ConnectInfo<SocketAddr>>,
+    trust_proxy: bool,
+) -> Option<IpAddr> {
+    if trust_proxy {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|v| v.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    peer.map(|ConnectInfo(addr)| addr.ip())
+}
+
+/// Answers `429` with `Retry-After` once a client is over the budget of the
+/// endpoint it calls.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let throttled = match (budget_for(req.uri().path()), limiter.client(&req)) {
+        (Some(budget), Some(client)) => limiter.try_request(client, budget).err(),
+        _ => None,
+    };
+    if let Some(throttled) = throttled {
+        return throttled.into_response();
+    }
+    next.run(req).await
+}
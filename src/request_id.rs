@@ -0,0 +1,100 @@
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{
+        HeaderValue,
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Accepts an incoming `X-Request-ID` (or generates one), runs the request
+/// inside a tracing span carrying it, echoes it in the response header and
+/// adds it to plain-text and JSON error bodies.
+pub async fn request_id_middleware(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| is_valid_request_id(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+
+    async move {
+        let response = next.run(req).await;
+        tracing::debug!(status = %response.status(), "request finished");
+
+        let mut response = annotate_error_body(response, &request_id).await;
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        response
+    }
+    .instrument(span)
+    .await
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// Appends the id to plain-text error bodies and adds it as `request_id`
+/// to JSON error objects, such as the throttling backoff.
+async fn annotate_error_body(response: Response, request_id: &str) -> Response {
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let is_text = content_type.starts_with("text/plain");
+    let is_json = content_type.starts_with("application/json");
+    if !(status.is_client_error() || status.is_server_error()) || !(is_text || is_json) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let message = match to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => String::new(),
+    };
+    if status.is_server_error() {
+        tracing::error!(%status, error = %message, "error response");
+    }
+
+    let body = if is_json {
+        match serde_json::from_str::<serde_json::Value>(&message) {
+            Ok(serde_json::Value::Object(mut fields)) => {
+                fields.insert("request_id".to_string(), request_id.into());
+                serde_json::Value::Object(fields).to_string()
+            }
+            // Arrays and scalars have nowhere to put the id.
+            _ => message,
+        }
+    } else if message.is_empty() {
+        format!("request_id: {request_id}")
+    } else {
+        format!("{message} (request_id: {request_id})")
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
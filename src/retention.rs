@@ -0,0 +1,49 @@
+use chrono::{SecondsFormat, Utc};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::store;
+
+/// Deletes solve records older than `days` once a day, starting at
+/// startup. They only keep a player from being counted twice for the same
+/// puzzle, which matters little once the puzzle is that old.
+pub async fn run_retention_loop(db: SqlitePool, days: u64) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+    loop {
+        ticker.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let cutoff = cutoff.to_rfc3339_opts(SecondsFormat::Millis, true);
+        match store::delete_solve_records_before(&db, &cutoff).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("deleted {deleted} solve record(s) from before {cutoff}"),
+            Err(e) => tracing::warn!("solve record retention failed: {e}"),
+        }
+    }
+}
+
+/// Oldest `created_at_utc` of a random puzzle that can still be checked,
+/// or `None` when they are kept forever.
+pub fn random_puzzle_cutoff(hours: u64) -> Option<String> {
+    if hours == 0 {
+        return None;
+    }
+    let cutoff = Utc::now() - chrono::Duration::hours(hours as i64);
+    Some(cutoff.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// Deletes expired random puzzles once an hour, starting at startup.
+/// Lookups skip them anyway; this only keeps the table small.
+pub async fn run_random_puzzle_expiry_loop(db: SqlitePool, hours: u64) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(60 * 60));
+    loop {
+        ticker.tick().await;
+        let Some(cutoff) = random_puzzle_cutoff(hours) else {
+            return;
+        };
+        match store::delete_random_puzzles_before(&db, &cutoff).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("deleted {deleted} expired random puzzle(s)"),
+            Err(e) => tracing::warn!("random puzzle expiry failed: {e}"),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use makudoku::SimpleRng;
+
+/// Recorded as `rng` in generated puzzle_json. `SimpleRng` is the only RNG
+/// the engine exposes; `EngineRng` is the trait it implements.
+pub const RNG_KIND: &str = "SimpleRng";
+
+/// How a generating endpoint seeds its RNG when a request brings no seed.
+#[derive(Clone, Copy, Debug)]
+pub enum Seeding {
+    /// A fresh seed per request from `SimpleRng::new`.
+    Entropy,
+    /// The same seed for every request, for reproducible test instances.
+    Fixed(u64),
+}
+
+impl Seeding {
+    /// `entropy` or `fixed:<seed>`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.split_once(':') {
+            None if value == "entropy" => Ok(Self::Entropy),
+            Some(("fixed", seed)) => seed
+                .trim()
+                .parse()
+                .map(Self::Fixed)
+                .map_err(|_| format!("invalid fixed seed {seed}")),
+            _ => Err(format!(
+                "unknown seeding {value}; expected entropy or fixed:<seed>"
+            )),
+        }
+    }
+
+    pub fn rng(self) -> SimpleRng {
+        match self {
+            Self::Entropy => SimpleRng::new(),
+            Self::Fixed(seed) => SimpleRng::from_seed(seed),
+        }
+    }
+
+    /// Name reported in `/api/meta` and stored as `seed_source`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Entropy => "entropy",
+            Self::Fixed(_) => "fixed",
+        }
+    }
+}
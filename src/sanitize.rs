@@ -0,0 +1,163 @@
+use serde_json::{Map, Value};
+
+/// Top-level `puzzle_json` fields the server writes or reads back.
+const KNOWN_PUZZLE_FIELDS: &[&str] = &[
+    "puzzle",
+    "solution",
+    "constraints",
+    "seed",
+    "rng",
+    "seed_source",
+    "clue_count",
+    "symmetry",
+    "engine_version",
+    "custom_rules",
+    "machine_checkable",
+    "regions",
+];
+
+/// Substrings (matched case-insensitively) that can run script inside an SVG
+/// once it is inlined into the page.
+const FORBIDDEN_SVG_PATTERNS: &[&str] = &[
+    "<script",
+    "<foreignobject",
+    "<iframe",
+    "<embed",
+    "<object",
+    "javascript:",
+    "data:text/html",
+    "@import",
+];
+
+/// Splits unknown top-level fields out of `puzzle_json`. Returns the
+/// re-encoded puzzle JSON and, when anything was removed, the removed fields
+/// as a JSON object.
+pub fn quarantine_unknown_fields(puzzle_json: &str) -> Result<(String, Option<String>), String> {
+    let value: Value =
+        serde_json::from_str(puzzle_json).map_err(|e| format!("invalid JSON: {e}"))?;
+    let Value::Object(fields) = value else {
+        return Err("puzzle_json must be a JSON object".to_string());
+    };
+
+    let (known, extra): (Map<String, Value>, Map<String, Value>) = fields
+        .into_iter()
+        .partition(|(key, _)| KNOWN_PUZZLE_FIELDS.contains(&key.as_str()));
+
+    let extra = if extra.is_empty() {
+        None
+    } else {
+        Some(Value::Object(extra).to_string())
+    };
+    Ok((Value::Object(known).to_string(), extra))
+}
+
+/// Rejects SVG markup carrying scripts, event handler attributes, embedded
+/// documents or references to anything outside the document.
+pub fn check_svg(svg: &str) -> Result<(), String> {
+    let lower = svg.to_ascii_lowercase();
+    if let Some(pattern) = FORBIDDEN_SVG_PATTERNS.iter().find(|p| lower.contains(*p)) {
+        return Err(format!("svg must not contain {pattern}"));
+    }
+    if has_event_handler_attribute(&lower) {
+        return Err("svg must not contain event handler attributes".to_string());
+    }
+    if has_external_reference(&lower) {
+        return Err("svg must not reference external resources".to_string());
+    }
+    Ok(())
+}
+
+/// Looks for `on<name>=` attributes such as `onload=` or `onclick =`.
+/// Browsers also take an attribute straight after `/` or a closing quote,
+/// as in `<svg/onload=...>` or `x=""onclick=...`.
+fn has_event_handler_attribute(lower: &str) -> bool {
+    let bytes = lower.as_bytes();
+    lower.match_indices("on").any(|(idx, _)| {
+        let starts_attribute = idx > 0
+            && (bytes[idx - 1].is_ascii_whitespace()
+                || matches!(bytes[idx - 1], b'/' | b'"' | b'\''));
+        if !starts_attribute {
+            return false;
+        }
+        let rest = &lower[idx + 2..];
+        let name_len = rest.bytes().take_while(|b| b.is_ascii_alphabetic()).count();
+        name_len > 0 && rest[name_len..].trim_start().starts_with('=')
+    })
+}
+
+/// Looks for `href`/`xlink:href` values and CSS `url(...)`s that point
+/// anywhere but a fragment in the same document (or an inline image).
+fn has_external_reference(lower: &str) -> bool {
+    let is_local = |value: &str| {
+        let value = value.trim_start_matches(['"', '\'', ' ']);
+        value.starts_with('#') || value.starts_with("data:image/")
+    };
+
+    let external_href = lower.match_indices("href").any(|(idx, _)| {
+        lower[idx + 4..]
+            .trim_start()
+            .strip_prefix('=')
+            .is_some_and(|value| !is_local(value.trim_start()))
+    });
+    let external_url = lower
+        .match_indices("url(")
+        .any(|(idx, _)| !is_local(&lower[idx + 4..]));
+
+    external_href || external_url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_svg;
+
+    #[test]
+    fn accepts_rendered_markup() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 450 450"><g id="grid"><rect x="0" y="0" width="450" height="450" fill="url(#shade)"/></g><use href="#dot"/><text font-family="monospace">5</text></svg>"##;
+        assert_eq!(check_svg(svg), Ok(()));
+    }
+
+    #[test]
+    fn rejects_event_handlers() {
+        for svg in [
+            r#"<svg onload="alert(1)"></svg>"#,
+            r#"<svg/onload=alert(1)>"#,
+            r#"<rect x=""onclick="alert(1)"/>"#,
+            r#"<rect x=''onmouseover='alert(1)'/>"#,
+            "<rect\tONCLICK = \"alert(1)\"/>",
+        ] {
+            assert_eq!(
+                check_svg(svg),
+                Err("svg must not contain event handler attributes".to_string()),
+                "{svg}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_scripts() {
+        assert_eq!(
+            check_svg("<svg><SCRIPT>alert(1)</SCRIPT></svg>"),
+            Err("svg must not contain <script".to_string())
+        );
+        assert_eq!(
+            check_svg(r#"<a href="javascript:alert(1)"><rect/></a>"#),
+            Err("svg must not contain javascript:".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_external_references() {
+        for svg in [
+            r#"<use href="https://example.com/a.svg#x"/>"#,
+            r#"<use xlink:href="//example.com/a.svg#x"/>"#,
+            r#"<image href = 'http://example.com/a.png'/>"#,
+            r#"<rect fill="url(https://example.com/a.svg#p)"/>"#,
+        ] {
+            assert_eq!(
+                check_svg(svg),
+                Err("svg must not reference external resources".to_string()),
+                "{svg}"
+            );
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use serde::Serialize;
+
+/// JSON shape of one constraint `type` accepted by `constraints_from_json`,
+/// for editors that build their forms from it.
+#[derive(Serialize)]
+pub struct ConstraintSchema {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Global constraints apply to the whole grid rather than to given cells.
+    pub global: bool,
+    pub fields: &'static [FieldSchema],
+}
+
+#[derive(Serialize)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// One of `cell` (`[row, col]`), `cells` (array of cells), `paths`
+    /// (array of cell arrays), `integer`, `digits` (array of 1-9),
+    /// `boolean`, `string`, `color`, `enum` or `cage_style`.
+    pub kind: &'static str,
+    pub required: bool,
+    /// Bounds of an integer, or of the number of cells or digits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<u32>,
+    #[serde(skip_serializing_if = "is_empty")]
+    pub values: &'static [&'static str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<&'static str>,
+}
+
+fn is_empty(values: &&[&str]) -> bool {
+    values.is_empty()
+}
+
+const fn field(name: &'static str, kind: &'static str, required: bool) -> FieldSchema {
+    FieldSchema {
+        name,
+        kind,
+        required,
+        min: None,
+        max: None,
+        values: &[],
+        default: None,
+    }
+}
+
+const fn ranged(
+    name: &'static str,
+    kind: &'static str,
+    required: bool,
+    min: u32,
+    max: u32,
+) -> FieldSchema {
+    FieldSchema {
+        min: Some(min),
+        max: Some(max),
+        ..field(name, kind, required)
+    }
+}
+
+const fn choice(
+    name: &'static str,
+    values: &'static [&'static str],
+    default: &'static str,
+) -> FieldSchema {
+    FieldSchema {
+        values,
+        default: Some(default),
+        ..field(name, "enum", false)
+    }
+}
+
+const fn flag(name: &'static str, default: &'static str) -> FieldSchema {
+    FieldSchema {
+        default: Some(default),
+        ..field(name, "boolean", false)
+    }
+}
+
+const fn constraint(kind: &'static str, fields: &'static [FieldSchema]) -> ConstraintSchema {
+    ConstraintSchema {
+        kind,
+        global: false,
+        fields,
+    }
+}
+
+const fn global(kind: &'static str) -> ConstraintSchema {
+    ConstraintSchema {
+        kind,
+        global: true,
+        fields: &[],
+    }
+}
+
+const LINE_FIELDS: &[FieldSchema] = &[ranged("path", "cells", true, 2, 81)];
+
+/// Optional on every constraint; never affect solving.
+pub const COMMON_FIELDS: &[FieldSchema] = &[
+    ranged("label", "string", false, 0, 64),
+    ranged("group", "string", false, 0, 64),
+];
+
+/// Every constraint `type`. Keep in step with `constraints_from_json`; the
+/// tests below fail when a type here stops parsing.
+pub const CONSTRAINTS: &[ConstraintSchema] = &[
+    constraint(
+        "kropki_white",
+        &[field("a", "cell", true), field("b", "cell", true)],
+    ),
+    constraint(
+        "kropki_black",
+        &[field("a", "cell", true), field("b", "cell", true)],
+    ),
+    constraint(
+        "thermo",
+        &[
+            field("path", "cells", false),
+            field("bulb", "cell", false),
+            field("branches", "paths", false),
+        ],
+    ),
+    constraint(
+        "arrow",
+        &[field("path", "cells", true), ranged("pill", "cells", false, 1, 2)],
+    ),
+    constraint("renban", &[ranged("path", "cells", true, 2, 9)]),
+    constraint("whisper", LINE_FIELDS),
+    constraint("palindrome", LINE_FIELDS),
+    constraint("between", &[ranged("path", "cells", true, 3, 81)]),
+    constraint(
+        "killer",
+        &[
+            field("cells", "cells", true),
+            ranged("sum", "integer", true, 1, 45),
+            flag("no_repeats", "true"),
+            flag("show_sum", "true"),
+            field("style", "cage_style", false),
+        ],
+    ),
+    constraint(
+        "little_killer",
+        &[
+            field("cell", "cell", true),
+            FieldSchema {
+                required: true,
+                ..choice(
+                    "direction",
+                    &["down_right", "down_left", "up_right", "up_left"],
+                    "down_right",
+                )
+            },
+            ranged("sum", "integer", true, 1, 81),
+        ],
+    ),
+    constraint(
+        "sandwich",
+        &[
+            ranged("row", "integer", false, 0, 8),
+            ranged("col", "integer", false, 0, 8),
+            ranged("sum", "integer", true, 0, 35),
+        ],
+    ),
+    constraint(
+        "quadruple",
+        &[
+            field("cell", "cell", true),
+            ranged("digits", "digits", true, 1, 4),
+        ],
+    ),
+    constraint(
+        "color_region",
+        &[
+            field("cells", "cells", true),
+            field("color", "color", true),
+            flag("distinct", "false"),
+        ],
+    ),
+    global("king"),
+    global("knight"),
+    global("queen"),
+    global("disjoint_groups"),
+    constraint("extra_region", &[ranged("cells", "cells", true, 9, 9)]),
+    global("windoku"),
+    ConstraintSchema {
+        global: true,
+        ..constraint("diagonal", &[choice("which", &["both", "main", "anti"], "both")])
+    },
+    global("kropki_negative"),
+    global("xv_negative"),
+];
+
+/// Every `type` accepted by `constraints_from_json`.
+pub fn constraint_types() -> Vec<&'static str> {
+    CONSTRAINTS.iter().map(|c| c.kind).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::CONSTRAINTS;
+    use crate::constraints_from_json;
+
+    /// A minimal valid constraint of every type, so a type in the schema
+    /// that `constraints_from_json` doesn't accept fails here.
+    fn examples() -> Vec<Value> {
+        vec![
+            json!({ "type": "kropki_white", "a": [0, 0], "b": [0, 1] }),
+            json!({ "type": "kropki_black", "a": [0, 0], "b": [1, 0] }),
+            json!({ "type": "thermo", "path": [[0, 0], [0, 1], [0, 2]] }),
+            json!({ "type": "arrow", "path": [[0, 0], [0, 1], [0, 2]] }),
+            json!({ "type": "renban", "path": [[1, 0], [1, 1], [1, 2]] }),
+            json!({ "type": "whisper", "path": [[2, 0], [2, 1]] }),
+            json!({ "type": "palindrome", "path": [[3, 0], [3, 1]] }),
+            json!({ "type": "between", "path": [[4, 0], [4, 1], [4, 2]] }),
+            json!({ "type": "killer", "cells": [[5, 0], [5, 1]], "sum": 10 }),
+            json!({ "type": "little_killer", "cell": [0, 0], "direction": "down_right", "sum": 45 }),
+            json!({ "type": "sandwich", "row": 0, "sum": 10 }),
+            json!({ "type": "quadruple", "cell": [6, 6], "digits": [1, 2] }),
+            json!({ "type": "color_region", "cells": [[7, 0], [7, 1]], "color": "#ffcc00" }),
+            json!({ "type": "king" }),
+            json!({ "type": "knight" }),
+            json!({ "type": "queen" }),
+            json!({ "type": "disjoint_groups" }),
+            json!({
+                "type": "extra_region",
+                "cells": [[0, 0], [0, 1], [0, 2], [1, 0], [1, 1], [1, 2], [2, 0], [2, 1], [2, 2]],
+            }),
+            json!({ "type": "windoku" }),
+            json!({ "type": "diagonal", "which": "main" }),
+            json!({ "type": "kropki_negative" }),
+            json!({ "type": "xv_negative" }),
+        ]
+    }
+
+    #[test]
+    fn every_schema_type_parses() {
+        let examples = examples();
+        let mut example_kinds: Vec<&str> = examples
+            .iter()
+            .map(|example| example["type"].as_str().unwrap())
+            .collect();
+        let mut schema_kinds: Vec<&str> = CONSTRAINTS.iter().map(|c| c.kind).collect();
+        example_kinds.sort_unstable();
+        schema_kinds.sort_unstable();
+        assert_eq!(example_kinds, schema_kinds);
+
+        for example in examples {
+            let parsed = constraints_from_json(std::slice::from_ref(&example));
+            assert!(parsed.is_ok(), "{example}: {:?}", parsed.err());
+        }
+    }
+
+    #[test]
+    fn unknown_types_are_rejected() {
+        let err = constraints_from_json(&[json!({ "type": "kropki" })]).err();
+        assert_eq!(err.as_deref(), Some("unknown constraint type: kropki"));
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+use crate::config::Config;
+
+/// Prefix of every signed message, bumped if the message layout ever changes.
+/// Version 1 joined the fields with newlines, which titles could contain.
+const MESSAGE_PREFIX: &str = "makudoku-puzzle-v2";
+
+/// Signs public puzzle payloads so mirrors and apps can verify their origin.
+pub struct PuzzleSigner {
+    key: SigningKey,
+    key_id: String,
+}
+
+impl PuzzleSigner {
+    /// Builds the signer from config, or `None` when no signing key is configured.
+    pub fn from_config(config: &Config) -> anyhow::Result<Option<Self>> {
+        let Some(encoded) = config.signing_key.as_deref() else {
+            return Ok(None);
+        };
+        let bytes = BASE64
+            .decode(encoded)
+            .context("MAKUDOKU_SIGNING_KEY must be base64")?;
+        let Ok(secret) = <[u8; 32]>::try_from(bytes.as_slice()) else {
+            bail!("MAKUDOKU_SIGNING_KEY must decode to 32 bytes");
+        };
+        let key = SigningKey::from_bytes(&secret);
+        let key_id = config.signing_key_id.clone().unwrap_or_else(|| {
+            key.verifying_key().as_bytes()[..8]
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect()
+        });
+        Ok(Some(Self { key, key_id }))
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn public_key_base64(&self) -> String {
+        BASE64.encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Returns the base64 signature over the JSON-encoded puzzle fields
+    /// (see `signed_message`).
+    pub fn sign_puzzle(
+        &self,
+        date_utc: Option<&str>,
+        title: Option<&str>,
+        variants: &[String],
+        svg: Option<&str>,
+    ) -> String {
+        self.sign(&signed_message(date_utc, title, variants, svg))
+    }
+
+    /// Returns the base64 signature over `message`.
+    pub fn sign(&self, message: &str) -> String {
+        BASE64.encode(self.key.sign(message.as_bytes()).to_bytes())
+    }
+}
+
+/// Checks a base64 `signature` over `message` against a base64 Ed25519
+/// public key. Malformed keys or signatures don't verify.
+pub fn verify(public_key: &str, message: &str, signature: &str) -> bool {
+    let key = BASE64
+        .decode(public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+    let signature = BASE64
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok());
+    match (key, signature) {
+        (Some(key), Some(signature)) => key.verify(message.as_bytes(), &signature).is_ok(),
+        _ => false,
+    }
+}
+
+/// `makudoku-puzzle-v2\n[<date_utc>, <title>, [<variants>...], <svg>]`, the
+/// fields as a compact JSON array with missing ones as `null`, so no field
+/// can run into the next.
+fn signed_message(
+    date_utc: Option<&str>,
+    title: Option<&str>,
+    variants: &[String],
+    svg: Option<&str>,
+) -> String {
+    let fields = serde_json::json!([date_utc, title, variants, svg]);
+    format!("{MESSAGE_PREFIX}\n{fields}")
+}
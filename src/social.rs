@@ -0,0 +1,385 @@
+use anyhow::{Context, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{SecondsFormat, Utc};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::{sync::Arc, time::Duration};
+
+use crate::{branding::Branding, config::Config, feed::escape, store};
+
+const DEFAULT_TEMPLATE: &str = "{title}\n{variants}\n\n{link}";
+
+/// Preview images are rendered at this width, keeping the SVG's aspect ratio.
+pub const IMAGE_WIDTH: u32 = 1200;
+
+/// Layout of the post image: the puzzle on white, above a band in the
+/// accent color carrying the site name.
+const CARD_PADDING: f32 = 40.0;
+const CARD_GRID: f32 = 1000.0;
+const CARD_BAND: f32 = 110.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mastodon,
+    Bluesky,
+}
+
+impl Network {
+    pub const ALL: [Network; 2] = [Network::Mastodon, Network::Bluesky];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mastodon => "mastodon",
+            Network::Bluesky => "bluesky",
+        }
+    }
+
+    pub fn is_configured(self, config: &Config) -> bool {
+        match self {
+            Network::Mastodon => config.mastodon_url.is_some() && config.mastodon_token.is_some(),
+            Network::Bluesky => {
+                config.bluesky_handle.is_some() && config.bluesky_app_password.is_some()
+            }
+        }
+    }
+
+    fn template(self, config: &Config) -> &str {
+        let template = match self {
+            Network::Mastodon => config.mastodon_template.as_deref(),
+            Network::Bluesky => config.bluesky_template.as_deref(),
+        };
+        template.unwrap_or(DEFAULT_TEMPLATE)
+    }
+}
+
+/// The published fields an announcement is built from.
+pub struct Announcement {
+    pub date_utc: String,
+    pub title: Option<String>,
+    pub variants: Vec<String>,
+    pub svg: String,
+    pub branding: Branding,
+}
+
+impl Announcement {
+    pub async fn load(db: &SqlitePool, date_utc: &str) -> anyhow::Result<Option<Self>> {
+        let Some(row) = store::get_puzzle(db, date_utc).await? else {
+            return Ok(None);
+        };
+
+        let svg = crate::ensure_svg(db, date_utc, row.svg, &row.puzzle_json)
+            .await
+            .map_err(anyhow::Error::msg)?;
+        Ok(Some(Self {
+            date_utc: date_utc.to_string(),
+            title: row.title,
+            variants: serde_json::from_str(row.variants.as_deref().unwrap_or("[]"))
+                .unwrap_or_default(),
+            svg,
+            branding: crate::branding::load_or_default(db).await,
+        }))
+    }
+
+    fn title(&self) -> String {
+        self.title
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.branding.site_name, self.date_utc))
+    }
+
+    /// Fills `{title}`, `{date}`, `{variants}` and `{link}` in the network's
+    /// template.
+    pub fn text(&self, config: &Config, network: Network) -> String {
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| v.replace('_', " "))
+            .collect::<Vec<_>>()
+            .join(", ");
+        network
+            .template(config)
+            .replace("{title}", &self.title())
+            .replace("{date}", &self.date_utc)
+            .replace("{variants}", &variants)
+            .replace("{link}", config.public_url.as_deref().unwrap_or_default())
+    }
+
+    fn alt_text(&self) -> String {
+        format!("Sudoku grid for {}", self.title())
+    }
+
+    /// The SVG of the image attached to posts: the puzzle with the site
+    /// name below it in the instance's colors.
+    pub fn card_svg(&self) -> String {
+        let width = CARD_GRID + 2.0 * CARD_PADDING;
+        let band_top = width;
+        let height = band_top + CARD_BAND;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif"><rect width="100%" height="100%" fill="#fff"/><image x="{CARD_PADDING}" y="{CARD_PADDING}" width="{CARD_GRID}" height="{CARD_GRID}" xlink:href="data:image/svg+xml;base64,{}"/><rect x="0" y="{band_top}" width="{width}" height="{CARD_BAND}" fill="{}"/><text x="{}" y="{}" font-size="48" font-weight="bold" text-anchor="middle" fill="{}">{}</text></svg>"##,
+            BASE64.encode(&self.svg),
+            self.branding.accent_color,
+            width / 2.0,
+            band_top + CARD_BAND / 2.0 + 17.0,
+            self.branding.accent_text_color,
+            escape(&self.branding.site_name)
+        )
+    }
+}
+
+/// Rasterizes the puzzle SVG at `width` pixels, since neither network
+/// accepts SVG uploads.
+pub fn render_png(svg: &str, width: u32) -> anyhow::Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options).context("invalid svg")?;
+
+    let size = tree.size();
+    let scale = width as f32 / size.width();
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).context("image too large")?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().context("failed to encode png")
+}
+
+/// Posts the puzzle on every configured network it hasn't been posted to
+/// yet, recording each post in `social_posts`.
+pub async fn announce(db: &SqlitePool, config: &Config, date_utc: &str) -> anyhow::Result<()> {
+    let networks: Vec<Network> = Network::ALL
+        .into_iter()
+        .filter(|n| n.is_configured(config))
+        .collect();
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    let Some(puzzle) = Announcement::load(db, date_utc).await? else {
+        bail!("puzzle {date_utc} not found");
+    };
+    let card = puzzle.card_svg();
+    let png = tokio::task::spawn_blocking(move || render_png(&card, IMAGE_WIDTH))
+        .await
+        .context("render task failed")??;
+    let client = reqwest::Client::new();
+
+    for network in networks {
+        let name = network.name();
+        // Claimed before posting, so an announcement running at the same
+        // time, e.g. the daily loop and a publish, skips it.
+        if !store::claim_social_post(db, date_utc, name).await? {
+            continue;
+        }
+
+        let text = puzzle.text(config, network);
+        let alt = puzzle.alt_text();
+        let result = match network {
+            Network::Mastodon => post_mastodon(&client, config, &text, &png, &alt).await,
+            Network::Bluesky => post_bluesky(&client, config, &text, &png, &alt).await,
+        };
+        match result {
+            Ok(post_url) => {
+                if let Some(post_url) = post_url {
+                    store::set_social_post_url(db, date_utc, name, &post_url).await?;
+                }
+                tracing::info!("posted {date_utc} to {name}");
+            }
+            Err(e) => {
+                tracing::warn!("posting {date_utc} to {name} failed: {e:#}");
+                store::release_social_post(db, date_utc, name).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Announces today's puzzle once it is published, for puzzles scheduled
+/// ahead of their date.
+pub async fn run_daily_loop(db: SqlitePool, config: Arc<Config>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(15 * 60));
+    loop {
+        ticker.tick().await;
+        let today = Utc::now().date_naive().to_string();
+        match store::puzzle_status(&db, &today).await {
+            Ok(Some(status)) if status == "published" => {
+                if let Err(e) = announce(&db, &config, &today).await {
+                    tracing::warn!("social announcement failed: {e:#}");
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("social announcement failed: {e}"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MastodonMedia {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct MastodonStatus {
+    url: Option<String>,
+}
+
+async fn post_mastodon(
+    client: &reqwest::Client,
+    config: &Config,
+    text: &str,
+    png: &[u8],
+    alt: &str,
+) -> anyhow::Result<Option<String>> {
+    let (Some(base), Some(token)) = (&config.mastodon_url, &config.mastodon_token) else {
+        bail!("mastodon is not configured");
+    };
+    let base = base.trim_end_matches('/');
+
+    let file = reqwest::multipart::Part::bytes(png.to_vec())
+        .file_name("puzzle.png")
+        .mime_str("image/png")?;
+    let form = reqwest::multipart::Form::new()
+        .part("file", file)
+        .text("description", alt.to_string());
+    let media: MastodonMedia = client
+        .post(format!("{base}/api/v2/media"))
+        .bearer_auth(token)
+        .multipart(form)
+        .send()
+        .await
+        .context("media upload failed")?
+        .error_for_status()
+        .context("media upload rejected")?
+        .json()
+        .await?;
+
+    let status: MastodonStatus = client
+        .post(format!("{base}/api/v1/statuses"))
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "status": text,
+            "media_ids": [media.id],
+        }))
+        .send()
+        .await
+        .context("status request failed")?
+        .error_for_status()
+        .context("status rejected")?
+        .json()
+        .await?;
+    Ok(status.url)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlueskySession {
+    access_jwt: String,
+    did: String,
+}
+
+#[derive(Deserialize)]
+struct BlueskyBlob {
+    blob: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct BlueskyRecord {
+    uri: String,
+}
+
+#[derive(Serialize)]
+struct BlueskyFacet {
+    index: BlueskyByteSlice,
+    features: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlueskyByteSlice {
+    byte_start: usize,
+    byte_end: usize,
+}
+
+async fn post_bluesky(
+    client: &reqwest::Client,
+    config: &Config,
+    text: &str,
+    png: &[u8],
+    alt: &str,
+) -> anyhow::Result<Option<String>> {
+    let (Some(handle), Some(password)) = (&config.bluesky_handle, &config.bluesky_app_password)
+    else {
+        bail!("bluesky is not configured");
+    };
+    let base = config.bluesky_service.trim_end_matches('/');
+
+    let session: BlueskySession = client
+        .post(format!("{base}/xrpc/com.atproto.server.createSession"))
+        .json(&serde_json::json!({ "identifier": handle, "password": password }))
+        .send()
+        .await
+        .context("login request failed")?
+        .error_for_status()
+        .context("login rejected")?
+        .json()
+        .await?;
+
+    let blob: BlueskyBlob = client
+        .post(format!("{base}/xrpc/com.atproto.repo.uploadBlob"))
+        .bearer_auth(&session.access_jwt)
+        .header(reqwest::header::CONTENT_TYPE, "image/png")
+        .body(png.to_vec())
+        .send()
+        .await
+        .context("image upload failed")?
+        .error_for_status()
+        .context("image upload rejected")?
+        .json()
+        .await?;
+
+    // Bluesky only links URLs that are marked up as facets.
+    let facets: Vec<BlueskyFacet> = config
+        .public_url
+        .as_deref()
+        .and_then(|link| text.find(link).map(|start| (link, start)))
+        .map(|(link, start)| BlueskyFacet {
+            index: BlueskyByteSlice {
+                byte_start: start,
+                byte_end: start + link.len(),
+            },
+            features: vec![serde_json::json!({
+                "$type": "app.bsky.richtext.facet#link",
+                "uri": link,
+            })],
+        })
+        .into_iter()
+        .collect();
+
+    let record: BlueskyRecord = client
+        .post(format!("{base}/xrpc/com.atproto.repo.createRecord"))
+        .bearer_auth(&session.access_jwt)
+        .json(&serde_json::json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": {
+                "$type": "app.bsky.feed.post",
+                "text": text,
+                "facets": facets,
+                "createdAt": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                "embed": {
+                    "$type": "app.bsky.embed.images",
+                    "images": [{ "alt": alt, "image": blob.blob }],
+                },
+            },
+        }))
+        .send()
+        .await
+        .context("post request failed")?
+        .error_for_status()
+        .context("post rejected")?
+        .json()
+        .await?;
+    Ok(Some(record.uri))
+}
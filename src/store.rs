@@ -0,0 +1,1463 @@
+use chrono::{SecondsFormat, Utc};
+use futures_util::stream::BoxStream;
+use sqlx::{Acquire, Sqlite, SqlitePool};
+
+use crate::bundle::BundlePuzzle;
+
+/// A `puzzles` row as the admin API shows it.
+pub struct PuzzleRow {
+    pub date_utc: String,
+    pub status: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    /// JSON array of variant names.
+    pub variants: Option<String>,
+    pub difficulty: Option<i64>,
+    pub created_at_utc: String,
+    pub updated_at_utc: String,
+    pub published_at_utc: Option<String>,
+    pub deleted_at_utc: Option<String>,
+    /// Set on puzzles imported from another instance.
+    pub origin_json: Option<String>,
+}
+
+pub async fn get_puzzle(db: &SqlitePool, date_utc: &str) -> Result<Option<PuzzleRow>, sqlx::Error> {
+    sqlx::query_as!(
+        PuzzleRow,
+        r#"
+        SELECT date_utc AS "date_utc!", status, title, author, puzzle_json, svg, variants,
+               difficulty, created_at_utc, updated_at_utc, published_at_utc, deleted_at_utc,
+               origin_json
+        FROM puzzles
+        WHERE date_utc = ?
+        "#,
+        date_utc
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// The columns `upsert_puzzle` sets. Everything else keeps its default on
+/// insert and its value on replace.
+pub struct PuzzleWrite<'a> {
+    pub date_utc: &'a str,
+    pub status: &'a str,
+    pub puzzle_json: &'a str,
+    pub svg: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub author: Option<&'a str>,
+    pub difficulty: Option<i64>,
+    /// JSON array of variant names.
+    pub variants_json: &'a str,
+    pub published_at_utc: Option<&'a str>,
+    pub engine_version: Option<&'a str>,
+    pub extra_json: Option<&'a str>,
+}
+
+/// Inserts a puzzle, or replaces the one on its date when `overwrite` is
+/// set. A replaced puzzle loses its thumbnail and origin, and is no longer
+/// deleted.
+/// Returns whether a row was written.
+pub async fn upsert_puzzle(
+    db: &SqlitePool,
+    puzzle: &PuzzleWrite<'_>,
+    overwrite: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO puzzles (
+            date_utc, status, puzzle_json, svg, render_version,
+            title, author, difficulty, variants, published_at_utc, engine_version,
+            extra_json
+        )
+        VALUES (?, ?, ?, ?, 1, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(date_utc) DO UPDATE SET
+            status = excluded.status,
+            puzzle_json = excluded.puzzle_json,
+            svg = excluded.svg,
+            thumbnail_png = NULL,
+            render_version = excluded.render_version,
+            title = excluded.title,
+            author = excluded.author,
+            difficulty = excluded.difficulty,
+            variants = excluded.variants,
+            published_at_utc = excluded.published_at_utc,
+            engine_version = excluded.engine_version,
+            extra_json = excluded.extra_json,
+            deleted_at_utc = NULL,
+            origin_json = NULL
+        WHERE ?
+        "#,
+        puzzle.date_utc,
+        puzzle.status,
+        puzzle.puzzle_json,
+        puzzle.svg,
+        puzzle.title,
+        puzzle.author,
+        puzzle.difficulty,
+        puzzle.variants_json,
+        puzzle.published_at_utc,
+        puzzle.engine_version,
+        puzzle.extra_json,
+        overwrite,
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records where an imported puzzle came from.
+pub async fn set_puzzle_origin(
+    db: &SqlitePool,
+    date_utc: &str,
+    origin_json: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET origin_json = ? WHERE date_utc = ?"#,
+        origin_json,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// One admin list entry, with its stats when the puzzle has any.
+pub struct PuzzleSummaryRow {
+    pub date_utc: String,
+    pub status: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub variants: Option<String>,
+    pub difficulty: Option<i64>,
+    pub created_at_utc: String,
+    pub published_at_utc: Option<String>,
+    pub deleted_at_utc: Option<String>,
+    pub views: Option<i64>,
+    pub checks: Option<i64>,
+    pub solves: Option<i64>,
+}
+
+/// Puzzles newest first, optionally of one status. Deleted puzzles are
+/// left out unless `include_deleted` is set.
+pub async fn list_puzzles(
+    db: &SqlitePool,
+    status: Option<&str>,
+    include_deleted: bool,
+) -> Result<Vec<PuzzleSummaryRow>, sqlx::Error> {
+    sqlx::query_as!(
+        PuzzleSummaryRow,
+        r#"
+        SELECT p.date_utc AS "date_utc!", p.status, p.title, p.author, p.variants,
+               p.difficulty, p.created_at_utc, p.published_at_utc, p.deleted_at_utc,
+               s.views AS "views?: i64", s.checks AS "checks?: i64",
+               s.solves AS "solves?: i64"
+        FROM puzzles p
+        LEFT JOIN puzzle_stats s ON s.date_utc = p.date_utc
+        WHERE (? IS NULL OR p.status = ?)
+          AND (? OR p.deleted_at_utc IS NULL)
+        ORDER BY p.date_utc DESC
+        "#,
+        status,
+        status,
+        include_deleted
+    )
+    .fetch_all(db)
+    .await
+}
+
+#[derive(Clone, Copy)]
+pub enum Stat {
+    View,
+    Check,
+    Solve,
+}
+
+/// Adds one to a puzzle counter and to its bucket for the current hour,
+/// creating either row on first use. Takes a pool or a connection already
+/// in a transaction.
+pub async fn bump_stat<'a, A>(db: A, date_utc: &str, stat: Stat) -> Result<(), sqlx::Error>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let now = Utc::now();
+    let now_value = now.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let hour_utc = now.format("%Y-%m-%dT%H:00:00Z").to_string();
+    let mut tx = db.begin().await?;
+    let (total, hourly) = match stat {
+        Stat::View => (
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats (date_utc, views, last_seen_utc)
+                VALUES (?, 1, ?)
+                ON CONFLICT(date_utc) DO UPDATE SET
+                    views = views + 1,
+                    last_seen_utc = excluded.last_seen_utc
+                "#,
+                date_utc,
+                now_value,
+            ),
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats_hourly (date_utc, hour_utc, views)
+                VALUES (?, ?, 1)
+                ON CONFLICT(date_utc, hour_utc) DO UPDATE SET views = views + 1
+                "#,
+                date_utc,
+                hour_utc,
+            ),
+        ),
+        Stat::Check => (
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats (date_utc, checks, last_seen_utc)
+                VALUES (?, 1, ?)
+                ON CONFLICT(date_utc) DO UPDATE SET
+                    checks = checks + 1,
+                    last_seen_utc = excluded.last_seen_utc
+                "#,
+                date_utc,
+                now_value,
+            ),
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats_hourly (date_utc, hour_utc, checks)
+                VALUES (?, ?, 1)
+                ON CONFLICT(date_utc, hour_utc) DO UPDATE SET checks = checks + 1
+                "#,
+                date_utc,
+                hour_utc,
+            ),
+        ),
+        Stat::Solve => (
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats (date_utc, solves, last_seen_utc)
+                VALUES (?, 1, ?)
+                ON CONFLICT(date_utc) DO UPDATE SET
+                    solves = solves + 1,
+                    last_seen_utc = excluded.last_seen_utc
+                "#,
+                date_utc,
+                now_value,
+            ),
+            sqlx::query!(
+                r#"
+                INSERT INTO puzzle_stats_hourly (date_utc, hour_utc, solves)
+                VALUES (?, ?, 1)
+                ON CONFLICT(date_utc, hour_utc) DO UPDATE SET solves = solves + 1
+                "#,
+                date_utc,
+                hour_utc,
+            ),
+        ),
+    };
+    total.execute(&mut *tx).await?;
+    hourly.execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+pub struct HourlyStats {
+    pub hour_utc: String,
+    pub views: i64,
+    pub checks: i64,
+    pub solves: i64,
+}
+
+/// Hours with any activity on a puzzle, oldest first.
+pub async fn hourly_stats(
+    db: &SqlitePool,
+    date_utc: &str,
+) -> Result<Vec<HourlyStats>, sqlx::Error> {
+    sqlx::query_as!(
+        HourlyStats,
+        r#"
+        SELECT hour_utc, views, checks, solves
+        FROM puzzle_stats_hourly
+        WHERE date_utc = ?
+        ORDER BY hour_utc
+        "#,
+        date_utc
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub struct UnratedPuzzle {
+    pub date_utc: String,
+    pub puzzle_json: String,
+}
+
+/// Puzzles without a difficulty, in date order.
+pub async fn unrated_puzzles(db: &SqlitePool) -> Result<Vec<UnratedPuzzle>, sqlx::Error> {
+    sqlx::query_as!(
+        UnratedPuzzle,
+        r#"
+        SELECT date_utc AS "date_utc!", puzzle_json
+        FROM puzzles
+        WHERE difficulty IS NULL
+        ORDER BY date_utc
+        "#
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Sets a difficulty unless one was set in the meantime.
+pub async fn set_missing_difficulty(
+    db: &SqlitePool,
+    date_utc: &str,
+    difficulty: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET difficulty = ? WHERE date_utc = ? AND difficulty IS NULL"#,
+        difficulty,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The JSON stored under a `settings` key.
+pub async fn get_setting(db: &SqlitePool, key: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT value_json FROM settings WHERE key = ?"#, key)
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn set_setting(db: &SqlitePool, key: &str, value_json: &str) -> Result<(), sqlx::Error> {
+    let now = crate::now_utc_string();
+    sqlx::query!(
+        r#"
+        INSERT INTO settings (key, value_json, updated_at_utc)
+        VALUES (?, ?, ?)
+        ON CONFLICT(key) DO UPDATE SET
+            value_json = excluded.value_json,
+            updated_at_utc = excluded.updated_at_utc
+        "#,
+        key,
+        value_json,
+        now
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Deletes everything kept about one player session. Returns the number of
+/// solve records removed; the aggregate stats they counted towards stay.
+pub async fn delete_session_data(db: &SqlitePool, session: &str) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query!(r#"DELETE FROM solve_records WHERE session = ?"#, session)
+        .execute(db)
+        .await?
+        .rows_affected();
+    Ok(deleted)
+}
+
+/// Deletes solve records made before `cutoff_utc`.
+pub async fn delete_solve_records_before(
+    db: &SqlitePool,
+    cutoff_utc: &str,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"DELETE FROM solve_records WHERE solved_at_utc < ?"#,
+        cutoff_utc
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(deleted)
+}
+
+/// Keeps a served random puzzle so it can be checked later.
+pub async fn insert_random_puzzle(
+    db: &SqlitePool,
+    id: &str,
+    puzzle_json: &str,
+) -> Result<(), sqlx::Error> {
+    let now = crate::now_utc_string();
+    sqlx::query!(
+        r#"INSERT INTO random_puzzles (id, puzzle_json, created_at_utc) VALUES (?, ?, ?)"#,
+        id,
+        puzzle_json,
+        now
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The puzzle_json of a random puzzle served at or after `since_utc`.
+pub async fn get_random_puzzle(
+    db: &SqlitePool,
+    id: &str,
+    since_utc: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT puzzle_json FROM random_puzzles WHERE id = ? AND created_at_utc >= ?"#,
+        id,
+        since_utc
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Deletes random puzzles served before `cutoff_utc`.
+pub async fn delete_random_puzzles_before(
+    db: &SqlitePool,
+    cutoff_utc: &str,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"DELETE FROM random_puzzles WHERE created_at_utc < ?"#,
+        cutoff_utc
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+    Ok(deleted)
+}
+
+/// Whether a puzzle exists on `date_utc`, deleted or not.
+pub async fn puzzle_exists(db: &SqlitePool, date_utc: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query_scalar!(
+        r#"SELECT date_utc AS "date_utc!" FROM puzzles WHERE date_utc = ?"#,
+        date_utc
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// The status of the puzzle on `date_utc`, unless there is none or it is
+/// deleted.
+pub async fn puzzle_status(db: &SqlitePool, date_utc: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT status FROM puzzles WHERE date_utc = ? AND deleted_at_utc IS NULL"#,
+        date_utc
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Every puzzle date, deleted ones included.
+pub async fn puzzle_dates(db: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT date_utc AS "date_utc!" FROM puzzles"#)
+        .fetch_all(db)
+        .await
+}
+
+/// Dates after `after` that have a draft or published puzzle.
+pub async fn assigned_dates_after(
+    db: &SqlitePool,
+    after: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT date_utc AS "date_utc!"
+        FROM puzzles
+        WHERE date_utc > ? AND status IN ('draft', 'published') AND deleted_at_utc IS NULL
+        "#,
+        after
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// What the public endpoints read of a puzzle.
+pub struct PublicPuzzleRow {
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    pub title: Option<String>,
+    /// JSON array of variant names.
+    pub variants: Option<String>,
+    pub difficulty: Option<i64>,
+}
+
+/// A puzzle players can see: published, or also archived with
+/// `include_archived`, and not deleted. `up_to` leaves out puzzles dated
+/// after it.
+pub async fn get_public_puzzle(
+    db: &SqlitePool,
+    date_utc: &str,
+    include_archived: bool,
+    up_to: Option<&str>,
+) -> Result<Option<PublicPuzzleRow>, sqlx::Error> {
+    sqlx::query_as!(
+        PublicPuzzleRow,
+        r#"
+        SELECT puzzle_json, svg, title, variants, difficulty
+        FROM puzzles
+        WHERE date_utc = ? AND (status = 'published' OR (? AND status = 'archived'))
+          AND (? IS NULL OR date_utc <= ?)
+          AND deleted_at_utc IS NULL
+        "#,
+        date_utc,
+        include_archived,
+        up_to,
+        up_to
+    )
+    .fetch_optional(db)
+    .await
+}
+
+pub struct ThumbnailRow {
+    pub svg: Option<String>,
+    pub thumbnail_png: Option<Vec<u8>>,
+    pub puzzle_json: String,
+}
+
+/// The stored thumbnail of a published or archived puzzle up to `up_to`,
+/// with what it is rendered from when there is none yet.
+pub async fn get_public_thumbnail(
+    db: &SqlitePool,
+    date_utc: &str,
+    up_to: &str,
+) -> Result<Option<ThumbnailRow>, sqlx::Error> {
+    sqlx::query_as!(
+        ThumbnailRow,
+        r#"
+        SELECT svg, thumbnail_png, puzzle_json
+        FROM puzzles
+        WHERE date_utc = ? AND status IN ('published', 'archived') AND date_utc <= ?
+          AND deleted_at_utc IS NULL
+        "#,
+        date_utc,
+        up_to
+    )
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn set_thumbnail(db: &SqlitePool, date_utc: &str, png: &[u8]) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET thumbnail_png = ? WHERE date_utc = ?"#,
+        png,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Stores a rendered SVG unless one was stored in the meantime.
+pub async fn set_missing_svg(
+    db: &SqlitePool,
+    date_utc: &str,
+    svg: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET svg = ? WHERE date_utc = ? AND svg IS NULL"#,
+        svg,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Replaces a puzzle's puzzle_json unless it changed since `previous_json`
+/// was read.
+pub async fn replace_puzzle_json(
+    db: &SqlitePool,
+    date_utc: &str,
+    puzzle_json: &str,
+    previous_json: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET puzzle_json = ? WHERE date_utc = ? AND puzzle_json = ?"#,
+        puzzle_json,
+        date_utc,
+        previous_json
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The date a slug points to, once that puzzle is published and its date
+/// is no later than `up_to`.
+pub async fn public_slug_date(
+    db: &SqlitePool,
+    slug: &str,
+    up_to: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT s.date_utc
+        FROM slugs s
+        JOIN puzzles p ON p.date_utc = s.date_utc
+        WHERE s.slug = ? AND p.status = 'published' AND p.date_utc <= ?
+          AND p.deleted_at_utc IS NULL
+        "#,
+        slug,
+        up_to
+    )
+    .fetch_optional(db)
+    .await
+}
+
+pub struct SlugRow {
+    pub slug: String,
+    pub date_utc: String,
+    pub created_at_utc: String,
+}
+
+/// Every slug, newest puzzle first.
+pub async fn list_slugs(db: &SqlitePool) -> Result<Vec<SlugRow>, sqlx::Error> {
+    sqlx::query_as!(
+        SlugRow,
+        r#"
+        SELECT slug AS "slug!", date_utc, created_at_utc
+        FROM slugs
+        ORDER BY date_utc DESC, slug
+        "#
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Points a slug at a date, moving it if it already names another one.
+pub async fn save_slug(
+    db: &SqlitePool,
+    slug: &str,
+    date_utc: &str,
+    created_at_utc: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO slugs (slug, date_utc, created_at_utc)
+        VALUES (?, ?, ?)
+        ON CONFLICT(slug) DO UPDATE SET date_utc = excluded.date_utc
+        "#,
+        slug,
+        date_utc,
+        created_at_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Returns whether the slug existed.
+pub async fn delete_slug(db: &SqlitePool, slug: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(r#"DELETE FROM slugs WHERE slug = ?"#, slug)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A published puzzle as mirrors receive it.
+pub struct SyncRow {
+    pub date_utc: String,
+    pub status: String,
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub variants: Option<String>,
+    pub difficulty: Option<i64>,
+    pub published_at_utc: Option<String>,
+    pub updated_at_utc: String,
+    pub deleted_at_utc: Option<String>,
+}
+
+/// Puzzles updated after `since`, deleted or not and of any status, in
+/// update order. Without `since`, only the published ones, since a new
+/// mirror has nothing to take down.
+pub async fn changed_since(
+    db: &SqlitePool,
+    since: Option<&str>,
+) -> Result<Vec<SyncRow>, sqlx::Error> {
+    sqlx::query_as!(
+        SyncRow,
+        r#"
+        SELECT date_utc AS "date_utc!", status, puzzle_json, svg, title, author, variants,
+               difficulty, published_at_utc, updated_at_utc, deleted_at_utc
+        FROM puzzles
+        WHERE (? IS NULL AND status = 'published' AND deleted_at_utc IS NULL)
+           OR updated_at_utc > ?
+        ORDER BY updated_at_utc
+        "#,
+        since,
+        since
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Applies an upstream status change or deletion to a mirrored puzzle.
+/// Returns whether the mirror had the puzzle.
+pub async fn set_mirrored_status(
+    db: &SqlitePool,
+    date_utc: &str,
+    status: &str,
+    deleted_at_utc: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE puzzles SET status = ?, deleted_at_utc = ? WHERE date_utc = ?"#,
+        status,
+        deleted_at_utc,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub struct FeedRow {
+    pub date_utc: String,
+    pub title: Option<String>,
+    pub variants: Option<String>,
+    pub published_at_utc: Option<String>,
+}
+
+/// The `limit` newest published or archived puzzles up to `up_to`,
+/// optionally only those using `variant`.
+pub async fn feed_puzzles(
+    db: &SqlitePool,
+    up_to: &str,
+    variant: Option<&str>,
+    limit: i64,
+) -> Result<Vec<FeedRow>, sqlx::Error> {
+    sqlx::query_as!(
+        FeedRow,
+        r#"
+        SELECT p.date_utc AS "date_utc!", p.title, p.variants, p.published_at_utc
+        FROM puzzles p
+        WHERE p.status IN ('published', 'archived')
+          AND p.deleted_at_utc IS NULL
+          AND p.date_utc <= ?
+          AND (? IS NULL OR EXISTS (
+              SELECT 1 FROM json_each(p.variants) v WHERE v.value = ?
+          ))
+        ORDER BY p.date_utc DESC
+        LIMIT ?
+        "#,
+        up_to,
+        variant,
+        variant,
+        limit
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Fields the admin list ETag is derived from.
+pub struct ListFingerprint {
+    pub count: i64,
+    pub updated_at_utc: Option<String>,
+    pub last_seen_utc: Option<String>,
+}
+
+/// Count and latest changes of the puzzles `list_puzzles` would return.
+pub async fn list_fingerprint(
+    db: &SqlitePool,
+    status: Option<&str>,
+    include_deleted: bool,
+) -> Result<ListFingerprint, sqlx::Error> {
+    sqlx::query_as!(
+        ListFingerprint,
+        r#"
+        SELECT COUNT(*) AS "count!: i64",
+               MAX(p.updated_at_utc) AS "updated_at_utc?: String",
+               MAX(s.last_seen_utc) AS "last_seen_utc?: String"
+        FROM puzzles p
+        LEFT JOIN puzzle_stats s ON s.date_utc = p.date_utc
+        WHERE (? IS NULL OR p.status = ?)
+          AND (? OR p.deleted_at_utc IS NULL)
+        "#,
+        status,
+        status,
+        include_deleted
+    )
+    .fetch_one(db)
+    .await
+}
+
+pub struct ScheduleRow {
+    pub date_utc: String,
+    pub status: String,
+    pub title: Option<String>,
+}
+
+/// Puzzles dated `from` or later, in no particular order.
+pub async fn puzzles_from(db: &SqlitePool, from: &str) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduleRow,
+        r#"
+        SELECT date_utc AS "date_utc!", status, title
+        FROM puzzles
+        WHERE date_utc >= ? AND deleted_at_utc IS NULL
+        "#,
+        from
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub struct PlannedRow {
+    pub date_utc: String,
+    pub status: String,
+    pub puzzle_json: String,
+    pub variants: Option<String>,
+    pub difficulty: Option<i64>,
+}
+
+/// Puzzles from `from` to `to`, inclusive, in no particular order.
+pub async fn puzzles_between(
+    db: &SqlitePool,
+    from: &str,
+    to: &str,
+) -> Result<Vec<PlannedRow>, sqlx::Error> {
+    sqlx::query_as!(
+        PlannedRow,
+        r#"
+        SELECT date_utc AS "date_utc!", status, puzzle_json, variants, difficulty
+        FROM puzzles
+        WHERE date_utc BETWEEN ? AND ? AND deleted_at_utc IS NULL
+        "#,
+        from,
+        to
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Publishes a puzzle that isn't deleted; with `only_draft`, only if it is
+/// still a draft. Returns whether it was updated.
+pub async fn publish_puzzle(
+    db: &SqlitePool,
+    date_utc: &str,
+    published_at_utc: &str,
+    only_draft: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE puzzles
+        SET status = 'published', published_at_utc = ?
+        WHERE date_utc = ? AND (NOT ? OR status = 'draft') AND deleted_at_utc IS NULL
+        "#,
+        published_at_utc,
+        date_utc,
+        only_draft
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Archives a puzzle that isn't deleted. Returns whether it was updated.
+pub async fn archive_puzzle(db: &SqlitePool, date_utc: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE puzzles
+        SET status = 'archived'
+        WHERE date_utc = ? AND deleted_at_utc IS NULL
+        "#,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Soft-deletes a puzzle, keeping the first deletion time. Returns whether
+/// the puzzle exists.
+pub async fn delete_puzzle(
+    db: &SqlitePool,
+    date_utc: &str,
+    deleted_at_utc: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE puzzles
+        SET deleted_at_utc = COALESCE(deleted_at_utc, ?)
+        WHERE date_utc = ?
+        "#,
+        deleted_at_utc,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Undoes a soft delete. Returns whether the puzzle exists.
+pub async fn undelete_puzzle(db: &SqlitePool, date_utc: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE puzzles
+        SET deleted_at_utc = NULL
+        WHERE date_utc = ?
+        "#,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub struct EngineVersionCount {
+    pub engine_version: Option<String>,
+    pub count: i64,
+}
+
+/// How many puzzles were generated with each engine version other than
+/// `engine_version`, including none recorded.
+pub async fn other_engine_versions(
+    db: &SqlitePool,
+    engine_version: &str,
+) -> Result<Vec<EngineVersionCount>, sqlx::Error> {
+    sqlx::query_as!(
+        EngineVersionCount,
+        r#"
+        SELECT engine_version, COUNT(*) AS "count!: i64"
+        FROM puzzles
+        WHERE engine_version IS NULL OR engine_version != ?
+        GROUP BY engine_version
+        "#,
+        engine_version
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub struct ReverifyRow {
+    pub date_utc: String,
+    pub puzzle_json: String,
+    pub engine_version: Option<String>,
+}
+
+/// Puzzles not yet verified with `engine_version`, or all of them with
+/// `all`, in date order.
+pub async fn puzzles_to_reverify(
+    db: &SqlitePool,
+    all: bool,
+    engine_version: &str,
+) -> Result<Vec<ReverifyRow>, sqlx::Error> {
+    sqlx::query_as!(
+        ReverifyRow,
+        r#"
+        SELECT date_utc AS "date_utc!", puzzle_json, engine_version
+        FROM puzzles
+        WHERE ? OR engine_version IS NULL OR engine_version != ?
+        ORDER BY date_utc
+        "#,
+        all,
+        engine_version
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn set_engine_version(
+    db: &SqlitePool,
+    date_utc: &str,
+    engine_version: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE puzzles SET engine_version = ? WHERE date_utc = ?"#,
+        engine_version,
+        date_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// One row of the JSON bundle and exchange exports: a puzzle with its
+/// stats.
+pub struct ExportRow {
+    pub date_utc: String,
+    pub status: String,
+    pub puzzle_json: String,
+    pub svg: Option<String>,
+    pub render_version: i64,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub difficulty: Option<i64>,
+    pub variants: Option<String>,
+    pub engine_version: Option<String>,
+    pub extra_json: Option<String>,
+    pub created_at_utc: String,
+    pub updated_at_utc: String,
+    pub published_at_utc: Option<String>,
+    pub views: Option<i64>,
+    pub checks: Option<i64>,
+    pub solves: Option<i64>,
+}
+
+/// Puzzles that aren't deleted, optionally of one status and within
+/// inclusive date bounds, in date order and fetched one at a time.
+/// Thumbnails are left out, as they are re-rendered on request.
+pub fn export_rows<'a>(
+    db: &'a SqlitePool,
+    status: Option<&'a str>,
+    from: Option<&'a str>,
+    to: Option<&'a str>,
+) -> BoxStream<'a, Result<ExportRow, sqlx::Error>> {
+    sqlx::query_as!(
+        ExportRow,
+        r#"
+        SELECT p.date_utc AS "date_utc!", p.status, p.puzzle_json, p.svg, p.render_version,
+               p.title, p.author, p.difficulty, p.variants, p.engine_version, p.extra_json,
+               p.created_at_utc, p.updated_at_utc, p.published_at_utc,
+               s.views AS "views?: i64", s.checks AS "checks?: i64",
+               s.solves AS "solves?: i64"
+        FROM puzzles p
+        LEFT JOIN puzzle_stats s ON s.date_utc = p.date_utc
+        WHERE p.deleted_at_utc IS NULL
+          AND (? IS NULL OR p.status = ?)
+          AND (? IS NULL OR p.date_utc >= ?)
+          AND (? IS NULL OR p.date_utc <= ?)
+        ORDER BY p.date_utc
+        "#,
+        status,
+        status,
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch(db)
+}
+
+/// Writes an exported puzzle and its stats, replacing any existing row
+/// along with its timestamps.
+pub async fn restore_puzzle(db: &SqlitePool, puzzle: &BundlePuzzle) -> Result<(), sqlx::Error> {
+    let variants_json = serde_json::to_string(&puzzle.variants).unwrap_or_else(|_| "[]".into());
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO puzzles (
+            date_utc, status, puzzle_json, svg, render_version,
+            title, author, difficulty, variants, engine_version, extra_json,
+            created_at_utc, updated_at_utc, published_at_utc
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(date_utc) DO UPDATE SET
+            status = excluded.status,
+            puzzle_json = excluded.puzzle_json,
+            svg = excluded.svg,
+            thumbnail_png = NULL,
+            render_version = excluded.render_version,
+            title = excluded.title,
+            author = excluded.author,
+            difficulty = excluded.difficulty,
+            variants = excluded.variants,
+            engine_version = excluded.engine_version,
+            extra_json = excluded.extra_json,
+            created_at_utc = excluded.created_at_utc,
+            published_at_utc = excluded.published_at_utc,
+            deleted_at_utc = NULL
+        "#,
+        puzzle.date_utc,
+        puzzle.status,
+        puzzle.puzzle_json,
+        puzzle.svg,
+        puzzle.render_version,
+        puzzle.title,
+        puzzle.author,
+        puzzle.difficulty,
+        variants_json,
+        puzzle.engine_version,
+        puzzle.extra_json,
+        puzzle.created_at_utc,
+        puzzle.updated_at_utc,
+        puzzle.published_at_utc,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(stats) = &puzzle.stats {
+        sqlx::query!(
+            r#"
+            INSERT INTO puzzle_stats (date_utc, views, checks, solves)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(date_utc) DO UPDATE SET
+                views = excluded.views,
+                checks = excluded.checks,
+                solves = excluded.solves
+            "#,
+            puzzle.date_utc,
+            stats.views,
+            stats.checks,
+            stats.solves,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+pub struct PuzzleStats {
+    pub date_utc: String,
+    pub views: i64,
+    pub checks: i64,
+    pub solves: i64,
+}
+
+/// A puzzle's counters, or `None` before its first view.
+pub async fn get_stats(
+    db: &SqlitePool,
+    date_utc: &str,
+) -> Result<Option<PuzzleStats>, sqlx::Error> {
+    sqlx::query_as!(
+        PuzzleStats,
+        r#"
+        SELECT date_utc AS "date_utc!", views, checks, solves
+        FROM puzzle_stats
+        WHERE date_utc = ?
+        "#,
+        date_utc
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Counters of the dates in `dates_json`, a JSON array; dates without
+/// stats are left out.
+pub async fn stats_for_dates(
+    db: &SqlitePool,
+    dates_json: &str,
+) -> Result<Vec<PuzzleStats>, sqlx::Error> {
+    sqlx::query_as!(
+        PuzzleStats,
+        r#"
+        SELECT date_utc AS "date_utc!", views, checks, solves
+        FROM puzzle_stats
+        WHERE date_utc IN (SELECT value FROM json_each(?))
+        "#,
+        dates_json
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub struct StatsTotals {
+    pub views: i64,
+    pub checks: i64,
+    pub solves: i64,
+}
+
+/// Summed counters of the dates from `from` to `to`, inclusive.
+pub async fn stats_totals(
+    db: &SqlitePool,
+    from: &str,
+    to: &str,
+) -> Result<StatsTotals, sqlx::Error> {
+    sqlx::query_as!(
+        StatsTotals,
+        r#"
+        SELECT COALESCE(SUM(views), 0) AS "views!: i64",
+               COALESCE(SUM(checks), 0) AS "checks!: i64",
+               COALESCE(SUM(solves), 0) AS "solves!: i64"
+        FROM puzzle_stats
+        WHERE date_utc BETWEEN ? AND ?
+        "#,
+        from,
+        to
+    )
+    .fetch_one(db)
+    .await
+}
+
+pub struct MostSolvedRow {
+    pub date_utc: String,
+    pub title: Option<String>,
+    pub solves: i64,
+}
+
+/// The most solved puzzle from `from` to `to`, inclusive, if any was
+/// solved; ties go to the later date.
+pub async fn most_solved(
+    db: &SqlitePool,
+    from: &str,
+    to: &str,
+) -> Result<Option<MostSolvedRow>, sqlx::Error> {
+    sqlx::query_as!(
+        MostSolvedRow,
+        r#"
+        SELECT s.date_utc AS "date_utc!", p.title AS "title?", s.solves
+        FROM puzzle_stats s
+        LEFT JOIN puzzles p ON p.date_utc = s.date_utc
+        WHERE s.date_utc BETWEEN ? AND ? AND s.solves > 0
+        ORDER BY s.solves DESC, s.date_utc DESC
+        LIMIT 1
+        "#,
+        from,
+        to
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// A public puzzle's counters and community goal, each `None` when unset.
+pub struct PublicStatsRow {
+    pub date_utc: String,
+    pub views: Option<i64>,
+    pub checks: Option<i64>,
+    pub solves: Option<i64>,
+    pub goal_metric: Option<String>,
+    pub goal_target: Option<i64>,
+    pub goal_label: Option<String>,
+}
+
+/// Stats of a published or archived puzzle dated no later than `up_to`.
+pub async fn public_stats(
+    db: &SqlitePool,
+    date_utc: &str,
+    up_to: &str,
+) -> Result<Option<PublicStatsRow>, sqlx::Error> {
+    sqlx::query_as!(
+        PublicStatsRow,
+        r#"
+        SELECT p.date_utc AS "date_utc!",
+               s.views AS "views?: i64", s.checks AS "checks?: i64",
+               s.solves AS "solves?: i64",
+               g.metric AS "goal_metric?: String", g.target AS "goal_target?: i64",
+               g.label AS "goal_label?: String"
+        FROM puzzles p
+        LEFT JOIN puzzle_stats s ON s.date_utc = p.date_utc
+        LEFT JOIN puzzle_goals g ON g.date_utc = p.date_utc
+        WHERE p.date_utc = ? AND p.status IN ('published', 'archived') AND p.date_utc <= ?
+          AND p.deleted_at_utc IS NULL
+        "#,
+        date_utc,
+        up_to
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Counts a solve once per session and puzzle: re-checking a finished grid
+/// leaves the counter alone. Returns whether this was a new solve.
+pub async fn record_solve(
+    db: &SqlitePool,
+    date_utc: &str,
+    session: &str,
+) -> Result<bool, sqlx::Error> {
+    let now_value = crate::now_utc_string();
+    let mut tx = db.begin().await?;
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO solve_records (date_utc, session, solved_at_utc)
+        VALUES (?, ?, ?)
+        ON CONFLICT(date_utc, session) DO NOTHING
+        "#,
+        date_utc,
+        session,
+        now_value,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if !inserted {
+        return Ok(false);
+    }
+
+    bump_stat(&mut *tx, date_utc, Stat::Solve).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+pub struct GoalRow {
+    pub metric: String,
+    pub target: i64,
+    pub label: Option<String>,
+    pub updated_at_utc: String,
+}
+
+pub async fn get_goal(db: &SqlitePool, date_utc: &str) -> Result<Option<GoalRow>, sqlx::Error> {
+    sqlx::query_as!(
+        GoalRow,
+        r#"
+        SELECT metric, target, label, updated_at_utc
+        FROM puzzle_goals
+        WHERE date_utc = ?
+        "#,
+        date_utc
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Sets or replaces the community goal of a puzzle.
+pub async fn save_goal(db: &SqlitePool, date_utc: &str, goal: &GoalRow) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO puzzle_goals (date_utc, metric, target, label, updated_at_utc)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(date_utc) DO UPDATE SET
+            metric = excluded.metric,
+            target = excluded.target,
+            label = excluded.label,
+            updated_at_utc = excluded.updated_at_utc
+        "#,
+        date_utc,
+        goal.metric,
+        goal.target,
+        goal.label,
+        goal.updated_at_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Returns whether the puzzle had a goal.
+pub async fn delete_goal(db: &SqlitePool, date_utc: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(r#"DELETE FROM puzzle_goals WHERE date_utc = ?"#, date_utc)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A tester's saved progress on a puzzle.
+pub struct TestSolveRow {
+    pub tester: String,
+    pub grid: String,
+    pub notes: Option<String>,
+    /// `pass` or `fail`, unset while in progress.
+    pub verdict: Option<String>,
+    pub updated_at_utc: String,
+}
+
+/// Test solves of a puzzle, most recently saved first.
+pub async fn list_test_solves(
+    db: &SqlitePool,
+    date_utc: &str,
+) -> Result<Vec<TestSolveRow>, sqlx::Error> {
+    sqlx::query_as!(
+        TestSolveRow,
+        r#"
+        SELECT tester, grid, notes, verdict, updated_at_utc
+        FROM test_solves
+        WHERE date_utc = ?
+        ORDER BY updated_at_utc DESC
+        "#,
+        date_utc
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Saves a tester's progress, replacing their previous save.
+pub async fn save_test_solve(
+    db: &SqlitePool,
+    date_utc: &str,
+    solve: &TestSolveRow,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO test_solves (date_utc, tester, grid, notes, verdict, updated_at_utc)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(date_utc, tester) DO UPDATE SET
+            grid = excluded.grid,
+            notes = excluded.notes,
+            verdict = excluded.verdict,
+            updated_at_utc = excluded.updated_at_utc
+        "#,
+        date_utc,
+        solve.tester,
+        solve.grid,
+        solve.notes,
+        solve.verdict,
+        solve.updated_at_utc
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub async fn has_passing_test_solve(db: &SqlitePool, date_utc: &str) -> Result<bool, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!: i64"
+        FROM test_solves
+        WHERE date_utc = ? AND verdict = 'pass'
+        "#,
+        date_utc
+    )
+    .fetch_one(db)
+    .await?;
+    Ok(count > 0)
+}
+
+/// Claims the post of `date_utc` to `network` before it is made, so two
+/// announcers can't both post. False when it was already claimed.
+pub async fn claim_social_post(
+    db: &SqlitePool,
+    date_utc: &str,
+    network: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO social_posts (date_utc, network) VALUES (?, ?)
+        ON CONFLICT (date_utc, network) DO NOTHING
+        "#,
+        date_utc,
+        network
+    )
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+pub async fn set_social_post_url(
+    db: &SqlitePool,
+    date_utc: &str,
+    network: &str,
+    post_url: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE social_posts SET post_url = ? WHERE date_utc = ? AND network = ?"#,
+        post_url,
+        date_utc,
+        network
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Drops a claim whose post failed, so the next run tries again.
+pub async fn release_social_post(
+    db: &SqlitePool,
+    date_utc: &str,
+    network: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM social_posts WHERE date_utc = ? AND network = ?"#,
+        date_utc,
+        network
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Whether the digest for the week starting `week_start` was sent.
+pub async fn digest_sent(db: &SqlitePool, week_start: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query_scalar!(
+        r#"SELECT week_start FROM digest_log WHERE week_start = ?"#,
+        week_start
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.is_some())
+}
+
+pub async fn record_digest(db: &SqlitePool, week_start: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"INSERT INTO digest_log (week_start) VALUES (?)"#,
+        week_start
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::ConstraintLabel;
+
+/// Entries are reloaded after this long even without a `clear`, which
+/// covers writers that can't reach the cache, such as mirror syncs.
+const MAX_AGE: Duration = Duration::from_secs(60);
+
+/// The parts of a published puzzle's public response that come from its
+/// row, before layers are stripped and the response is signed.
+pub struct CachedPuzzle {
+    pub svg: String,
+    pub variants: Vec<String>,
+    pub title: Option<String>,
+    pub labels: Vec<ConstraintLabel>,
+    pub custom_rules: Vec<String>,
+}
+
+/// Today's published puzzle, so `/api/puzzle/today` doesn't read the row
+/// on every request. Handlers that publish, change or remove puzzles call
+/// `clear`.
+#[derive(Default)]
+pub struct TodayCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Bumped by every `clear`, so a load that started before one doesn't
+    /// store what it read.
+    generation: u64,
+    entry: Option<Entry>,
+}
+
+struct Entry {
+    date_utc: String,
+    loaded: Instant,
+    puzzle: Arc<CachedPuzzle>,
+}
+
+impl TodayCache {
+    pub fn get(&self, date_utc: &str) -> Option<Arc<CachedPuzzle>> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .entry
+            .as_ref()
+            .filter(|entry| entry.date_utc == date_utc && entry.loaded.elapsed() < MAX_AGE)
+            .map(|entry| entry.puzzle.clone())
+    }
+
+    /// Taken before loading a puzzle and passed to `put`.
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    pub fn put(&self, generation: u64, date_utc: &str, puzzle: Arc<CachedPuzzle>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+        inner.entry = Some(Entry {
+            date_utc: date_utc.to_string(),
+            loaded: Instant::now(),
+            puzzle,
+        });
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        inner.entry = None;
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::Context;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use resvg::usvg;
+use std::fmt::Write as _;
+
+use crate::feed::escape;
+
+/// A4 portrait in PDF points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+
+/// The puzzle as rendered on the site, top right of the page.
+const PREVIEW_SIZE: f32 = 180.0;
+
+/// The enlarged grid below the header.
+const CELL_SIZE: f32 = 56.0;
+const GRID_TOP: f32 = 250.0;
+/// Side of each candidate's tick box in an empty cell.
+const TICK_SIZE: f32 = 11.0;
+
+/// Rules lines are wrapped at this many characters, and cut off after
+/// `MAX_RULE_LINES` so they stay above the grid.
+const RULE_LINE_CHARS: usize = 62;
+const MAX_RULE_LINES: usize = 9;
+
+/// What goes on a worksheet.
+pub struct Worksheet<'a> {
+    /// Heads untitled puzzles.
+    pub site_name: &'a str,
+    pub title: Option<&'a str>,
+    pub date_utc: &'a str,
+    /// The puzzle's own SVG, constraints and all.
+    pub svg: &'a str,
+    /// 81 characters, `.` or `0` for empty cells.
+    pub givens: &'a str,
+    /// Jigsaw regions; the standard boxes when unset.
+    pub regions: Option<&'a [Vec<(usize, usize)>]>,
+    pub rules: &'a [String],
+}
+
+/// Renders a one page PDF for paper solvers: the puzzle as published, and
+/// an enlarged copy of the grid whose empty cells hold a tick box per
+/// candidate for pencil marks. Blocking.
+pub fn render_pdf(sheet: &Worksheet) -> anyhow::Result<Vec<u8>> {
+    let svg = page_svg(sheet);
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(&svg, &options).context("invalid worksheet svg")?;
+    svg2pdf::to_pdf(
+        &tree,
+        svg2pdf::ConversionOptions::default(),
+        svg2pdf::PageOptions::default(),
+    )
+    .map_err(|e| anyhow::anyhow!("failed to convert worksheet to pdf: {e}"))
+}
+
+fn page_svg(sheet: &Worksheet) -> String {
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{PAGE_WIDTH}" height="{PAGE_HEIGHT}" viewBox="0 0 {PAGE_WIDTH} {PAGE_HEIGHT}" font-family="sans-serif"><rect width="100%" height="100%" fill="#fff"/>"##
+    );
+
+    let title = sheet.title.unwrap_or(sheet.site_name);
+    let _ = write!(
+        svg,
+        r##"<text x="{MARGIN}" y="{}" font-size="20" font-weight="bold">{}</text><text x="{MARGIN}" y="{}" font-size="11" fill="#555">{}</text>"##,
+        MARGIN + 20.0,
+        escape(title),
+        MARGIN + 38.0,
+        escape(sheet.date_utc)
+    );
+    for (i, line) in rule_lines(sheet.rules).iter().enumerate() {
+        let _ = write!(
+            svg,
+            r#"<text x="{MARGIN}" y="{}" font-size="9">{}</text>"#,
+            MARGIN + 62.0 + i as f32 * 12.0,
+            escape(line)
+        );
+    }
+
+    let _ = write!(
+        svg,
+        r#"<image x="{}" y="{MARGIN}" width="{PREVIEW_SIZE}" height="{PREVIEW_SIZE}" xlink:href="data:image/svg+xml;base64,{}"/>"#,
+        PAGE_WIDTH - MARGIN - PREVIEW_SIZE,
+        BASE64.encode(sheet.svg)
+    );
+
+    grid_svg(&mut svg, sheet);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Each rule as its own paragraph, wrapped at word boundaries.
+fn rule_lines(rules: &[String]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for rule in rules {
+        let mut line = String::new();
+        for word in rule.split_whitespace() {
+            if !line.is_empty() && line.len() + 1 + word.len() > RULE_LINE_CHARS {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.truncate(MAX_RULE_LINES);
+    lines
+}
+
+fn grid_svg(svg: &mut String, sheet: &Worksheet) {
+    let left = (PAGE_WIDTH - CELL_SIZE * 9.0) / 2.0;
+    let region = region_map(sheet.regions);
+    let givens: Vec<char> = sheet.givens.chars().collect();
+
+    for r in 0..9 {
+        for c in 0..9 {
+            let x = left + c as f32 * CELL_SIZE;
+            let y = GRID_TOP + r as f32 * CELL_SIZE;
+            let _ = write!(
+                svg,
+                r##"<rect x="{x}" y="{y}" width="{CELL_SIZE}" height="{CELL_SIZE}" fill="none" stroke="#000" stroke-width="0.5"/>"##
+            );
+            match givens.get(r * 9 + c).and_then(|ch| ch.to_digit(10)) {
+                Some(digit) if digit > 0 => {
+                    let _ = write!(
+                        svg,
+                        r#"<text x="{}" y="{}" font-size="32" font-weight="bold" text-anchor="middle">{digit}</text>"#,
+                        x + CELL_SIZE / 2.0,
+                        y + CELL_SIZE / 2.0 + 11.0
+                    );
+                }
+                _ => tick_boxes(svg, x, y),
+            }
+        }
+    }
+
+    // Region borders, drawn wherever neighbouring cells differ.
+    for r in 0..9 {
+        for c in 0..9 {
+            let x = left + c as f32 * CELL_SIZE;
+            let y = GRID_TOP + r as f32 * CELL_SIZE;
+            if c < 8 && region[r][c] != region[r][c + 1] {
+                thick_line(svg, x + CELL_SIZE, y, x + CELL_SIZE, y + CELL_SIZE);
+            }
+            if r < 8 && region[r][c] != region[r + 1][c] {
+                thick_line(svg, x, y + CELL_SIZE, x + CELL_SIZE, y + CELL_SIZE);
+            }
+        }
+    }
+    let _ = write!(
+        svg,
+        r##"<rect x="{left}" y="{GRID_TOP}" width="{size}" height="{size}" fill="none" stroke="#000" stroke-width="2.5"/>"##,
+        size = CELL_SIZE * 9.0
+    );
+}
+
+/// A faint box labelled with each candidate, three by three.
+fn tick_boxes(svg: &mut String, x: f32, y: f32) {
+    let step = CELL_SIZE / 3.0;
+    for digit in 1..=9 {
+        let cx = x + ((digit - 1) % 3) as f32 * step + step / 2.0;
+        let cy = y + ((digit - 1) / 3) as f32 * step + step / 2.0;
+        let _ = write!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{TICK_SIZE}" height="{TICK_SIZE}" fill="none" stroke="#bbb" stroke-width="0.5"/><text x="{cx}" y="{}" font-size="7" fill="#bbb" text-anchor="middle">{digit}</text>"##,
+            cx - TICK_SIZE / 2.0,
+            cy - TICK_SIZE / 2.0,
+            cy + 2.5
+        );
+    }
+}
+
+fn thick_line(svg: &mut String, x1: f32, y1: f32, x2: f32, y2: f32) {
+    let _ = write!(
+        svg,
+        r##"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="#000" stroke-width="2" stroke-linecap="square"/>"##
+    );
+}
+
+/// The region index of every cell.
+fn region_map(regions: Option<&[Vec<(usize, usize)>]>) -> [[usize; 9]; 9] {
+    let mut map = [[0; 9]; 9];
+    match regions {
+        Some(regions) => {
+            for (i, cells) in regions.iter().enumerate() {
+                for &(r, c) in cells {
+                    map[r][c] = i;
+                }
+            }
+        }
+        None => {
+            for (r, row) in map.iter_mut().enumerate() {
+                for (c, cell) in row.iter_mut().enumerate() {
+                    *cell = (r / 3) * 3 + c / 3;
+                }
+            }
+        }
+    }
+    map
+}